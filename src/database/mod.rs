@@ -1,2 +1,4 @@
 pub mod mongo;
-pub mod repos;
\ No newline at end of file
+pub mod repos;
+pub mod reactions;
+pub mod tagging;
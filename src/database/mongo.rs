@@ -1,15 +1,30 @@
+use std::collections::HashMap;
+
+use chrono::{ Duration, Utc };
+
+use crate::models::tag::TagKind;
+use crate::utils::trending::SCORING_WINDOW_DAYS;
 use crate::utils::error::AppError;
 use mongodb::{Client, Database};
 use crate::database::repos::announcement_repository::AnnouncementRepository;
+use crate::database::repos::bookmark_repo::BookmarkRepository;
+use crate::database::repos::character_repo::CharacterRepository;
 use crate::database::repos::codes_repo::CodeRepository;
+use crate::database::repos::hash_ban_repo::HashBanRepository;
+use crate::database::repos::collection_repo::CollectionRepository;
 use crate::database::repos::comment_replies_repo::CommentRepliesRepository;
 use crate::database::repos::comment_repo::CommentRepository;
+use crate::database::repos::media_repo::MediaIndexRepository;
+use crate::database::repos::notification_repo::NotificationRepository;
 use crate::database::repos::post_repo::PostRepository;
 use crate::database::repos::preuser_repo::PreRegisterUserRepository;
 use crate::database::repos::profile_repo::ProfileRepository;
 use crate::database::repos::reports_repo::ReportRepository;
+use crate::database::repos::revision_repo::RevisionRepository;
 use crate::database::repos::session_repo::SessionRepository;
 use crate::database::repos::settings_repo::SettingsRepository;
+use crate::database::repos::tag_repo::TagRepository;
+use crate::database::repos::upload_repo::PendingUploadRepository;
 use crate::database::repos::user_repo::UserRepository;
 
 #[derive(Clone)]
@@ -27,6 +42,15 @@ pub struct InkvaultDB {
     pub pre_user_repo: PreRegisterUserRepository,
     pub reporting: ReportRepository,
     pub announcements: AnnouncementRepository,
+    pub characters: CharacterRepository,
+    pub tags: TagRepository,
+    pub revisions: RevisionRepository,
+    pub collections: CollectionRepository,
+    pub bookmarks: BookmarkRepository,
+    pub pending_uploads: PendingUploadRepository,
+    pub media_index: MediaIndexRepository,
+    pub hash_bans: HashBanRepository,
+    pub notifications: NotificationRepository,
 }
 
 impl InkvaultDB {
@@ -51,6 +75,130 @@ impl InkvaultDB {
             pre_user_repo: PreRegisterUserRepository::new(&db),
             reporting: ReportRepository::new(&db),
             announcements: AnnouncementRepository::new(&db),
+            characters: CharacterRepository::new(&db),
+            tags: TagRepository::new(&db),
+            revisions: RevisionRepository::new(&db),
+            collections: CollectionRepository::new(&db),
+            bookmarks: BookmarkRepository::new(&db),
+            pending_uploads: PendingUploadRepository::new(&db),
+            media_index: MediaIndexRepository::new(&db),
+            hash_bans: HashBanRepository::new(&db),
+            notifications: NotificationRepository::new(&db),
         })
     }
+
+    /// Brings documents written by older versions up to date, run once on startup
+    pub async fn migrate(&self) -> Result<(), AppError> {
+        let posts = self.posts.backfill_reaction_counts().await?;
+        let comments = self.comments.backfill_reaction_counts().await?;
+        let characters = self.characters.backfill_reaction_counts().await?;
+        if posts + comments + characters > 0 {
+            log::info!(
+                "Backfilled reaction counts on {} posts, {} comments and {} characters",
+                posts,
+                comments,
+                characters
+            );
+        }
+
+        let dates = self.comments.backfill_created_at().await?;
+        if dates > 0 {
+            log::info!("Converted created_at on {} comments", dates);
+        }
+
+        // replies used to be nested in one comment_replies document per comment
+        self.comment_replies.delete_empty().await?;
+        let threads = self.comment_replies.ids_with_replies().await?;
+        for id in &threads {
+            let legacy = self.comment_replies.get_replies(id).await?;
+            let Ok(root) = self.comments.get_by_id(id).await else {
+                log::warn!("Dropping replies to missing comment {}", id);
+                self.comment_replies.delete_by_id(id).await?;
+                continue;
+            };
+
+            let top_level = legacy.replies.len() as u64;
+            for reply in legacy.into_comments(&root) {
+                self.comments.upsert(&reply).await?;
+            }
+            self.comments.set_reply_count(id, top_level).await?;
+            self.comment_replies.delete_by_id(id).await?;
+        }
+        if !threads.is_empty() {
+            log::info!("Moved the replies of {} comments into their own documents", threads.len());
+        }
+
+        // engagement used to be stored only for recent posts, the all time ranking sorts on it
+        let now = Utc::now();
+        let scored = self.posts.backfill_engagement(now - Duration::days(SCORING_WINDOW_DAYS), now).await?;
+        if scored > 0 {
+            log::info!("Scored the engagement of {} posts", scored);
+        }
+
+        let statuses = self.posts.backfill_status().await?;
+        if statuses > 0 {
+            log::info!("Marked {} existing posts as published", statuses);
+        }
+
+        // indexed files used to be released by checking every post and character, they now
+        // count their references
+        if self.media_index.has_uncounted().await? {
+            let mut counts: HashMap<String, i64> = HashMap::new();
+            let posts = self.posts.media_hash_counts().await?;
+            let characters = self.characters.media_hash_counts().await?;
+            for (hash, count) in posts.into_iter().chain(characters) {
+                *counts.entry(hash).or_default() += count;
+            }
+            let counted = self.media_index.backfill_refs(&counts).await?;
+            log::info!("Counted the references of {} indexed files", counted);
+        }
+
+        // unread notifications about the same thing are kept unique from now on
+        let duplicates = self.notifications.mark_duplicates_read().await?;
+        if duplicates > 0 {
+            log::info!("Marked {} duplicate notifications read", duplicates);
+        }
+
+        // revision numbers used to be counted from the history, the post now keeps a counter.
+        // racing edits could share a number, which the unique index won't allow
+        let renumbered = self.revisions.renumber_duplicates().await?;
+        if renumbered > 0 {
+            log::info!("Renumbered the revisions of {} posts", renumbered);
+        }
+        let mut counters = 0;
+        for (post_id, latest) in self.revisions.latest_numbers().await? {
+            counters += self.posts.backfill_revision_count(&post_id, latest).await? as u64;
+        }
+        if counters > 0 {
+            log::info!("Started revision counters on {} posts", counters);
+        }
+
+        // tags were stored as typed before they were normalized on write
+        let normalized_posts = self.posts.normalize_stored_tags().await?;
+        let normalized_characters = self.characters.normalize_stored_tags().await?;
+        if normalized_posts + normalized_characters > 0 {
+            log::info!(
+                "Normalized the tags of {} posts and {} characters",
+                normalized_posts,
+                normalized_characters
+            );
+        }
+
+        // the tag registry starts empty, fill it from the tags already in use. renamed tags
+        // leave the old counts wrong, so those are recounted too
+        if normalized_posts + normalized_characters > 0 || self.tags.is_empty().await? {
+            self.tags.reset_counts().await?;
+            let posts = self.posts.tag_counts().await?;
+            let characters = self.characters.tag_counts().await?;
+            for (name, count) in &posts {
+                self.tags.set_count(name, TagKind::Post, *count).await?;
+            }
+            for (name, count) in &characters {
+                self.tags.set_count(name, TagKind::Character, *count).await?;
+            }
+            log::info!("Registered {} post tags and {} character tags", posts.len(), characters.len());
+        }
+
+        Ok(())
+    }
 }
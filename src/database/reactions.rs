@@ -0,0 +1,144 @@
+//! Atomic like/dislike toggles shared by every collection that stores reactions as a pair
+//! of `likes`/`dislikes` user id sets next to `like_count`/`dislike_count` counters.
+
+use bson::{ doc, Document };
+use futures::TryStreamExt;
+use mongodb::Collection;
+use mongodb::options::{ FindOneAndUpdateOptions, ReturnDocument };
+use serde::de::DeserializeOwned;
+
+use crate::models::reaction::Reaction;
+use crate::utils::error::AppError;
+
+// how often a toggle is retried when other reactions keep racing it
+const MAX_ATTEMPTS: usize = 3;
+
+/// Toggles `reaction` from `user_id` on the document with `_id == id`.
+///
+/// Each step only matches when the sets are in the state it expects, so counters never drift
+/// even when the same user reacts from two places at once. Returns whether the reaction is now
+/// set and the updated document, or `None` if the document does not exist.
+pub async fn toggle<T>(
+    coll: &Collection<T>,
+    id: &str,
+    user_id: &str,
+    reaction: Reaction
+) -> Result<Option<(bool, T)>, AppError>
+    where T: DeserializeOwned + Unpin + Send + Sync
+{
+    let (own, other, own_count, other_count) = reaction.fields();
+    let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+
+    for _ in 0..MAX_ATTEMPTS {
+        // already reacted this way, undo it
+        let undone = coll
+            .find_one_and_update(
+                doc! { "_id": id, own: user_id },
+                doc! { "$pull": { own: user_id }, "$inc": { own_count: -1 } },
+                options.clone()
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        if let Some(updated) = undone {
+            return Ok(Some((false, updated)));
+        }
+
+        // reacted the other way, switch sides
+        let switched = coll
+            .find_one_and_update(
+                doc! { "_id": id, other: user_id },
+                doc! {
+                    "$pull": { other: user_id },
+                    "$addToSet": { own: user_id },
+                    "$inc": { own_count: 1, other_count: -1 }
+                },
+                options.clone()
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        if let Some(updated) = switched {
+            return Ok(Some((true, updated)));
+        }
+
+        // no reaction yet
+        let added = coll
+            .find_one_and_update(
+                doc! { "_id": id, own: { "$ne": user_id }, other: { "$ne": user_id } },
+                doc! { "$addToSet": { own: user_id }, "$inc": { own_count: 1 } },
+                options.clone()
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        if let Some(updated) = added {
+            return Ok(Some((true, updated)));
+        }
+
+        // nothing matched, either the document is gone or another toggle got in between
+        let exists = coll
+            .count_documents(doc! { "_id": id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        if exists == 0 {
+            return Ok(None);
+        }
+    }
+
+    Err(AppError::InternalServerError("Reaction changed too often, try again".into()))
+}
+
+/// One page of the user ids that reacted with `reaction`, oldest reaction first.
+/// `None` if the document does not exist.
+pub async fn reactors<T>(
+    coll: &Collection<T>,
+    id: &str,
+    reaction: Reaction,
+    skip: u64,
+    limit: u64
+) -> Result<Option<Vec<String>>, AppError>
+    where T: Send + Sync
+{
+    let (own, ..) = reaction.fields();
+    let pipeline = vec![
+        doc! { "$match": { "_id": id } },
+        doc! {
+            "$project": {
+                "users": { "$slice": [{ "$ifNull": [format!("${own}"), []] }, skip as i64, limit.max(1) as i64] }
+            }
+        }
+    ];
+
+    let mut cursor = coll
+        .aggregate(pipeline, None).await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    let doc: Option<Document> = cursor
+        .try_next().await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    Ok(
+        doc.map(|doc| {
+            doc.get_array("users")
+                .map(|users| {
+                    users
+                        .iter()
+                        .filter_map(|u| u.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+    )
+}
+
+/// Fills in `like_count`/`dislike_count` on documents written before the counters existed.
+pub async fn backfill_counts<T>(coll: &Collection<T>) -> Result<u64, AppError> where T: Send + Sync {
+    let pipeline = vec![
+        doc! {
+            "$set": {
+                "like_count": { "$size": { "$ifNull": ["$likes", []] } },
+                "dislike_count": { "$size": { "$ifNull": ["$dislikes", []] } }
+            }
+        }
+    ];
+
+    let result = coll
+        .update_many(doc! { "like_count": { "$exists": false } }, pipeline, None).await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    Ok(result.modified_count)
+}
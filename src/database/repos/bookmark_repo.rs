@@ -0,0 +1,169 @@
+use bson::{ doc, Bson, Document };
+use futures::TryStreamExt;
+use mongodb::{ Collection, Database, IndexModel, options::{ FindOptions, IndexOptions } };
+use crate::models::bookmark::{ Bookmark, BookmarkFolder, BookmarkKind };
+use crate::utils::cursor::{ FeedCursor, FeedSort };
+use crate::utils::error::AppError;
+
+#[derive(Clone)]
+pub struct BookmarkRepository {
+    pub coll: Collection<Bookmark>,
+}
+
+impl BookmarkRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            coll: db.collection("bookmarks"),
+        }
+    }
+
+    /// One bookmark per user and target, plus the index the list pages through
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "kind": 1, "target_id": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            IndexModel::builder().keys(doc! { "user_id": 1, "created_at": -1, "_id": -1 }).build(),
+            IndexModel::builder().keys(doc! { "kind": 1, "target_id": 1 }).build()
+        ];
+        self.coll
+            .create_indexes(indexes, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub async fn get(&self, user_id: &str, kind: BookmarkKind, target_id: &str) -> Result<Option<Bookmark>, AppError> {
+        self.coll
+            .find_one(doc! { "user_id": user_id, "kind": kind.as_str(), "target_id": target_id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    pub async fn create(&self, bookmark: &Bookmark) -> Result<(), AppError> {
+        self.coll.insert_one(bookmark, None).await.map_err(|_| AppError::DBError)?;
+        Ok(())
+    }
+
+    pub async fn set_folder(&self, id: &str, folder: Option<&str>) -> Result<(), AppError> {
+        self.coll
+            .update_one(doc! { "_id": id }, doc! { "$set": { "folder": folder } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, user_id: &str, kind: BookmarkKind, target_id: &str) -> Result<bool, AppError> {
+        let result = self.coll
+            .delete_one(doc! { "user_id": user_id, "kind": kind.as_str(), "target_id": target_id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Drops every bookmark of a deleted post or character
+    pub async fn delete_by_target(&self, kind: BookmarkKind, target_id: &str) -> Result<(), AppError> {
+        self.coll
+            .delete_many(doc! { "kind": kind.as_str(), "target_id": target_id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn count_by_user(&self, user_id: &str) -> Result<u64, AppError> {
+        self.coll
+            .count_documents(doc! { "user_id": user_id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// A user's bookmarks, newest first. `folder` of `Some(None)` means the unsorted folder.
+    pub async fn list(
+        &self,
+        user_id: &str,
+        kind: Option<BookmarkKind>,
+        folder: Option<Option<String>>,
+        after: Option<&FeedCursor>,
+        limit: u64
+    ) -> Result<Vec<Bookmark>, AppError> {
+        let mut filter = doc! { "user_id": user_id };
+        if let Some(kind) = kind {
+            filter.insert("kind", kind.as_str());
+        }
+        if let Some(folder) = folder {
+            filter.insert("folder", folder);
+        }
+
+        // bookmarks are ordered like the newest feed, so the same cursor works
+        if let Some(cursor) = after {
+            filter = doc! { "$and": [filter, cursor.seek_filter(FeedSort::Newest)?] };
+        }
+
+        let options = FindOptions::builder()
+            .sort(FeedSort::Newest.sort_doc())
+            .limit(Some(limit as i64))
+            .build();
+
+        let cursor = self.coll
+            .find(filter, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Which of `target_ids` the user bookmarked
+    pub async fn bookmarked_among(
+        &self,
+        user_id: &str,
+        kind: BookmarkKind,
+        target_ids: &[String]
+    ) -> Result<Vec<String>, AppError> {
+        if target_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids = self.coll
+            .distinct(
+                "target_id",
+                doc! { "user_id": user_id, "kind": kind.as_str(), "target_id": { "$in": target_ids } },
+                None
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(
+            ids
+                .into_iter()
+                .filter_map(|id| match id {
+                    Bson::String(id) => Some(id),
+                    _ => None,
+                })
+                .collect()
+        )
+    }
+
+    /// The user's folders with how many bookmarks each holds
+    pub async fn folders(&self, user_id: &str) -> Result<Vec<BookmarkFolder>, AppError> {
+        let pipeline = vec![
+            doc! { "$match": { "user_id": user_id } },
+            doc! { "$group": { "_id": "$folder", "count": { "$sum": 1 } } },
+            doc! { "$sort": { "_id": 1 } }
+        ];
+
+        let cursor = self.coll
+            .aggregate(pipeline, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let docs: Vec<Document> = cursor
+            .try_collect().await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(
+            docs
+                .iter()
+                .map(|item| BookmarkFolder {
+                    name: item.get_str("_id").ok().map(String::from),
+                    count: match item.get("count") {
+                        Some(Bson::Int32(n)) => *n as u64,
+                        Some(Bson::Int64(n)) => *n as u64,
+                        _ => 0,
+                    },
+                })
+                .collect()
+        )
+    }
+}
@@ -0,0 +1,204 @@
+use bson::{ doc, to_document, Document };
+use futures::TryStreamExt;
+use mongodb::{ Collection, Database, IndexModel, options::FindOptions };
+use crate::{ models::character::Character, utils::error::AppError };
+use crate::database::{ reactions, tagging };
+use crate::models::reaction::Reaction;
+
+#[derive(Clone)]
+pub struct CharacterRepository {
+    pub coll: Collection<Character>,
+}
+
+impl CharacterRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            coll: db.collection("characters"),
+        }
+    }
+
+    pub async fn create(&self, character: &Character) -> Result<(), AppError> {
+        self.coll.insert_one(character, None).await.map_err(|_| AppError::DBError)?;
+        Ok(())
+    }
+
+    pub async fn get_by_id(&self, id: &str) -> Result<Character, AppError> {
+        let filter = doc! { "_id": id };
+
+        self.coll
+            .find_one(filter, None).await
+            .map_err(|_| AppError::DBError)?
+            .ok_or(AppError::CharacterNotFound)
+    }
+
+    /// Characters with any of the given ids, in no particular order
+    pub async fn get_many(&self, ids: &[String]) -> Result<Vec<Character>, AppError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let cursor = self.coll
+            .find(doc! { "_id": { "$in": ids } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Which of the given media hashes some character still uses
+    pub async fn media_hashes_in_use(&self, hashes: &[String]) -> Result<Vec<String>, AppError> {
+        if hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let values = self.coll
+            .distinct("media.sha256", doc! { "media.sha256": { "$in": hashes } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(
+            values
+                .into_iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .filter(|hash| hashes.contains(hash))
+                .collect()
+        )
+    }
+
+    /// How many media of all characters point at each file, see MediaIndexRepository::backfill_refs
+    pub async fn media_hash_counts(&self) -> Result<Vec<(String, i64)>, AppError> {
+        let pipeline = vec![
+            doc! { "$unwind": "$media" },
+            doc! { "$match": { "media.sha256": { "$type": "string" } } },
+            doc! { "$group": { "_id": "$media.sha256", "count": { "$sum": 1 } } }
+        ];
+        let cursor = self.coll
+            .aggregate(pipeline, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let docs: Vec<Document> = cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(
+            docs
+                .into_iter()
+                .filter_map(|d| {
+                    let count = d.get_i32("count").ok()?;
+                    Some((d.get_str("_id").ok()?.to_string(), i64::from(count)))
+                })
+                .collect()
+        )
+    }
+
+    pub async fn ensure_media_hash_index(&self) -> Result<(), AppError> {
+        self.coll
+            .create_index(IndexModel::builder().keys(doc! { "media.sha256": 1 }).build(), None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn save(&self, character: &Character) -> Result<(), AppError> {
+        let filter = doc! { "_id": character.id.to_string() };
+        let mut fields = to_document(character).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        // reactions are updated in place, a (possibly cached) copy must not overwrite them
+        for field in ["likes", "dislikes", "like_count", "dislike_count"] {
+            fields.remove(field);
+        }
+        let update = doc! { "$set": fields };
+
+        self.coll
+            .update_one(filter, update, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Atomically toggles a user's reaction, returning whether it is now set and the updated character
+    pub async fn toggle_reaction(&self, id: &str, user_id: &str, reaction: Reaction) -> Result<(bool, Character), AppError> {
+        reactions::toggle(&self.coll, id, user_id, reaction).await?.ok_or(AppError::CharacterNotFound)
+    }
+
+    pub async fn backfill_reaction_counts(&self) -> Result<u64, AppError> {
+        reactions::backfill_counts(&self.coll).await
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), AppError> {
+        let filter = doc! { "_id": id };
+
+        self.coll.delete_one(filter, None).await.map_err(|_| AppError::DBError)?;
+        Ok(())
+    }
+
+    pub async fn get_all_by_owner(
+        &self,
+        owner: &str,
+        limit: u64,
+        skip: u64
+    ) -> Result<Vec<Character>, AppError> {
+        let filter = doc! { "owner": owner };
+
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(Some(limit as i64))
+            .skip(Some(skip))
+            .build();
+
+        let cursor = self.coll
+            .find(filter, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        let characters: Vec<Character> = cursor
+            .try_collect().await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(characters)
+    }
+
+    /// Finds characters carrying every one of the given tags, newest first.
+    pub async fn search_by_tags(
+        &self,
+        tags: Vec<String>,
+        limit: u64,
+        skip: u64
+    ) -> Result<Vec<Character>, AppError> {
+        let mut filter = doc! {};
+
+        if !tags.is_empty() {
+            filter.insert("tags", doc! { "$all": tags });
+        }
+
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(Some(limit as i64))
+            .skip(Some(skip))
+            .build();
+
+        let cursor = self.coll
+            .find(filter, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        let characters: Vec<Character> = cursor
+            .try_collect().await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(characters)
+    }
+
+    /// Swaps `from` for `to` on every character, returning the ids that changed
+    pub async fn retag(&self, from: &str, to: &str) -> Result<Vec<String>, AppError> {
+        tagging::retag(&self.coll, from, to).await
+    }
+
+    /// Adds `implied` to every character tagged `tag`, returning the ids that changed
+    pub async fn add_implied_tag(&self, tag: &str, implied: &str) -> Result<Vec<String>, AppError> {
+        tagging::add_implied(&self.coll, tag, implied).await
+    }
+
+    pub async fn count_tagged(&self, tag: &str) -> Result<u64, AppError> {
+        tagging::count_tagged(&self.coll, tag, doc! {}).await
+    }
+
+    pub async fn tag_counts(&self) -> Result<Vec<(String, u64)>, AppError> {
+        tagging::tag_counts(&self.coll, doc! {}).await
+    }
+
+    pub async fn normalize_stored_tags(&self) -> Result<u64, AppError> {
+        tagging::normalize_stored(&self.coll).await
+    }
+}
@@ -0,0 +1,191 @@
+use bson::doc;
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::{ Database, IndexModel, options::FindOptions };
+use crate::models::collection::Collection;
+use crate::utils::error::AppError;
+
+#[derive(Clone)]
+pub struct CollectionRepository {
+    pub coll: mongodb::Collection<Collection>,
+}
+
+impl CollectionRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            coll: db.collection("collections"),
+        }
+    }
+
+    /// Indexes for listing a user's collections and finding the ones a post is in
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexModel::builder().keys(doc! { "owner_id": 1, "updated_at": -1 }).build(),
+            IndexModel::builder().keys(doc! { "posts": 1 }).build()
+        ];
+        self.coll
+            .create_indexes(indexes, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub async fn create(&self, collection: &Collection) -> Result<(), AppError> {
+        self.coll.insert_one(collection, None).await.map_err(|_| AppError::DBError)?;
+        Ok(())
+    }
+
+    pub async fn get_by_id(&self, id: &str) -> Result<Collection, AppError> {
+        self.coll
+            .find_one(doc! { "_id": id }, None).await
+            .map_err(|_| AppError::DBError)?
+            .ok_or(AppError::CollectionNotFound)
+    }
+
+    /// Saves the name, description, visibility and cover. Posts are changed with the
+    /// methods below so concurrent adds don't overwrite each other.
+    pub async fn save_details(&self, collection: &Collection) -> Result<(), AppError> {
+        let visibility = bson::to_bson(&collection.visibility).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        self.coll
+            .update_one(
+                doc! { "_id": collection.id.to_string() },
+                doc! {
+                    "$set": {
+                        "name": &collection.name,
+                        "description": &collection.description,
+                        "visibility": visibility,
+                        "cover_post_id": &collection.cover_post_id,
+                        "updated_at": bson::DateTime::from_chrono(Utc::now()),
+                    }
+                },
+                None
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), AppError> {
+        self.coll.delete_one(doc! { "_id": id }, None).await.map_err(|_| AppError::DBError)?;
+        Ok(())
+    }
+
+    /// A user's collections, most recently changed first
+    pub async fn get_by_owner(
+        &self,
+        owner_id: &str,
+        include_hidden: bool,
+        skip: u64,
+        limit: u64
+    ) -> Result<Vec<Collection>, AppError> {
+        let mut filter = doc! { "owner_id": owner_id };
+        if !include_hidden {
+            filter.insert("visibility", "public");
+        }
+
+        let options = FindOptions::builder()
+            .sort(doc! { "updated_at": -1, "_id": 1 })
+            .skip(Some(skip))
+            .limit(Some(limit as i64))
+            .build();
+
+        let cursor = self.coll
+            .find(filter, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    pub async fn count_by_owner(&self, owner_id: &str) -> Result<u64, AppError> {
+        self.coll
+            .count_documents(doc! { "owner_id": owner_id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Public collections containing a post
+    pub async fn get_public_containing(&self, post_id: &str, limit: u64) -> Result<Vec<Collection>, AppError> {
+        let options = FindOptions::builder()
+            .sort(doc! { "updated_at": -1 })
+            .limit(Some(limit as i64))
+            .build();
+
+        let cursor = self.coll
+            .find(doc! { "posts": post_id, "visibility": "public" }, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Inserts a post at `position` (the end when `None`) unless it is already in there or the
+    /// collection is full. Returns whether it was added.
+    pub async fn add_post(
+        &self,
+        id: &str,
+        post_id: &str,
+        position: Option<usize>,
+        max_posts: usize
+    ) -> Result<bool, AppError> {
+        let mut push = doc! { "$each": [post_id] };
+        if let Some(position) = position {
+            push.insert("$position", position as i64);
+        }
+
+        let result = self.coll
+            .update_one(
+                doc! {
+                    "_id": id,
+                    "posts": { "$ne": post_id },
+                    // the array has no element at max_posts - 1, so there is still room
+                    format!("posts.{}", max_posts - 1): { "$exists": false },
+                },
+                doc! {
+                    "$push": { "posts": push },
+                    "$set": { "updated_at": bson::DateTime::from_chrono(Utc::now()) },
+                },
+                None
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(result.modified_count > 0)
+    }
+
+    pub async fn remove_post(&self, id: &str, post_id: &str) -> Result<bool, AppError> {
+        let result = self.coll
+            .update_one(
+                doc! { "_id": id, "posts": post_id },
+                doc! {
+                    "$pull": { "posts": post_id },
+                    "$set": { "updated_at": bson::DateTime::from_chrono(Utc::now()) },
+                },
+                None
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(result.modified_count > 0)
+    }
+
+    /// Drops a deleted post from every collection, clearing covers that pointed at it
+    pub async fn remove_post_everywhere(&self, post_id: &str) -> Result<(), AppError> {
+        self.coll
+            .update_many(doc! { "posts": post_id }, doc! { "$pull": { "posts": post_id } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        self.coll
+            .update_many(doc! { "cover_post_id": post_id }, doc! { "$set": { "cover_post_id": null } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Replaces the order of the posts, only if the collection still holds exactly `expected`
+    pub async fn set_order(&self, id: &str, expected: &[String], posts: &[String]) -> Result<bool, AppError> {
+        let result = self.coll
+            .update_one(
+                doc! { "_id": id, "posts": expected },
+                doc! { "$set": { "posts": posts, "updated_at": bson::DateTime::from_chrono(Utc::now()) } },
+                None
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(result.matched_count > 0)
+    }
+}
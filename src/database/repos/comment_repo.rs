@@ -1,8 +1,35 @@
-use bson::{doc, to_document};
+use bson::{doc, to_document, Document};
+use chrono::{DateTime, Utc};
 use futures::TryStreamExt;
-use mongodb::{Collection, Database, options::FindOptions};
+use mongodb::{Collection, Database, IndexModel, options::{FindOneAndUpdateOptions, FindOptions, ReplaceOptions, ReturnDocument}};
 
-use crate::{models::comment::Comment, utils::error::AppError};
+use crate::{models::comment::{Comment, Mention}, utils::error::AppError};
+use crate::database::reactions;
+use crate::models::reaction::Reaction;
+use crate::utils::cursor::{FeedCursor, FeedSort};
+
+/// What a page of comments leaves out
+#[derive(Default)]
+pub struct CommentFilter<'a> {
+    pub viewer: Option<&'a str>, // sees their own held comments
+    pub show_pending: bool, // the post's author sees everyone's
+    pub exclude: Option<&'a str>, // the pinned comment, which is shown on its own
+}
+
+impl CommentFilter<'_> {
+    fn apply(&self, filter: &mut Document) {
+        if !self.show_pending {
+            let mut shown = vec![doc! { "pending": { "$ne": true } }];
+            if let Some(viewer) = self.viewer {
+                shown.push(doc! { "author_id": viewer });
+            }
+            filter.insert("$or", shown);
+        }
+        if let Some(id) = self.exclude {
+            filter.insert("_id", doc! { "$ne": id });
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct CommentRepository {
@@ -16,6 +43,21 @@ impl CommentRepository {
         }
     }
 
+    /// Indexes the top-level and reply pages are read through
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexModel::builder().keys(doc! { "post_id": 1, "parent_id": 1, "created_at": -1, "_id": -1 }).build(),
+            IndexModel::builder().keys(doc! { "post_id": 1, "parent_id": 1, "like_count": -1, "_id": -1 }).build(),
+            IndexModel::builder().keys(doc! { "parent_id": 1, "created_at": 1, "_id": 1 }).build(),
+            IndexModel::builder().keys(doc! { "parent_id": 1, "like_count": -1, "_id": -1 }).build(),
+            IndexModel::builder().keys(doc! { "root_id": 1 }).build()
+        ];
+        self.coll
+            .create_indexes(indexes, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
     pub async fn create(&self, comment: &Comment) -> Result<(), AppError> {
         self.coll
             .insert_one(comment, None)
@@ -32,10 +74,84 @@ impl CommentRepository {
             .ok_or(AppError::CommentNotFound)
     }
 
-    pub async fn get_for_post(&self, post_id: &str) -> Result<Vec<Comment>, AppError> {
-        let filter = doc! { "post_id": post_id };
+    /// One page of a post's top-level comments
+    pub async fn get_top_level(
+        &self,
+        post_id: &str,
+        shown: &CommentFilter<'_>,
+        sort: FeedSort,
+        after: Option<&FeedCursor>,
+        limit: u64
+    ) -> Result<Vec<Comment>, AppError> {
+        let mut filter = doc! { "post_id": post_id, "parent_id": null };
+        shown.apply(&mut filter);
+        self.page(filter, sort, after, limit).await
+    }
+
+    /// One page of the direct replies to a comment
+    pub async fn get_replies(
+        &self,
+        parent_id: &str,
+        shown: &CommentFilter<'_>,
+        sort: FeedSort,
+        after: Option<&FeedCursor>,
+        limit: u64
+    ) -> Result<Vec<Comment>, AppError> {
+        let mut filter = doc! { "parent_id": parent_id };
+        shown.apply(&mut filter);
+        self.page(filter, sort, after, limit).await
+    }
+
+    /// Comments and replies on a post waiting for approval, oldest first
+    pub async fn get_pending(
+        &self,
+        post_id: &str,
+        after: Option<&FeedCursor>,
+        limit: u64
+    ) -> Result<Vec<Comment>, AppError> {
+        self.page(doc! { "post_id": post_id, "pending": true }, FeedSort::Oldest, after, limit).await
+    }
+
+    /// Whether the user has a comment on the post that isn't waiting for approval
+    pub async fn has_approved(&self, post_id: &str, author_id: &str) -> Result<bool, AppError> {
+        let filter = doc! { "post_id": post_id, "author_id": author_id, "pending": { "$ne": true } };
+        let found = self.coll
+            .find_one(filter, None)
+            .await
+            .map_err(|_| AppError::DBError)?;
+        Ok(found.is_some())
+    }
+
+    /// Lets a held comment through, counting it on its parent now that it shows
+    pub async fn approve(&self, id: &str) -> Result<Option<Comment>, AppError> {
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        let approved = self.coll
+            .find_one_and_update(doc! { "_id": id, "pending": true }, doc! { "$set": { "pending": false } }, options)
+            .await
+            .map_err(|_| AppError::DBError)?;
+
+        if let Some(parent_id) = approved.as_ref().and_then(|c| c.parent_id.as_deref()) {
+            self.coll
+                .update_one(doc! { "_id": parent_id }, doc! { "$inc": { "reply_count": 1 } }, None)
+                .await
+                .map_err(|_| AppError::DBError)?;
+        }
+        Ok(approved)
+    }
+
+    async fn page(
+        &self,
+        mut filter: Document,
+        sort: FeedSort,
+        after: Option<&FeedCursor>,
+        limit: u64
+    ) -> Result<Vec<Comment>, AppError> {
+        if let Some(cursor) = after {
+            filter = doc! { "$and": [filter, cursor.seek_filter(sort)?] };
+        }
         let options = FindOptions::builder()
-            .sort(doc! { "created_at": -1 }) // newest first
+            .sort(sort.sort_doc())
+            .limit(Some(limit as i64))
             .build();
 
         let cursor = self
@@ -44,17 +160,35 @@ impl CommentRepository {
             .await
             .map_err(|_| AppError::DBError)?;
 
-        let comments: Vec<Comment> = cursor
+        cursor
             .try_collect()
             .await
-            .map_err(|_| AppError::InternalServerError("Failed to collect comments.".into()))?;
+            .map_err(|_| AppError::InternalServerError("Failed to collect comments.".into()))
+    }
 
-        Ok(comments)
+    /// Stores a reply and counts it on its parent, held replies are counted once approved
+    pub async fn create_reply(&self, reply: &Comment) -> Result<(), AppError> {
+        let parent_id = reply.parent_id.as_deref().ok_or(AppError::CommentNotFound)?;
+        self.create(reply).await?;
+        if reply.pending {
+            return Ok(());
+        }
+        self.coll
+            .update_one(doc! { "_id": parent_id }, doc! { "$inc": { "reply_count": 1 } }, None)
+            .await
+            .map_err(|_| AppError::DBError)?;
+        Ok(())
     }
 
     pub async fn save(&self, comment: &Comment) -> Result<(), AppError> {
         let filter = doc! { "_id": comment.id.to_string() };
-        let update = doc! { "$set": to_document(comment).map_err(|e| AppError::InternalServerError(e.to_string()))? };
+        let mut fields = to_document(comment).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        // reactions only change through toggle_reaction
+        for field in ["likes", "dislikes", "like_count", "dislike_count"] {
+            fields.remove(field);
+        }
+        let update = doc! { "$set": fields };
 
         self.coll
             .update_one(filter, update, None)
@@ -64,6 +198,105 @@ impl CommentRepository {
         Ok(())
     }
 
+    /// Atomically toggles a user's reaction, returning whether it is now set and the updated comment
+    pub async fn toggle_reaction(&self, id: &str, user_id: &str, reaction: Reaction) -> Result<(bool, Comment), AppError> {
+        reactions::toggle(&self.coll, id, user_id, reaction)
+            .await?
+            .ok_or(AppError::CommentNotFound)
+    }
+
+    pub async fn backfill_reaction_counts(&self) -> Result<u64, AppError> {
+        reactions::backfill_counts(&self.coll).await
+    }
+
+    /// Older comments stored `created_at` as a string, which neither sorts nor pages right
+    pub async fn backfill_created_at(&self) -> Result<u64, AppError> {
+        let raw = self.coll.clone_with_type::<Document>();
+        let mut cursor = raw
+            .find(doc! { "created_at": { "$type": "string" } }, None)
+            .await
+            .map_err(|_| AppError::DBError)?;
+
+        let mut updated = 0;
+        while let Some(comment) = cursor.try_next().await.map_err(|_| AppError::DBError)? {
+            let (Ok(id), Ok(created_at)) = (comment.get_str("_id"), comment.get_str("created_at")) else {
+                continue;
+            };
+            let Ok(created_at) = DateTime::parse_from_rfc3339(created_at) else {
+                log::warn!("Comment {} has an unreadable created_at {}", id, created_at);
+                continue;
+            };
+            let created_at = bson::DateTime::from_chrono(created_at.with_timezone(&Utc));
+            raw.update_one(doc! { "_id": id }, doc! { "$set": { "created_at": created_at } }, None)
+                .await
+                .map_err(|_| AppError::DBError)?;
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
+    /// Writes a comment as given, for moving replies over from the old layout
+    pub async fn upsert(&self, comment: &Comment) -> Result<(), AppError> {
+        let options = ReplaceOptions::builder().upsert(true).build();
+        self.coll
+            .replace_one(doc! { "_id": comment.id.to_string() }, comment, options)
+            .await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn set_reply_count(&self, id: &str, count: u64) -> Result<(), AppError> {
+        self.coll
+            .update_one(doc! { "_id": id }, doc! { "$set": { "reply_count": count as i64 } }, None)
+            .await
+            .map_err(|_| AppError::DBError)?;
+        Ok(())
+    }
+
+    pub async fn set_content(
+        &self,
+        id: &str,
+        content: &str,
+        mentions: &[Mention],
+        edited_at: DateTime<Utc>
+    ) -> Result<(), AppError> {
+        let mentions = bson::to_bson(mentions).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let edited_at = bson::to_bson(&edited_at).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let update = doc! { "$set": { "content": content, "mentions": mentions, "edited_at": edited_at } };
+        self.coll
+            .update_one(doc! { "_id": id }, update, None)
+            .await
+            .map_err(|_| AppError::DBError)?;
+        Ok(())
+    }
+
+    /// Turns a comment into a tombstone. The author's own deletions also drop the text,
+    /// removals keep it for moderators to look back on
+    pub async fn tombstone(&self, id: &str, removed_by: Option<&str>) -> Result<(), AppError> {
+        let mut fields = doc! { "deleted": true, "removed_by": removed_by };
+        if removed_by.is_none() {
+            fields.insert("content", "");
+        }
+        self.coll
+            .update_one(doc! { "_id": id }, doc! { "$set": fields }, None)
+            .await
+            .map_err(|_| AppError::DBError)?;
+        Ok(())
+    }
+
+    /// Uncounts a deleted reply on its parent, returning the parent as it is now
+    pub async fn uncount_reply(&self, parent_id: &str) -> Result<Option<Comment>, AppError> {
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.coll
+            .find_one_and_update(
+                doc! { "_id": parent_id, "reply_count": { "$gt": 0 } },
+                doc! { "$inc": { "reply_count": -1 } },
+                options
+            )
+            .await
+            .map_err(|_| AppError::DBError)
+    }
+
     pub async fn delete_by_id(&self, id: &str) -> Result<bool, AppError> {
         let filter = doc! { "_id": id };
         let result = self
@@ -0,0 +1,48 @@
+use bson::doc;
+use futures::TryStreamExt;
+use mongodb::{ Collection, Database, options::FindOptions };
+use crate::models::media::BannedHash;
+use crate::utils::error::AppError;
+
+#[derive(Clone)]
+pub struct HashBanRepository {
+    pub coll: Collection<BannedHash>,
+}
+
+impl HashBanRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            coll: db.collection("banned_hashes"),
+        }
+    }
+
+    pub async fn is_banned(&self, sha256: &str) -> Result<bool, AppError> {
+        let count = self.coll
+            .count_documents(doc! { "_id": sha256 }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(count > 0)
+    }
+
+    /// Bans a hash, banning it again just replaces the reason
+    pub async fn ban(&self, ban: &BannedHash) -> Result<(), AppError> {
+        let options = mongodb::options::ReplaceOptions::builder().upsert(true).build();
+        self.coll
+            .replace_one(doc! { "_id": &ban.sha256 }, ban, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn unban(&self, sha256: &str) -> Result<bool, AppError> {
+        let result = self.coll
+            .delete_one(doc! { "_id": sha256 }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Newest bans first
+    pub async fn list(&self, skip: u64, limit: i64) -> Result<Vec<BannedHash>, AppError> {
+        let options = FindOptions::builder().sort(doc! { "created_at": -1 }).skip(skip).limit(limit).build();
+        let cursor = self.coll.find(None, options).await.map_err(|_| AppError::DBError)?;
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use bson::{ doc, to_document };
+use mongodb::{ Collection, Database };
+use mongodb::options::{ FindOneAndUpdateOptions, ReturnDocument };
+use crate::models::media::MediaObject;
+use crate::utils::error::AppError;
+
+#[derive(Clone)]
+pub struct MediaIndexRepository {
+    pub coll: Collection<MediaObject>,
+}
+
+impl MediaIndexRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            coll: db.collection("media_index"),
+        }
+    }
+
+    /// Takes a reference to an indexed object. None when it isn't indexed, or stopped being
+    /// while the caller was looking at it
+    pub async fn take_ref(&self, sha256: &str) -> Result<Option<MediaObject>, AppError> {
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.coll
+            .find_one_and_update(doc! { "_id": sha256 }, doc! { "$inc": { "refs": 1_i64 } }, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Gives back `count` references to an object
+    pub async fn drop_refs(&self, sha256: &str, count: i64) -> Result<(), AppError> {
+        self.coll
+            .update_one(doc! { "_id": sha256 }, doc! { "$inc": { "refs": -count } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Unindexes an object nobody holds a reference to, in one step so a reference taken in
+    /// the meantime keeps it. Returns it when it was removed
+    pub async fn delete_unreferenced(&self, sha256: &str) -> Result<Option<MediaObject>, AppError> {
+        self.coll
+            .find_one_and_delete(doc! { "_id": sha256, "refs": { "$lte": 0 } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Adds an object with one reference taken, or takes one on the existing object when the
+    /// same file was indexed in the meantime
+    pub async fn insert(&self, object: &MediaObject) -> Result<MediaObject, AppError> {
+        let mut fields = to_document(object).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        fields.remove("refs");
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        self.coll
+            .find_one_and_update(
+                doc! { "_id": &object.sha256 },
+                doc! { "$setOnInsert": fields, "$inc": { "refs": 1_i64 } },
+                options
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?
+            .ok_or(AppError::DBError)
+    }
+
+    /// Whether some objects were indexed before references were counted
+    pub async fn has_uncounted(&self) -> Result<bool, AppError> {
+        let found = self.coll
+            .find_one(doc! { "refs": { "$exists": false } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(found.is_some())
+    }
+
+    /// Sets the references of objects indexed before they were counted from how many posts
+    /// and characters use them. Returns how many were set
+    pub async fn backfill_refs(&self, counts: &HashMap<String, i64>) -> Result<u64, AppError> {
+        let uncounted = doc! { "refs": { "$exists": false } };
+        let missing = self.coll
+            .count_documents(uncounted.clone(), None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        for (sha256, count) in counts {
+            self.coll
+                .update_one(doc! { "_id": sha256, "refs": { "$exists": false } }, doc! { "$set": { "refs": count } }, None).await
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        }
+        self.coll
+            .update_many(uncounted, doc! { "$set": { "refs": 0_i64 } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(missing)
+    }
+
+    pub async fn delete_many(&self, hashes: &[String]) -> Result<(), AppError> {
+        self.coll
+            .delete_many(doc! { "_id": { "$in": hashes } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+}
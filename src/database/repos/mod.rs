@@ -9,3 +9,12 @@ pub mod codes_repo;
 pub mod preuser_repo;
 pub mod reports_repo;
 pub mod announcement_repository;
+pub mod character_repo;
+pub mod tag_repo;
+pub mod revision_repo;
+pub mod collection_repo;
+pub mod bookmark_repo;
+pub mod upload_repo;
+pub mod media_repo;
+pub mod hash_ban_repo;
+pub mod notification_repo;
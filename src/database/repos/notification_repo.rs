@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use bson::{ doc, to_document, Bson, Document };
+use futures::TryStreamExt;
+use mongodb::{ Collection, Database, IndexModel, options::{ FindOptions, IndexOptions, UpdateOptions } };
+use mongodb::error::{ Error, ErrorKind, WriteFailure };
+use crate::models::notification::Notification;
+use crate::utils::cursor::{ FeedCursor, FeedSort };
+use crate::utils::error::AppError;
+
+// a group keeps this many actors around to show, the count keeps going past it
+const MAX_GROUP_ACTORS: i32 = 50;
+// old notifications are dropped by mongo after this long
+const NOTIFICATION_TTL_DAYS: u64 = 90;
+
+#[derive(Clone)]
+pub struct NotificationRepository {
+    coll: Collection<Notification>,
+}
+
+impl NotificationRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            coll: db.collection("notifications"),
+        }
+    }
+
+    /// The index the list pages through, the one grouping looks up by and the expiry. There
+    /// is only ever one unread notification of a kind about a target, so racing notifies join
+    /// the same group
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexModel::builder().keys(doc! { "user_id": 1, "created_at": -1, "_id": -1 }).build(),
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "kind": 1, "target_id": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .unique(true)
+                        .partial_filter_expression(doc! { "read": false })
+                        .build()
+                )
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "created_at": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .expire_after(Duration::from_secs(NOTIFICATION_TTL_DAYS * 24 * 60 * 60))
+                        .build()
+                )
+                .build()
+        ];
+        self.coll
+            .create_indexes(indexes, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub async fn create(&self, notification: &Notification) -> Result<(), AppError> {
+        self.coll.insert_one(notification, None).await.map_err(|_| AppError::DBError)?;
+        Ok(())
+    }
+
+    /// Adds the actor to the recipient's unread notification of the same kind about the same
+    /// target, or starts one from `notification` when there is none. Actors already in the
+    /// group, say after unliking and liking again, change nothing
+    pub async fn group(&self, notification: &Notification) -> Result<(), AppError> {
+        let Some(actor_id) = notification.actor_ids.last() else {
+            return self.create(notification).await;
+        };
+        let group = doc! {
+            "user_id": &notification.user_id,
+            "kind": notification.kind.as_str(),
+            "target_id": &notification.target_id,
+            "read": false,
+        };
+
+        let mut joining = group.clone();
+        joining.insert("actor_ids", doc! { "$ne": actor_id });
+        let update = doc! {
+            "$push": { "actor_ids": { "$each": [actor_id], "$slice": -MAX_GROUP_ACTORS } },
+            "$inc": { "actor_count": 1 },
+            "$set": { "created_at": bson::DateTime::from_chrono(notification.created_at) },
+        };
+        let result = self.coll
+            .update_one(joining.clone(), update.clone(), None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        if result.matched_count > 0 {
+            return Ok(());
+        }
+
+        let fresh = to_document(notification).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let started = self.coll.update_one(
+            group,
+            doc! { "$setOnInsert": fresh },
+            UpdateOptions::builder().upsert(true).build()
+        ).await;
+        match started {
+            Ok(result) if result.upserted_id.is_some() => {
+                return Ok(());
+            }
+            // someone else started the group in the meantime, join theirs
+            Ok(_) => {}
+            Err(e) if is_duplicate_key(&e) => {}
+            Err(e) => {
+                return Err(AppError::InternalServerError(e.to_string()));
+            }
+        }
+
+        self.coll
+            .update_one(joining, update, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Marks all but the newest of unread notifications that should have been one group read,
+    /// left behind by notifies that raced before the unread index was unique
+    pub async fn mark_duplicates_read(&self) -> Result<u64, AppError> {
+        let pipeline = vec![
+            doc! { "$match": { "read": false } },
+            doc! { "$sort": { "created_at": -1 } },
+            doc! {
+                "$group": {
+                    "_id": { "user_id": "$user_id", "kind": "$kind", "target_id": "$target_id" },
+                    "ids": { "$push": "$_id" },
+                }
+            },
+            doc! { "$match": { "ids.1": { "$exists": true } } }
+        ];
+        let cursor = self.coll
+            .aggregate(pipeline, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let groups: Vec<Document> = cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        let older: Vec<Bson> = groups
+            .iter()
+            .filter_map(|group| group.get_array("ids").ok())
+            .flat_map(|ids| ids.iter().skip(1).cloned())
+            .collect();
+        if older.is_empty() {
+            return Ok(0);
+        }
+
+        let result = self.coll
+            .update_many(doc! { "_id": { "$in": older } }, doc! { "$set": { "read": true } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(result.modified_count)
+    }
+
+    /// A user's notifications, newest first
+    pub async fn list(
+        &self,
+        user_id: &str,
+        unread_only: bool,
+        after: Option<&FeedCursor>,
+        limit: u64
+    ) -> Result<Vec<Notification>, AppError> {
+        let mut filter = doc! { "user_id": user_id };
+        if unread_only {
+            filter.insert("read", false);
+        }
+        if let Some(cursor) = after {
+            filter = doc! { "$and": [filter, cursor.seek_filter(FeedSort::Newest)?] };
+        }
+
+        let options = FindOptions::builder()
+            .sort(FeedSort::Newest.sort_doc())
+            .limit(Some(limit as i64))
+            .build();
+
+        let cursor = self.coll
+            .find(filter, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    pub async fn unread_count(&self, user_id: &str) -> Result<u64, AppError> {
+        self.coll
+            .count_documents(doc! { "user_id": user_id, "read": false }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Marks the given notifications of the user read, or all of them without ids
+    pub async fn mark_read(&self, user_id: &str, ids: Option<&[String]>) -> Result<u64, AppError> {
+        let mut filter = doc! { "user_id": user_id, "read": false };
+        if let Some(ids) = ids {
+            filter.insert("_id", doc! { "$in": ids });
+        }
+
+        let result = self.coll
+            .update_many(filter, doc! { "$set": { "read": true } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(result.modified_count)
+    }
+}
+
+fn is_duplicate_key(e: &Error) -> bool {
+    match e.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(e)) => e.code == 11000,
+        ErrorKind::Command(e) => e.code == 11000,
+        _ => false,
+    }
+}
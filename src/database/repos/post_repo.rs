@@ -1,8 +1,19 @@
-use bson::{ doc, from_document, to_document, Document };
+use bson::{ doc, from_document, to_document, Bson, Document };
 use futures::{ StreamExt, TryStreamExt };
-use mongodb::{ Collection, Database, options::FindOptions };
-use mongodb::options::AggregateOptions;
-use crate::{ models::post::Post, utils::error::AppError };
+use mongodb::{ Collection, Database, IndexModel, options::FindOptions };
+use mongodb::options::{ AggregateOptions, FindOneAndUpdateOptions, IndexOptions, ReturnDocument };
+use crate::{ models::post::{ CommentSettings, Post }, utils::error::AppError };
+use crate::utils::post::PostStatus;
+use crate::database::{ reactions, tagging };
+use crate::models::media::Media;
+use crate::models::reaction::Reaction;
+use crate::models::search::{ FacetCount, SearchFacets };
+use crate::utils::cursor::{ FeedPage, FeedSort };
+use crate::utils::trending::{ engagement_expr, trending_expr, PopularWindow };
+use chrono::{ DateTime, Utc };
+
+// name of the posts text index, there can only be one per collection
+const SEARCH_INDEX: &str = "post_text_search";
 
 #[derive(Clone)]
 pub struct PostRepository {
@@ -33,16 +44,50 @@ impl PostRepository {
         Ok(posts)
     }
 
-    pub async fn save(&self, post: &Post) -> Result<(), AppError> {
+    /// Writes the post's own fields and returns it as stored now
+    pub async fn save(&self, post: &Post) -> Result<Option<Post>, AppError> {
         let filter = doc! { "_id": post.id.to_string() };
-        let update =
-            doc! { "$set": to_document(post).map_err(|e| AppError::InternalServerError(e.to_string()))? };
+        let mut fields = to_document(post).map_err(|e| AppError::InternalServerError(e.to_string()))?;
 
+        // reactions, counters and scores are updated in place, a (possibly cached) copy must not overwrite them
+        for field in ["likes", "dislikes", "like_count", "dislike_count"] {
+            fields.remove(field);
+        }
+        fields.remove("views");
+        fields.remove("engagement");
+        fields.remove("trending_score");
+        // so are attached media, comment settings and the publish state
+        for field in ["media", "comment_settings", "status", "publish_at"] {
+            fields.remove(field);
+        }
+        let update = doc! { "$set": fields };
+
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
         self.coll
-            .update_one(filter, update, None).await
-            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+            .find_one_and_update(filter, update, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
 
-        Ok(())
+    /// Moves a draft or scheduled post to the status, publish time and creation time of `post`,
+    /// only while it's still `from` so it can't undo the publish task
+    pub async fn set_publish_state(&self, post: &Post, from: PostStatus) -> Result<Option<Post>, AppError> {
+        let status = |status: &PostStatus| bson::to_bson(status).map_err(|e| AppError::InternalServerError(e.to_string()));
+        let mut set = doc! { "status": status(&post.status)?, "created_at": bson::DateTime::from_chrono(post.created_at) };
+        let mut update = doc! {};
+        match post.publish_at {
+            Some(publish_at) => {
+                set.insert("publish_at", bson::DateTime::from_chrono(publish_at));
+            }
+            None => {
+                update.insert("$unset", doc! { "publish_at": "" });
+            }
+        }
+        update.insert("$set", set);
+
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.coll
+            .find_one_and_update(doc! { "_id": post.id.to_string(), "status": status(&from)? }, update, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
     }
 
     pub async fn delete(&self, id: &str) -> Result<(), AppError> {
@@ -59,6 +104,7 @@ impl PostRepository {
         tags: Option<Vec<String>>
     ) -> Result<Vec<Post>, AppError> {
         let mut filter = doc! {};
+        published_only(&mut filter);
 
         if let Some(tags) = tags {
             if !tags.is_empty() {
@@ -92,6 +138,19 @@ impl PostRepository {
             .ok_or(AppError::PostNotFound)
     }
 
+    /// Posts with any of the given ids, in no particular order
+    pub async fn get_many(&self, ids: &[String]) -> Result<Vec<Post>, AppError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let cursor = self.coll
+            .find(doc! { "_id": { "$in": ids } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
     pub async fn find_by_author_and_short_id(
         &self,
         username: &str,
@@ -113,10 +172,13 @@ impl PostRepository {
         &self,
         username: &str,
         limit: u64,
-        skip: u64,
-        tags: Option<Vec<String>>
+        page: &FeedPage,
+        tags: Option<Vec<String>>,
+        include_nsfw: bool
     ) -> Result<Vec<Post>, AppError> {
         let mut filter = doc! { "author": username };
+        published_only(&mut filter);
+        hide_nsfw(&mut filter, include_nsfw);
 
         if let Some(tags) = tags {
             if !tags.is_empty() {
@@ -124,49 +186,82 @@ impl PostRepository {
             }
         }
 
-        // log::debug!("filter {:?}", filter);
-        // log::debug!("limit {}, skip {}", limit, skip);
-
-        let options = FindOptions::builder()
-            .sort(doc! { "created_at": -1 })
-            .limit(Some(limit as i64))
-            .skip(Some(skip))
-            .build();
+        self.find_feed(vec![], filter, FeedSort::Newest, page, limit).await
+    }
 
-        let cursor = self.coll
-            .find(filter, options).await
-            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    pub async fn get_all_by_character(
+        &self,
+        character_id: &str,
+        limit: u64,
+        page: &FeedPage,
+        include_nsfw: bool
+    ) -> Result<Vec<Post>, AppError> {
+        let mut filter = doc! { "characters": character_id };
+        published_only(&mut filter);
+        hide_nsfw(&mut filter, include_nsfw);
+        self.find_feed(vec![], filter, FeedSort::Newest, page, limit).await
+    }
 
-        let posts: Vec<Post> = cursor
-            .try_collect().await
-            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    /// Posts written by any of the given authors, newest first
+    pub async fn get_by_authors(
+        &self,
+        author_ids: Vec<String>,
+        include_nsfw: bool,
+        limit: u64,
+        page: &FeedPage
+    ) -> Result<Vec<Post>, AppError> {
+        let mut filter = doc! { "author_id": { "$in": author_ids } };
+        published_only(&mut filter);
+        hide_nsfw(&mut filter, include_nsfw);
 
-        Ok(posts)
+        self.find_feed(vec![], filter, FeedSort::Newest, page, limit).await
     }
 
-    pub async fn get_latest(&self, limit: u64, skip: u64) -> Result<Vec<Post>, AppError> {
-        let options = FindOptions::builder()
-            .sort(doc! { "created_at": -1 })
-            .skip(Some(skip))
-            .limit(Some(limit as i64))
-            .build();
+    /// Drops a character from every post featuring it, returning the ids that changed
+    pub async fn remove_character_ref(&self, character_id: &str) -> Result<Vec<String>, AppError> {
+        let ids: Vec<String> = self.coll
+            .distinct("_id", doc! { "characters": character_id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|id| match id {
+                Bson::String(id) => Some(id),
+                _ => None,
+            })
+            .collect();
+        if ids.is_empty() {
+            return Ok(ids);
+        }
 
-        let cursor = self.coll.find(None, options).await.map_err(|_| AppError::DBError)?;
+        self.coll
+            .update_many(
+                doc! { "_id": { "$in": &ids } },
+                doc! { "$pull": { "characters": character_id } },
+                None
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
 
-        let posts: Vec<Post> = cursor
-            .try_collect().await
-            .map_err(|_| AppError::InternalServerError("Failed to collect posts".into()))?;
+        Ok(ids)
+    }
 
-        Ok(posts)
+    pub async fn get_latest(&self, limit: u64, page: &FeedPage, include_nsfw: bool) -> Result<Vec<Post>, AppError> {
+        let mut filter = doc! {};
+        published_only(&mut filter);
+        hide_nsfw(&mut filter, include_nsfw);
+        self.find_feed(vec![], filter, FeedSort::Newest, page, limit).await
     }
 
+    /// Popular posts inside `window`, see `PopularWindow` for how each window is ranked
     pub async fn get_popular_posts(
         &self,
         limit: u64,
-        skip: u64,
-        tags: Option<Vec<String>>
+        page: &FeedPage,
+        window: PopularWindow,
+        tags: Option<Vec<String>>,
+        include_nsfw: bool
     ) -> Result<Vec<Post>, AppError> {
         let mut filter = doc! {};
+        published_only(&mut filter);
+        hide_nsfw(&mut filter, include_nsfw);
 
         if let Some(tags) = tags {
             if !tags.is_empty() {
@@ -174,29 +269,258 @@ impl PostRepository {
             }
         }
 
-        let options = FindOptions::builder()
-            .sort(doc! { "likes": -1 })
-            .limit(Some(limit as i64))
-            .skip(Some(skip))
-            .build();
+        if let Some(cutoff) = window.cutoff(Utc::now()) {
+            filter.insert("created_at", doc! { "$gte": bson::DateTime::from_chrono(cutoff) });
+        }
+
+        let sort = match window {
+            PopularWindow::Trending => FeedSort::Trending,
+            _ => FeedSort::Top,
+        };
+
+        self.find_feed(vec![], filter, sort, page, limit).await
+    }
+
+    /// An author's drafts and scheduled posts, most recently created first
+    pub async fn get_unpublished_by_author(
+        &self,
+        author_id: &str,
+        limit: u64,
+        page: &FeedPage
+    ) -> Result<Vec<Post>, AppError> {
+        let filter = doc! { "author_id": author_id, "status": { "$ne": "published" } };
+        self.find_feed(vec![], filter, FeedSort::Newest, page, limit).await
+    }
 
+    /// Scheduled posts whose publish time has passed
+    pub async fn get_due(&self, now: DateTime<Utc>) -> Result<Vec<Post>, AppError> {
+        let filter = doc! { "status": "scheduled", "publish_at": { "$lte": bson::DateTime::from_chrono(now) } };
         let cursor = self.coll
-            .find(filter, options).await
+            .find(filter, None).await
             .map_err(|e| AppError::InternalServerError(e.to_string()))?;
 
-        let posts: Vec<Post> = cursor
-            .try_collect().await
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Publishes a scheduled post dated `published_at`. `None` when it was edited or
+    /// unscheduled in the meantime.
+    pub async fn publish_scheduled(&self, id: &str, published_at: DateTime<Utc>) -> Result<Option<Post>, AppError> {
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.coll
+            .find_one_and_update(
+                doc! { "_id": id, "status": "scheduled" },
+                doc! {
+                    "$set": { "status": "published", "created_at": bson::DateTime::from_chrono(published_at) },
+                    "$unset": { "publish_at": "" }
+                },
+                options
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Which of the given media hashes some post still uses
+    pub async fn media_hashes_in_use(&self, hashes: &[String]) -> Result<Vec<String>, AppError> {
+        if hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let values = self.coll
+            .distinct("media.sha256", doc! { "media.sha256": { "$in": hashes } }, None).await
             .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(
+            values
+                .into_iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .filter(|hash| hashes.contains(hash))
+                .collect()
+        )
+    }
 
-        Ok(posts)
+    /// How many media of all posts point at each file, see MediaIndexRepository::backfill_refs
+    pub async fn media_hash_counts(&self) -> Result<Vec<(String, i64)>, AppError> {
+        let pipeline = vec![
+            doc! { "$unwind": "$media" },
+            doc! { "$match": { "media.sha256": { "$type": "string" } } },
+            doc! { "$group": { "_id": "$media.sha256", "count": { "$sum": 1 } } }
+        ];
+        let cursor = self.coll
+            .aggregate(pipeline, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let docs: Vec<Document> = cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(
+            docs
+                .into_iter()
+                .filter_map(|d| {
+                    let count = d.get_i32("count").ok()?;
+                    Some((d.get_str("_id").ok()?.to_string(), i64::from(count)))
+                })
+                .collect()
+        )
+    }
+
+    pub async fn ensure_media_hash_index(&self) -> Result<(), AppError> {
+        self.coll
+            .create_index(IndexModel::builder().keys(doc! { "media.sha256": 1 }).build(), None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Appends media to a post, `None` when the post is gone
+    pub async fn push_media(&self, id: &str, media: &Media) -> Result<Option<Post>, AppError> {
+        let media = to_document(media).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.coll
+            .find_one_and_update(doc! { "_id": id }, doc! { "$push": { "media": media } }, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    pub async fn set_comment_settings(&self, id: &str, settings: &CommentSettings) -> Result<Option<Post>, AppError> {
+        let settings = to_document(settings).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.coll
+            .find_one_and_update(doc! { "_id": id }, doc! { "$set": { "comment_settings": settings } }, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Unpins the comment if it is still the one pinned on the post. Returns the post when it was
+    pub async fn clear_pinned_comment(&self, id: &str, comment_id: &str) -> Result<Option<Post>, AppError> {
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.coll
+            .find_one_and_update(
+                doc! { "_id": id, "comment_settings.pinned_comment_id": comment_id },
+                doc! { "$set": { "comment_settings.pinned_comment_id": null } },
+                options
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Hands out the next revision number of a post. The counter lives only in the database
+    /// so concurrent edits can never be given the same number
+    pub async fn next_revision_number(&self, id: &str) -> Result<u64, AppError> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .projection(doc! { "revision_count": 1 })
+            .build();
+        let counter = self.coll
+            .clone_with_type::<Document>()
+            .find_one_and_update(doc! { "_id": id }, doc! { "$inc": { "revision_count": 1_i64 } }, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?
+            .ok_or(AppError::PostNotFound)?;
+
+        counter
+            .get_i64("revision_count")
+            .map(|n| n as u64)
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Starts the revision counter of posts edited before it existed after their latest revision
+    pub async fn backfill_revision_count(&self, id: &str, latest: u64) -> Result<bool, AppError> {
+        let result = self.coll
+            .update_one(
+                doc! { "_id": id, "revision_count": { "$exists": false } },
+                doc! { "$set": { "revision_count": latest as i64 } },
+                None
+            ).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(result.modified_count > 0)
+    }
+
+    /// Marks posts written before drafts existed as published
+    pub async fn backfill_status(&self) -> Result<u64, AppError> {
+        let result = self.coll
+            .update_many(doc! { "status": { "$exists": false } }, doc! { "$set": { "status": "published" } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(result.modified_count)
+    }
+
+    /// Atomically toggles a user's reaction, returning whether it is now set and the updated post
+    pub async fn toggle_reaction(&self, id: &str, user_id: &str, reaction: Reaction) -> Result<(bool, Post), AppError> {
+        reactions::toggle(&self.coll, id, user_id, reaction).await?.ok_or(AppError::PostNotFound)
+    }
+
+    /// One page of the ids of users that reacted with `reaction`
+    pub async fn get_reactors(&self, id: &str, reaction: Reaction, skip: u64, limit: u64) -> Result<Vec<String>, AppError> {
+        reactions::reactors(&self.coll, id, reaction, skip, limit).await?.ok_or(AppError::PostNotFound)
+    }
+
+    pub async fn backfill_reaction_counts(&self) -> Result<u64, AppError> {
+        reactions::backfill_counts(&self.coll).await
+    }
+
+    /// Counts a view of a post
+    pub async fn add_view(&self, id: &str) -> Result<(), AppError> {
+        self.coll
+            .update_one(doc! { "_id": id }, doc! { "$inc": { "views": 1 } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Recomputes the engagement of every post in one server-side pass, comment counts
+    /// included. Only posts created since `since` keep a trending score, older ones drop to 0
+    pub async fn rescore(&self, since: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), AppError> {
+        self.score(doc! {}, since, now).await
+    }
+
+    /// Scores posts written before engagement was stored, returns how many there were
+    pub async fn backfill_engagement(&self, since: DateTime<Utc>, now: DateTime<Utc>) -> Result<u64, AppError> {
+        let missing = doc! { "engagement": { "$exists": false } };
+        let count = self.coll
+            .count_documents(missing.clone(), None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        if count > 0 {
+            self.score(missing, since, now).await?;
+        }
+        Ok(count)
+    }
+
+    async fn score(&self, filter: Document, since: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), AppError> {
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! {
+                "$lookup": {
+                    "from": "comments",
+                    "let": { "post_id": "$_id" },
+                    "pipeline": [
+                        { "$match": { "$expr": { "$eq": ["$post_id", "$$post_id"] } } },
+                        { "$count": "count" }
+                    ],
+                    "as": "comments"
+                }
+            },
+            doc! { "$set": { "comment_count": { "$ifNull": [{ "$first": "$comments.count" }, 0] } } },
+            doc! { "$set": { "engagement": engagement_expr() } },
+            doc! {
+                "$set": {
+                    "trending_score": {
+                        "$cond": [{ "$gte": ["$created_at", bson::DateTime::from_chrono(since)] }, trending_expr(now), 0.0]
+                    }
+                }
+            },
+            doc! { "$project": { "engagement": 1, "trending_score": 1 } },
+            doc! { "$merge": { "into": "posts", "on": "_id", "whenMatched": "merge", "whenNotMatched": "discard" } }
+        ];
+
+        self.coll
+            .aggregate(pipeline, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
     }
 
     pub async fn get_random_posts(
         &self,
         limit: u64,
-        tags: Option<Vec<String>>
+        tags: Option<Vec<String>>,
+        include_nsfw: bool
     ) -> Result<Vec<Post>, AppError> {
-        let mut pipeline: Vec<Document> = vec![];
+        let mut pipeline: Vec<Document> = vec![doc! { "$match": { "status": "published" } }];
+
+        if !include_nsfw {
+            pipeline.push(doc! { "$match": { "nsfw": false } });
+        }
 
         if let Some(tags) = tags {
             if !tags.is_empty() {
@@ -232,10 +556,11 @@ impl PostRepository {
     pub async fn get_premium_posts(
         &self,
         limit: u64,
-        skip: u64,
-        tags: Option<Vec<String>>
+        page: &FeedPage,
+        tags: Option<Vec<String>>,
+        include_nsfw: bool
     ) -> Result<Vec<Post>, AppError> {
-        let mut pipeline: Vec<Document> = vec![
+        let pipeline: Vec<Document> = vec![
             doc! {
             "$lookup": {
                 "from": "users",
@@ -251,28 +576,247 @@ impl PostRepository {
             "$match": {
                 "author_info.premium": true
             }
-        },
-            doc! {
-            "$sort": { "created_at": -1 }
-        },
-            doc! {
-            "$skip": skip as i64
-        },
-            doc! {
-            "$limit": limit as i64
         }
         ];
 
+        let mut filter = doc! {};
+        published_only(&mut filter);
+        hide_nsfw(&mut filter, include_nsfw);
+
         if let Some(tags) = tags {
             if !tags.is_empty() {
-                pipeline.insert(
-                    3,
-                    doc! {
-                "$match": { "tags": { "$in": tags } }
+                filter.insert("tags", doc! { "$in": tags });
             }
-                );
+        }
+
+        self.find_feed(pipeline, filter, FeedSort::Newest, page, limit).await
+    }
+
+    /// Runs a free-form post search (used by the search endpoint)
+    pub async fn search(
+        &self,
+        filter: Document,
+        sort: FeedSort,
+        page: &FeedPage,
+        limit: u64
+    ) -> Result<Vec<Post>, AppError> {
+        self.find_feed(vec![], filter, sort, page, limit).await
+    }
+
+    /// Creates the indexes search relies on, replacing a text index that no longer matches ours
+    pub async fn ensure_search_indexes(&self) -> Result<(), AppError> {
+        let text_index = IndexModel::builder()
+            .keys(doc! { "title": "text", "tags": "text", "body": "text" })
+            .options(
+                IndexOptions::builder()
+                    .name(SEARCH_INDEX.to_string())
+                    .weights(doc! { "title": 10, "tags": 5, "body": 1 })
+                    .build()
+            )
+            .build();
+
+        // drop any other text index, mongo refuses to create a second one
+        let mut existing = self.coll
+            .list_indexes(None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        while
+            let Some(index) = existing
+                .try_next().await
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        {
+            let name = index.options.and_then(|o| o.name).unwrap_or_default();
+            if index.keys.contains_key("_fts") && name != SEARCH_INDEX {
+                log::info!("Dropping outdated text index {}", name);
+                self.coll
+                    .drop_index(name, None).await
+                    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+            }
+        }
+
+        if self.coll.create_index(text_index.clone(), None).await.is_err() {
+            // ours exists with other fields or weights, rebuild it
+            log::info!("Rebuilding text index {}", SEARCH_INDEX);
+            self.coll.drop_index(SEARCH_INDEX, None).await.ok();
+            self.coll
+                .create_index(text_index, None).await
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        }
+
+        let indexes = vec![
+            IndexModel::builder().keys(doc! { "tags": 1 }).build(),
+            IndexModel::builder().keys(doc! { "author_id": 1, "created_at": -1 }).build(),
+            IndexModel::builder().keys(doc! { "created_at": -1, "_id": -1 }).build()
+        ];
+        self.coll
+            .create_indexes(indexes, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Total matches plus tag and author counts for a search filter, in one round trip
+    pub async fn search_facets(&self, filter: Document) -> Result<(u64, SearchFacets), AppError> {
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! {
+            "$facet": {
+                "total": [{ "$count": "count" }],
+                "tags": [
+                    { "$unwind": "$tags" },
+                    { "$group": { "_id": "$tags", "count": { "$sum": 1 } } },
+                    { "$sort": { "count": -1, "_id": 1 } },
+                    { "$limit": 20 }
+                ],
+                "authors": [
+                    { "$group": { "_id": "$author", "count": { "$sum": 1 } } },
+                    { "$sort": { "count": -1, "_id": 1 } },
+                    { "$limit": 10 }
+                ]
             }
         }
+        ];
+
+        let mut cursor = self.coll
+            .aggregate(pipeline, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        let Some(result) = cursor
+            .try_next().await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))? else {
+            return Ok((0, SearchFacets::default()));
+        };
+
+        let buckets = |name: &str| -> Vec<FacetCount> {
+            result
+                .get_array(name)
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(Bson::as_document)
+                        .filter_map(|item| {
+                            Some(FacetCount {
+                                value: item.get_str("_id").ok()?.to_string(),
+                                count: bson_count(item.get("count")?),
+                            })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let total = result
+            .get_array("total")
+            .ok()
+            .and_then(|t| t.first())
+            .and_then(Bson::as_document)
+            .and_then(|t| t.get("count"))
+            .map(bson_count)
+            .unwrap_or(0);
+
+        Ok((total, SearchFacets { tags: buckets("tags"), authors: buckets("authors") }))
+    }
+
+    /// Tags most often used on the same posts as `tag`
+    pub async fn related_tags(&self, tag: &str, limit: u64) -> Result<Vec<FacetCount>, AppError> {
+        let pipeline = vec![
+            doc! { "$match": { "tags": tag, "status": "published" } },
+            doc! { "$unwind": "$tags" },
+            doc! { "$match": { "tags": { "$ne": tag } } },
+            doc! { "$group": { "_id": "$tags", "count": { "$sum": 1 } } },
+            doc! { "$sort": { "count": -1, "_id": 1 } },
+            doc! { "$limit": limit as i64 }
+        ];
+
+        let cursor = self.coll
+            .aggregate(pipeline, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let docs: Vec<Document> = cursor
+            .try_collect().await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(
+            docs
+                .iter()
+                .filter_map(|item| {
+                    Some(FacetCount {
+                        value: item.get_str("_id").ok()?.to_string(),
+                        count: bson_count(item.get("count")?),
+                    })
+                })
+                .collect()
+        )
+    }
+
+    /// Swaps `from` for `to` on every post, returning the ids that changed
+    pub async fn retag(&self, from: &str, to: &str) -> Result<Vec<String>, AppError> {
+        tagging::retag(&self.coll, from, to).await
+    }
+
+    /// Adds `implied` to every post tagged `tag`, returning the ids that changed
+    pub async fn add_implied_tag(&self, tag: &str, implied: &str) -> Result<Vec<String>, AppError> {
+        tagging::add_implied(&self.coll, tag, implied).await
+    }
+
+    /// How many published posts use the tag, drafts don't count
+    pub async fn count_tagged(&self, tag: &str) -> Result<u64, AppError> {
+        let mut filter = doc! {};
+        published_only(&mut filter);
+        tagging::count_tagged(&self.coll, tag, filter).await
+    }
+
+    /// Tag usage across published posts, drafts don't count
+    pub async fn tag_counts(&self) -> Result<Vec<(String, u64)>, AppError> {
+        let mut filter = doc! {};
+        published_only(&mut filter);
+        tagging::tag_counts(&self.coll, filter).await
+    }
+
+    pub async fn normalize_stored_tags(&self) -> Result<u64, AppError> {
+        tagging::normalize_stored(&self.coll).await
+    }
+
+    /// Best `$text` matches first, paged by offset since scores can't be seeked past
+    pub async fn search_by_relevance(&self, filter: Document, skip: u64, limit: u64) -> Result<Vec<Post>, AppError> {
+        let options = FindOptions::builder()
+            .projection(doc! { "score": { "$meta": "textScore" } })
+            .sort(doc! { "score": { "$meta": "textScore" }, "_id": 1 })
+            .skip(Some(skip))
+            .limit(Some(limit as i64))
+            .build();
+
+        let cursor = self.coll
+            .find(filter, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Shared feed query: leading `pipeline` stages, then `filter`, then either seeks past
+    /// the cursor or skips the old way, sorted by `sort` with `_id` as the tie breaker.
+    async fn find_feed(
+        &self,
+        mut pipeline: Vec<Document>,
+        filter: Document,
+        sort: FeedSort,
+        page: &FeedPage,
+        limit: u64
+    ) -> Result<Vec<Post>, AppError> {
+        if !filter.is_empty() {
+            pipeline.push(doc! { "$match": filter });
+        }
+
+        match page {
+            FeedPage::After(cursor) => {
+                pipeline.push(doc! { "$match": cursor.seek_filter(sort)? });
+                pipeline.push(doc! { "$sort": sort.sort_doc() });
+            }
+            FeedPage::Offset(skip) => {
+                pipeline.push(doc! { "$sort": sort.sort_doc() });
+                pipeline.push(doc! { "$skip": *skip as i64 });
+            }
+        }
+
+        pipeline.push(doc! { "$limit": limit as i64 });
 
         let mut cursor = self.coll
             .aggregate(pipeline, AggregateOptions::default()).await
@@ -294,3 +838,24 @@ impl PostRepository {
         Ok(posts)
     }
 }
+
+/// Leaves drafts and scheduled posts out of a listing
+fn published_only(filter: &mut Document) {
+    filter.insert("status", "published");
+}
+
+/// Leaves NSFW posts out of a listing unless the viewer opted into them
+fn hide_nsfw(filter: &mut Document, include_nsfw: bool) {
+    if !include_nsfw {
+        filter.insert("nsfw", false);
+    }
+}
+
+/// Reads a `$sum`/`$count` result, which comes back as int32 or int64 depending on size
+fn bson_count(value: &Bson) -> u64 {
+    match value {
+        Bson::Int32(n) => *n as u64,
+        Bson::Int64(n) => *n as u64,
+        _ => 0,
+    }
+}
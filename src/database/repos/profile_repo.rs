@@ -118,4 +118,20 @@ impl ProfileRepository {
 
         Ok(profiles)
     }
+
+    /// Profiles using any of the urls as their picture or banner
+    pub async fn get_by_picture_urls(&self, urls: &[String]) -> Result<Vec<DBProfile>, AppError> {
+        if urls.is_empty() {
+            return Ok(vec![]);
+        }
+        let filter = doc! {
+            "$or": [
+                { "profile_picture": { "$in": urls } },
+                { "banner_picture": { "$in": urls } },
+            ]
+        };
+
+        let cursor = self.coll.find(filter, None).await.map_err(|_| AppError::DBError)?;
+        cursor.try_collect().await.map_err(|_| AppError::DBError)
+    }
 }
@@ -0,0 +1,115 @@
+use bson::{ doc, Document };
+use futures::TryStreamExt;
+use mongodb::{ Collection, Database, IndexModel, options::{ FindOptions, IndexOptions } };
+use crate::models::revision::PostRevision;
+use crate::utils::error::AppError;
+
+#[derive(Clone)]
+pub struct RevisionRepository {
+    pub coll: Collection<PostRevision>,
+}
+
+impl RevisionRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            coll: db.collection("post_revisions"),
+        }
+    }
+
+    /// Revision numbers are unique per post, which also serves the history listing
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let index = IndexModel::builder()
+            .keys(doc! { "post_id": 1, "number": -1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.coll
+            .create_index(index, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub async fn create(&self, revision: &PostRevision) -> Result<(), AppError> {
+        self.coll.insert_one(revision, None).await.map_err(|_| AppError::DBError)?;
+        Ok(())
+    }
+
+    pub async fn get(&self, post_id: &str, id: &str) -> Result<Option<PostRevision>, AppError> {
+        self.coll
+            .find_one(doc! { "_id": id, "post_id": post_id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// A post's revisions, newest first
+    pub async fn get_by_post(&self, post_id: &str, skip: u64, limit: u64) -> Result<Vec<PostRevision>, AppError> {
+        let options = FindOptions::builder()
+            .sort(doc! { "number": -1 })
+            .skip(Some(skip))
+            .limit(Some(limit as i64))
+            .build();
+
+        let cursor = self.coll
+            .find(doc! { "post_id": post_id }, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Numbers the revisions of posts where racing edits were given the same number again,
+    /// oldest first. Returns how many posts were fixed
+    pub async fn renumber_duplicates(&self) -> Result<u64, AppError> {
+        let pipeline = vec![
+            doc! { "$group": { "_id": { "post_id": "$post_id", "number": "$number" }, "count": { "$sum": 1 } } },
+            doc! { "$match": { "count": { "$gt": 1 } } },
+            doc! { "$group": { "_id": "$_id.post_id" } }
+        ];
+        let cursor = self.coll
+            .aggregate(pipeline, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let posts: Vec<Document> = cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        let mut fixed = 0;
+        for post_id in posts.iter().filter_map(|d| d.get_str("_id").ok()) {
+            let options = FindOptions::builder().sort(doc! { "created_at": 1, "_id": 1 }).build();
+            let revisions: Vec<PostRevision> = self.coll
+                .find(doc! { "post_id": post_id }, options).await
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?
+                .try_collect().await
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+            for (i, revision) in revisions.iter().enumerate() {
+                self.coll
+                    .update_one(doc! { "_id": revision.id.to_string() }, doc! { "$set": { "number": (i + 1) as i64 } }, None).await
+                    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+            }
+            fixed += 1;
+        }
+        Ok(fixed)
+    }
+
+    /// The highest revision number of every post that has revisions
+    pub async fn latest_numbers(&self) -> Result<Vec<(String, u64)>, AppError> {
+        let pipeline = vec![doc! { "$group": { "_id": "$post_id", "latest": { "$max": "$number" } } }];
+        let cursor = self.coll
+            .aggregate(pipeline, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let docs: Vec<Document> = cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(
+            docs
+                .into_iter()
+                .filter_map(|d| {
+                    let post_id = d.get_str("_id").ok()?.to_string();
+                    let latest = d.get_i64("latest").or_else(|_| d.get_i32("latest").map(i64::from)).ok()?;
+                    Some((post_id, latest as u64))
+                })
+                .collect()
+        )
+    }
+
+    pub async fn delete_by_post(&self, post_id: &str) -> Result<(), AppError> {
+        self.coll
+            .delete_many(doc! { "post_id": post_id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use bson::{ doc, Document };
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::{ Collection, Database, options::{ FindOptions, UpdateOptions } };
+use crate::models::tag::{ Tag, TagKind };
+use crate::utils::error::AppError;
+
+#[derive(Clone)]
+pub struct TagRepository {
+    pub coll: Collection<Tag>,
+}
+
+impl TagRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            coll: db.collection("tags"),
+        }
+    }
+
+    pub async fn get(&self, name: &str) -> Result<Option<Tag>, AppError> {
+        self.coll
+            .find_one(doc! { "_id": name }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    pub async fn is_empty(&self) -> Result<bool, AppError> {
+        let count = self.coll
+            .estimated_document_count(None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(count == 0)
+    }
+
+    pub async fn get_many(&self, names: &[String]) -> Result<Vec<Tag>, AppError> {
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let cursor = self.coll
+            .find(doc! { "_id": { "$in": names } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Creates the registry entry for `name` if it doesn't exist yet
+    pub async fn ensure(&self, name: &str) -> Result<(), AppError> {
+        self.upsert(name, doc! {}).await
+    }
+
+    /// Bumps the usage count of each tag by `delta`, registering tags seen for the first time.
+    /// Counts never go below zero.
+    pub async fn add_usage(&self, names: &[String], kind: TagKind, delta: i64) -> Result<(), AppError> {
+        let field = kind.count_field();
+
+        if delta < 0 {
+            self.coll
+                .update_many(
+                    doc! { "_id": { "$in": names }, field: { "$gt": 0 } },
+                    doc! { "$inc": { field: delta } },
+                    None
+                ).await
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+            return Ok(());
+        }
+
+        for name in names {
+            self.upsert(name, doc! { "$inc": { field: delta } }).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Zeroes every usage count, before they are recounted from scratch
+    pub async fn reset_counts(&self) -> Result<(), AppError> {
+        self.coll
+            .update_many(doc! {}, doc! { "$set": { "post_count": 0_i64, "character_count": 0_i64 } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn set_count(&self, name: &str, kind: TagKind, count: u64) -> Result<(), AppError> {
+        self.upsert(name, doc! { "$set": { kind.count_field(): count as i64 } }).await
+    }
+
+    pub async fn set_description(&self, name: &str, description: Option<String>) -> Result<(), AppError> {
+        self.upsert(name, doc! { "$set": { "description": description } }).await
+    }
+
+    pub async fn set_alias(&self, name: &str, alias_of: Option<&str>) -> Result<(), AppError> {
+        self.upsert(name, doc! { "$set": { "alias_of": alias_of } }).await
+    }
+
+    /// Points every alias of `from` at `to` instead, so aliases never chain
+    pub async fn repoint_aliases(&self, from: &str, to: &str) -> Result<(), AppError> {
+        self.coll
+            .update_many(doc! { "alias_of": from }, doc! { "$set": { "alias_of": to } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn get_aliases(&self, name: &str) -> Result<Vec<String>, AppError> {
+        let cursor = self.coll
+            .find(doc! { "alias_of": name }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let tags: Vec<Tag> = cursor
+            .try_collect().await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(tags.into_iter().map(|t| t.name).collect())
+    }
+
+    pub async fn add_implication(&self, name: &str, implied: &str) -> Result<(), AppError> {
+        self.upsert(name, doc! { "$addToSet": { "implies": implied } }).await
+    }
+
+    pub async fn remove_implication(&self, name: &str, implied: &str) -> Result<(), AppError> {
+        self.coll
+            .update_one(doc! { "_id": name }, doc! { "$pull": { "implies": implied } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Makes every tag implying `from` imply `to` instead
+    pub async fn replace_implication(&self, from: &str, to: &str) -> Result<(), AppError> {
+        self.coll
+            .update_many(doc! { "implies": from }, doc! { "$addToSet": { "implies": to } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        self.coll
+            .update_many(doc! { "implies": from }, doc! { "$pull": { "implies": from } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        // `to` may have implied `from`, it shouldn't end up implying itself
+        self.remove_implication(to, to).await
+    }
+
+    /// Moves implications onto the tag an alias now points to
+    pub async fn merge_implications(&self, from: &str, to: &str) -> Result<(), AppError> {
+        if let Some(tag) = self.get(from).await? {
+            for implied in tag.implies.iter().filter(|t| t.as_str() != to) {
+                self.add_implication(to, implied).await?;
+            }
+        }
+        self.coll
+            .update_one(doc! { "_id": from }, doc! { "$set": { "implies": [] } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Tags starting with `prefix`, most used first
+    pub async fn autocomplete(&self, prefix: &str, limit: u64) -> Result<Vec<Tag>, AppError> {
+        let filter = doc! { "_id": { "$regex": format!("^{}", regex::escape(prefix)) } };
+        let options = FindOptions::builder()
+            .sort(doc! { "post_count": -1, "_id": 1 })
+            .limit(Some(limit as i64))
+            .build();
+
+        let cursor = self.coll
+            .find(filter, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Applies `update` to the tag, inserting an empty entry first if there is none
+    async fn upsert(&self, name: &str, mut update: Document) -> Result<(), AppError> {
+        let mut on_insert = doc! {
+            "description": null,
+            "alias_of": null,
+            "implies": [],
+            "post_count": 0_i64,
+            "character_count": 0_i64,
+            "created_at": bson::DateTime::from_chrono(Utc::now()),
+        };
+
+        // a field can't be in both $setOnInsert and the update itself
+        for op in update.values() {
+            if let Some(fields) = op.as_document() {
+                for key in fields.keys() {
+                    on_insert.remove(key);
+                }
+            }
+        }
+        if !on_insert.is_empty() {
+            update.insert("$setOnInsert", on_insert);
+        }
+
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.coll
+            .update_one(doc! { "_id": name }, update, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+}
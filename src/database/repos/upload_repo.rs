@@ -0,0 +1,124 @@
+use bson::doc;
+use chrono::{ DateTime, Utc };
+use futures::TryStreamExt;
+use mongodb::{ Collection, Database, IndexModel };
+use mongodb::options::{ FindOneAndUpdateOptions, FindOptions, ReturnDocument };
+use crate::models::upload::PendingUpload;
+use crate::utils::error::AppError;
+
+#[derive(Clone)]
+pub struct PendingUploadRepository {
+    pub coll: Collection<PendingUpload>,
+}
+
+impl PendingUploadRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            coll: db.collection("pending_uploads"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexModel::builder().keys(doc! { "post_id": 1 }).build(),
+            IndexModel::builder().keys(doc! { "expires_at": 1 }).build()
+        ];
+        self.coll
+            .create_indexes(indexes, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub async fn create_many(&self, uploads: &[PendingUpload]) -> Result<(), AppError> {
+        if uploads.is_empty() {
+            return Ok(());
+        }
+        self.coll.insert_many(uploads, None).await.map_err(|_| AppError::DBError)?;
+        Ok(())
+    }
+
+    /// Takes one unexpired upload of the post by the user for finalizing until `until`.
+    /// None when it doesn't exist or another finalize is already working on it
+    pub async fn claim(
+        &self,
+        id: &str,
+        post_id: &str,
+        user_id: &str,
+        now: DateTime<Utc>,
+        until: DateTime<Utc>
+    ) -> Result<Option<PendingUpload>, AppError> {
+        let now = bson::DateTime::from_chrono(now);
+        let filter =
+            doc! {
+            "_id": id,
+            "post_id": post_id,
+            "user_id": user_id,
+            "expires_at": { "$gt": now },
+            "claimed_until": { "$not": { "$gt": now } },
+        };
+        let update = doc! { "$set": { "claimed_until": bson::DateTime::from_chrono(until) } };
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.coll
+            .find_one_and_update(filter, update, options).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Lets the upload be finalized again
+    pub async fn unclaim(&self, id: &str) -> Result<(), AppError> {
+        self.coll
+            .update_one(doc! { "_id": id }, doc! { "$unset": { "claimed_until": "" } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Which of the given object keys still belong to a pending upload
+    pub async fn keys_in_use(&self, keys: &[String]) -> Result<Vec<String>, AppError> {
+        let used = self.coll
+            .distinct("key", doc! { "key": { "$in": keys } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(
+            used
+                .into_iter()
+                .filter_map(|key| key.as_str().map(str::to_string))
+                .collect()
+        )
+    }
+
+    pub async fn count_by_post(&self, post_id: &str) -> Result<u64, AppError> {
+        self.coll
+            .count_documents(doc! { "post_id": post_id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), AppError> {
+        self.coll
+            .delete_one(doc! { "_id": id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn delete_many(&self, ids: &[String]) -> Result<(), AppError> {
+        self.coll
+            .delete_many(doc! { "_id": { "$in": ids } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Drops the pending uploads of a deleted post
+    pub async fn delete_by_post(&self, post_id: &str) -> Result<(), AppError> {
+        self.coll
+            .delete_many(doc! { "post_id": post_id }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Oldest first, at most `limit` of them
+    pub async fn get_expired(&self, now: DateTime<Utc>, limit: i64) -> Result<Vec<PendingUpload>, AppError> {
+        let options = FindOptions::builder().sort(doc! { "expires_at": 1 }).limit(limit).build();
+        let cursor = self.coll
+            .find(doc! { "expires_at": { "$lte": bson::DateTime::from_chrono(now) } }, options).await
+            .map_err(|_| AppError::DBError)?;
+        cursor.try_collect().await.map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+}
@@ -0,0 +1,161 @@
+//! Bulk tag rewrites shared by every collection that keeps a `tags` array, used when a
+//! moderator merges a tag into another or adds an implication.
+
+use bson::{ doc, Bson, Document };
+use futures::TryStreamExt;
+use mongodb::{ Collection, options::FindOptions };
+
+use crate::utils::error::AppError;
+use crate::utils::tags::{ normalize_tag, normalize_tags };
+
+/// Ids of every document carrying `tag`
+pub async fn tagged_ids<T>(coll: &Collection<T>, tag: &str) -> Result<Vec<String>, AppError> {
+    let ids = coll
+        .distinct("_id", doc! { "tags": tag }, None).await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    Ok(
+        ids
+            .into_iter()
+            .filter_map(|id| match id {
+                Bson::String(id) => Some(id),
+                _ => None,
+            })
+            .collect()
+    )
+}
+
+/// Replaces `from` with `to` everywhere, without duplicating `to` where both were set.
+/// Returns the ids that changed so cached copies can be dropped.
+pub async fn retag<T>(coll: &Collection<T>, from: &str, to: &str) -> Result<Vec<String>, AppError> {
+    let ids = tagged_ids(coll, from).await?;
+    if ids.is_empty() {
+        return Ok(ids);
+    }
+
+    // pipeline update so the swap keeps the tag's position in the list
+    let update = vec![
+        doc! {
+        "$set": {
+            "tags": {
+                "$reduce": {
+                    "input": { "$map": { "input": "$tags", "in": { "$cond": [{ "$eq": ["$$this", from] }, to, "$$this"] } } },
+                    "initialValue": [],
+                    "in": {
+                        "$cond": [
+                            { "$in": ["$$this", "$$value"] },
+                            "$$value",
+                            { "$concatArrays": ["$$value", ["$$this"]] }
+                        ]
+                    }
+                }
+            }
+        }
+    }
+    ];
+
+    coll
+        .update_many(doc! { "_id": { "$in": &ids } }, update, None).await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    Ok(ids)
+}
+
+/// Adds `implied` to every document tagged `tag` that doesn't have it yet, returning their ids
+pub async fn add_implied<T>(coll: &Collection<T>, tag: &str, implied: &str) -> Result<Vec<String>, AppError> {
+    let filter = doc! { "$and": [{ "tags": tag }, { "tags": { "$ne": implied } }] };
+    let ids: Vec<String> = coll
+        .distinct("_id", filter.clone(), None).await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .filter_map(|id| match id {
+            Bson::String(id) => Some(id),
+            _ => None,
+        })
+        .collect();
+
+    if !ids.is_empty() {
+        coll
+            .update_many(filter, doc! { "$addToSet": { "tags": implied } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    }
+
+    Ok(ids)
+}
+
+/// How many documents matching `filter` carry `tag`, used to resync a registry count after a bulk rewrite
+pub async fn count_tagged<T>(coll: &Collection<T>, tag: &str, mut filter: Document) -> Result<u64, AppError> {
+    filter.insert("tags", tag);
+    coll
+        .count_documents(filter, None).await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+/// How many documents matching `filter` use each tag, used to fill the registry for data
+/// written before it existed
+pub async fn tag_counts<T>(coll: &Collection<T>, filter: Document) -> Result<Vec<(String, u64)>, AppError> {
+    let pipeline = vec![
+        doc! { "$match": filter },
+        doc! { "$unwind": "$tags" },
+        doc! { "$group": { "_id": "$tags", "count": { "$sum": 1 } } }
+    ];
+
+    let cursor = coll
+        .aggregate(pipeline, None).await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    let docs: Vec<Document> = cursor
+        .try_collect().await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    Ok(
+        docs
+            .iter()
+            .filter_map(|item| {
+                let count = match item.get("count")? {
+                    Bson::Int32(n) => *n as u64,
+                    Bson::Int64(n) => *n as u64,
+                    _ => return None,
+                };
+                Some((item.get_str("_id").ok()?.to_string(), count))
+            })
+            .collect()
+    )
+}
+
+/// Rewrites the tags of documents saved before tags were normalized on write, returning
+/// how many documents changed
+pub async fn normalize_stored<T>(coll: &Collection<T>) -> Result<u64, AppError> where T: Send + Sync {
+    let stale: Vec<String> = coll
+        .distinct("tags", doc! {}, None).await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .filter_map(|tag| match tag {
+            Bson::String(tag) if normalize_tag(&tag).as_deref() != Some(tag.as_str()) => Some(tag),
+            _ => None,
+        })
+        .collect();
+    if stale.is_empty() {
+        return Ok(0);
+    }
+
+    let raw = coll.clone_with_type::<Document>();
+    let mut cursor = raw
+        .find(doc! { "tags": { "$in": &stale } }, FindOptions::builder().projection(doc! { "tags": 1 }).build()).await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    let mut updated = 0;
+    while let Some(item) = cursor.try_next().await.map_err(|e| AppError::InternalServerError(e.to_string()))? {
+        let Ok(id) = item.get_str("_id") else {
+            continue;
+        };
+        let tags: Vec<String> = item
+            .get_array("tags")
+            .map(|tags| tags.iter().filter_map(|t| t.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+
+        raw.update_one(doc! { "_id": id }, doc! { "$set": { "tags": normalize_tags(&tags) } }, None).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        updated += 1;
+    }
+    Ok(updated)
+}
@@ -0,0 +1,67 @@
+#![allow(dead_code)]
+
+use crate::models::bookmark::{ BookmarkFolder, BookmarkListResponse };
+use crate::routes::internal::bookmarks::types::NewBookmark;
+
+#[utoipa::path(
+    get,
+    path = "/api/bookmarks",
+    params(
+        ("Authorization" = String, Header, description = "Bearer token for user."),
+        ("kind" = Option<String>, Query, description = "Only `post` or only `character` bookmarks"),
+        ("folder" = Option<String>, Query, description = "Only this folder, empty for unsorted bookmarks"),
+        ("limit" = Option<u64>, Query, description = "Bookmarks per page, at most 100"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page")
+    ),
+    responses(
+        (status = 200, description = "Your bookmarks, newest first. `post`/`character` is null when it is no longer visible.", body = BookmarkListResponse),
+        (status = 400, description = "Invalid cursor"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "Bookmarks"
+)]
+pub async fn get_bookmarks() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/bookmarks/folders",
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    responses(
+        (status = 200, description = "Your bookmark folders with how many bookmarks are in each", body = [BookmarkFolder]),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "Bookmarks"
+)]
+pub async fn get_bookmark_folders() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/bookmarks",
+    request_body(content = NewBookmark, description = "What to bookmark and which folder to put it in"),
+    responses(
+        (status = 200, description = "Bookmarked, or moved to the folder if it already was"),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Post or character not found")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    tag = "Bookmarks"
+)]
+pub async fn add_bookmark() {}
+
+#[utoipa::path(
+    delete,
+    path = "/api/bookmarks/{kind}/{target_id}",
+    params(
+        ("Authorization" = String, Header, description = "Bearer token for user."),
+        ("kind" = String, Path, description = "`post` or `character`"),
+        ("target_id" = String, Path, description = "Post or character UUID")
+    ),
+    responses(
+        (status = 200, description = "Bookmark removed"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Bookmark not found")
+    ),
+    tag = "Bookmarks"
+)]
+pub async fn remove_bookmark() {}
@@ -0,0 +1,131 @@
+#![allow(dead_code)]
+
+use crate::models::character::CharacterResponse;
+use crate::models::post::PostFeedResponse;
+use crate::routes::internal::characters::types::UserPatchCharacter;
+
+#[utoipa::path(
+    get,
+    path = "/api/characters/search",
+    params(
+        ("tags" = Option<String>, Query, description = "Comma-separated list of tags the character must have"),
+        ("page" = Option<u64>, Query, description = "Page number, starting at 1"),
+        ("limit" = Option<u64>, Query, description = "Items per page")
+    ),
+    responses(
+        (status = 200, description = "Matching characters fetched successfully", body = [CharacterResponse]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Characters"
+)]
+pub async fn search_characters() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/characters/new",
+    request_body(
+        content_type = "multipart/form-data",
+        description = "Form fields `name`, `description`, `tags` (JSON array), `media_meta` (JSON array of pose/emotion/notes matched to files by index) and one or more `media` files."
+    ),
+    responses(
+        (status = 200, description = "Character created successfully", body = CharacterResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for user creating the character.")),
+    tag = "Characters"
+)]
+pub async fn create_character() {}
+
+#[utoipa::path(
+    patch,
+    path = "/api/characters/edit/{id}",
+    params(("id" = String, Path, description = "Character UUID")),
+    request_body(content = UserPatchCharacter, description = "Data for updating a character"),
+    responses(
+        (status = 200, description = "Character updated successfully", body = CharacterResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Character not found")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for character owner.")),
+    tag = "Characters"
+)]
+pub async fn edit_character() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/characters/id/{id}",
+    params(("id" = String, Path, description = "Character UUID")),
+    responses(
+        (status = 200, description = "Character fetched successfully", body = CharacterResponse),
+        (status = 404, description = "Character not found")
+    ),
+    tag = "Characters"
+)]
+pub async fn get_character_by_id() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/characters/id/{id}/posts",
+    params(
+        ("id" = String, Path, description = "Character UUID"),
+        ("page" = Option<u64>, Query, description = "Page number, starting at 1 (deprecated, use cursor)"),
+        ("limit" = Option<u64>, Query, description = "Items per page"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page")
+    ),
+    responses(
+        (status = 200, description = "Posts featuring the character, newest first", body = PostFeedResponse),
+        (status = 404, description = "Character not found")
+    ),
+    tag = "Characters"
+)]
+pub async fn get_character_posts() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/characters/by/{username}",
+    params(
+        ("username" = String, Path, description = "Owner username"),
+        ("page" = Option<u64>, Query, description = "Page number, starting at 1"),
+        ("limit" = Option<u64>, Query, description = "Items per page")
+    ),
+    responses(
+        (status = 200, description = "Characters owned by the user", body = [CharacterResponse])
+    ),
+    tag = "Characters"
+)]
+pub async fn get_user_characters() {}
+
+#[utoipa::path(
+    delete,
+    path = "/api/characters/delete/{id}",
+    params(("id" = String, Path, description = "Character UUID")),
+    responses(
+        (status = 200, description = "Character deleted successfully"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Character not found")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for character owner.")),
+    tag = "Characters"
+)]
+pub async fn delete_character() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/characters/{id}/like",
+    params(("id" = String, Path, description = "Character UUID")),
+    responses((status = 200, description = "Character liked/unliked successfully")),
+    tag = "Characters",
+    params(("Authorization" = String, Header, description = "Bearer token for user."))
+)]
+pub async fn like_character() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/characters/{id}/dislike",
+    params(("id" = String, Path, description = "Character UUID")),
+    responses((status = 200, description = "Character disliked/undisliked successfully")),
+    tag = "Characters",
+    params(("Authorization" = String, Header, description = "Bearer token for user."))
+)]
+pub async fn dislike_character() {}
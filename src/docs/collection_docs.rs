@@ -0,0 +1,134 @@
+#![allow(dead_code)]
+
+use crate::models::collection::{ CollectionPageResponse, CollectionResponse };
+use crate::routes::internal::collections::types::{
+    AddCollectionPost,
+    NewCollection,
+    ReorderCollection,
+    UserPatchCollection,
+};
+
+#[utoipa::path(
+    post,
+    path = "/api/collections/new",
+    request_body(content = NewCollection, description = "Name, description and visibility of the collection"),
+    responses(
+        (status = 200, description = "Collection created successfully", body = CollectionResponse),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unauthorized")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    tag = "Collections"
+)]
+pub async fn create_collection() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/collections/by/{username}",
+    params(
+        ("username" = String, Path, description = "Owner username"),
+        ("page" = Option<u64>, Query, description = "Page number, starting at 1"),
+        ("limit" = Option<u64>, Query, description = "Items per page, at most 100")
+    ),
+    responses(
+        (status = 200, description = "The user's collections, recently changed first. Unlisted and private ones are only included for the owner.", body = [CollectionResponse]),
+        (status = 404, description = "Profile not found")
+    ),
+    tag = "Collections"
+)]
+pub async fn get_user_collections() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/collections/id/{id}",
+    params(
+        ("id" = String, Path, description = "Collection UUID"),
+        ("page" = Option<u64>, Query, description = "Page number, starting at 1"),
+        ("limit" = Option<u64>, Query, description = "Posts per page, at most 100")
+    ),
+    responses(
+        (status = 200, description = "The collection and one page of its posts in order", body = CollectionPageResponse),
+        (status = 404, description = "Collection not found")
+    ),
+    tag = "Collections"
+)]
+pub async fn get_collection() {}
+
+#[utoipa::path(
+    patch,
+    path = "/api/collections/id/{id}",
+    params(("id" = String, Path, description = "Collection UUID")),
+    request_body(content = UserPatchCollection, description = "Fields to change"),
+    responses(
+        (status = 200, description = "Collection updated successfully", body = CollectionResponse),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Collection not found")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for collection owner.")),
+    tag = "Collections"
+)]
+pub async fn edit_collection() {}
+
+#[utoipa::path(
+    delete,
+    path = "/api/collections/id/{id}",
+    params(("id" = String, Path, description = "Collection UUID")),
+    responses(
+        (status = 200, description = "Collection deleted successfully, its posts are left alone"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Collection not found")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for collection owner.")),
+    tag = "Collections"
+)]
+pub async fn delete_collection() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/collections/id/{id}/posts",
+    params(("id" = String, Path, description = "Collection UUID")),
+    request_body(content = AddCollectionPost, description = "Post to add and where"),
+    responses(
+        (status = 200, description = "Post added"),
+        (status = 400, description = "Already in the collection or the collection is full"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Collection or post not found")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for collection owner.")),
+    tag = "Collections"
+)]
+pub async fn add_collection_post() {}
+
+#[utoipa::path(
+    delete,
+    path = "/api/collections/id/{id}/posts/{post_id}",
+    params(
+        ("id" = String, Path, description = "Collection UUID"),
+        ("post_id" = String, Path, description = "Post UUID")
+    ),
+    responses(
+        (status = 200, description = "Post removed"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Collection not found or post not in it")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for collection owner.")),
+    tag = "Collections"
+)]
+pub async fn remove_collection_post() {}
+
+#[utoipa::path(
+    put,
+    path = "/api/collections/id/{id}/order",
+    params(("id" = String, Path, description = "Collection UUID")),
+    request_body(content = ReorderCollection, description = "Every post id in the collection, in the new order"),
+    responses(
+        (status = 200, description = "Posts reordered"),
+        (status = 400, description = "The list doesn't match the collection's posts"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Collection not found")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for collection owner.")),
+    tag = "Collections"
+)]
+pub async fn reorder_collection() {}
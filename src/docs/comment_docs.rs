@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 use crate::{
-    models::comment::{ Comment, CommentReqInput, Reply },
+    models::comment::{ CommentPage, CommentReqInput, CommentResponse, PostCommentsResponse },
+    models::post::CommentSettings,
     routes::internal::comment::types::{
+        CommentEditInput,
         CommentReplyInput,
-        CommentWithRepliesFlag,
+        CommentSettingsPatch,
         DislikeResponse,
         ToggleResponse,
     },
@@ -12,9 +14,15 @@ use crate::{
 #[utoipa::path(
     get,
     path = "/api/comments/fetch/{id}",
-    params(("id" = String, Path, description = "Post ID to fetch comments for")),
+    params(
+        ("id" = String, Path, description = "Post ID to fetch comments for"),
+        ("sort" = Option<String>, Query, description = "top, newest (default) or oldest"),
+        ("limit" = Option<u64>, Query, description = "Comments per page, at most 100 (default 20)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page")
+    ),
     responses(
-        (status = 200, description = "List of comments for post", body = [CommentWithRepliesFlag]),
+        (status = 200, description = "One page of the post's top-level comments, with the pinned one and the post's comment settings", body = PostCommentsResponse),
+        (status = 400, description = "Invalid cursor"),
         (status = 404, description = "Post not found")
     ),
     tag = "Comments"
@@ -28,15 +36,123 @@ pub async fn get_comments() {}
     params(("id" = String, Path, description = "Post ID to comment on")),
     params(("Authorization" = String, Header, description = "Bearer token for user.")),
     responses(
-        (status = 200, description = "Comment created successfully", body = Comment),
-        (status = 400, description = "Invalid input data"),
-        (status = 401, description = "Unauthorized"),
+        (status = 200, description = "Comment created as the signed-in user, @mentions resolved. `pending` when held for approval", body = CommentResponse),
+        (status = 400, description = "Invalid input data or comments are locked"),
+        (status = 401, description = "Unauthorized, or the post only takes comments from followers"),
+        (status = 404, description = "Post not found"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Comments"
 )]
 pub async fn post_comment() {}
 
+#[utoipa::path(
+    patch,
+    path = "/api/comments/{id}",
+    request_body = CommentEditInput,
+    params(("id" = String, Path, description = "Comment or reply ID to edit")),
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    responses(
+        (status = 200, description = "Comment edited", body = CommentResponse),
+        (status = 400, description = "Edit window has passed or the content is empty"),
+        (status = 401, description = "Not the author of this comment"),
+        (status = 404, description = "Comment not found")
+    ),
+    tag = "Comments"
+)]
+pub async fn edit_comment() {}
+
+#[utoipa::path(
+    delete,
+    path = "/api/comments/{id}",
+    params(("id" = String, Path, description = "Comment or reply ID to delete")),
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    responses(
+        (status = 200, description = "Comment deleted, or left as a [deleted] tombstone when it has replies"),
+        (status = 401, description = "Not the author of this comment or of the post it is on"),
+        (status = 404, description = "Comment not found")
+    ),
+    tag = "Comments"
+)]
+pub async fn delete_comment() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/comments/pending/{post_id}",
+    params(
+        ("post_id" = String, Path, description = "Post ID to list held comments for"),
+        ("limit" = Option<u64>, Query, description = "Comments per page, at most 100 (default 20)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    responses(
+        (status = 200, description = "Comments and replies waiting for approval, oldest first", body = CommentPage),
+        (status = 401, description = "Not the author of this post"),
+        (status = 404, description = "Post not found")
+    ),
+    tag = "Comments"
+)]
+pub async fn get_pending_comments() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/comments/{id}/approve",
+    params(("id" = String, Path, description = "Held comment or reply ID to approve")),
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    responses(
+        (status = 200, description = "Comment approved and shown to everyone", body = CommentResponse),
+        (status = 400, description = "Comment is not waiting for approval"),
+        (status = 401, description = "Not the author of the post"),
+        (status = 404, description = "Comment not found")
+    ),
+    tag = "Comments"
+)]
+pub async fn approve_comment() {}
+
+#[utoipa::path(
+    patch,
+    path = "/api/comments/settings/{post_id}",
+    request_body = CommentSettingsPatch,
+    params(("post_id" = String, Path, description = "Post ID to change comment settings for")),
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    responses(
+        (status = 200, description = "Updated comment settings, fields left out are unchanged", body = CommentSettings),
+        (status = 401, description = "Not the author of this post"),
+        (status = 404, description = "Post not found")
+    ),
+    tag = "Comments"
+)]
+pub async fn update_comment_settings() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/comments/{id}/pin",
+    params(("id" = String, Path, description = "Top-level comment ID to pin")),
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    responses(
+        (status = 200, description = "Comment pinned, replacing any pinned before", body = CommentSettings),
+        (status = 400, description = "Replies can't be pinned"),
+        (status = 401, description = "Not the author of the post"),
+        (status = 404, description = "Comment not found")
+    ),
+    tag = "Comments"
+)]
+pub async fn pin_comment() {}
+
+#[utoipa::path(
+    delete,
+    path = "/api/comments/pin/{post_id}",
+    params(("post_id" = String, Path, description = "Post ID to unpin the comment of")),
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    responses(
+        (status = 200, description = "Comment unpinned", body = CommentSettings),
+        (status = 401, description = "Not the author of this post"),
+        (status = 404, description = "Post not found")
+    ),
+    tag = "Comments"
+)]
+pub async fn unpin_comment() {}
+
 #[utoipa::path(
     post,
     path = "/api/comments/{id}/like",
@@ -72,8 +188,9 @@ pub async fn dislike_comment() {}
     request_body = CommentReplyInput,
     params(("Authorization" = String, Header, description = "Bearer token for user.")),
     responses(
-        (status = 200, description = "Reply added successfully", body = Reply),
-        (status = 401, description = "Unauthorized"),
+        (status = 200, description = "Reply added successfully. `pending` when held for approval", body = CommentResponse),
+        (status = 400, description = "Parent is nested too deep to reply to, or comments are locked"),
+        (status = 401, description = "Unauthorized, or the post only takes comments from followers"),
         (status = 404, description = "Parent comment not found")
     ),
     tag = "Comments"
@@ -83,10 +200,15 @@ pub async fn reply_to_comment() {}
 #[utoipa::path(
     get,
     path = "/api/comments/reply/{comment_id}",
-    params(("comment_id" = String, Path, description = "Comment ID to fetch replies for")),
+    params(
+        ("comment_id" = String, Path, description = "Comment or reply ID to fetch direct replies for"),
+        ("sort" = Option<String>, Query, description = "top, newest or oldest (default)"),
+        ("limit" = Option<u64>, Query, description = "Replies per page, at most 100 (default 20)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page")
+    ),
     responses(
-        (status = 200, description = "List of replies for comment", body = [Reply]),
-        (status = 404, description = "Comment not found")
+        (status = 200, description = "One page of direct replies", body = CommentPage),
+        (status = 400, description = "Invalid cursor")
     ),
     tag = "Comments"
 )]
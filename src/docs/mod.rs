@@ -8,6 +8,11 @@ mod profiles_docs;
 mod reporting_docs;
 mod session_docs;
 mod userassets_docs;
+mod character_docs;
+mod tag_docs;
+mod collection_docs;
+mod bookmark_docs;
+mod notification_docs;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -15,16 +20,23 @@ mod userassets_docs;
         // Posts endpoints
         post_docs::search_posts,
         post_docs::create_post,
+        post_docs::request_uploads,
+        post_docs::finalize_uploads,
         post_docs::edit_post,
         post_docs::get_post_by_id,
         post_docs::delete_post,
         post_docs::get_latest_posts,
+        post_docs::get_following_posts,
+        post_docs::get_drafts,
         post_docs::get_popular_posts,
         post_docs::get_premium_posts,
         post_docs::get_random_posts,
         post_docs::get_post,
         post_docs::like_post,
         post_docs::dislike_post,
+        post_docs::get_post_likes,
+        post_docs::get_post_revisions,
+        post_docs::restore_post_revision,
         post_docs::get_a_random_post,
 
         // User endpoints
@@ -38,6 +50,13 @@ mod userassets_docs;
         // Comment endpoints
         comment_docs::get_comments,
         comment_docs::post_comment,
+        comment_docs::edit_comment,
+        comment_docs::delete_comment,
+        comment_docs::get_pending_comments,
+        comment_docs::approve_comment,
+        comment_docs::update_comment_settings,
+        comment_docs::pin_comment,
+        comment_docs::unpin_comment,
         comment_docs::like_comment,
         comment_docs::dislike_comment,
         comment_docs::reply_to_comment,
@@ -67,22 +86,72 @@ mod userassets_docs;
 
         // User asset endpoints
         userassets_docs::upload_profile_picture,
-        userassets_docs::upload_banner
+        userassets_docs::upload_banner,
+
+        // Character endpoints
+        character_docs::search_characters,
+        character_docs::create_character,
+        character_docs::edit_character,
+        character_docs::get_character_by_id,
+        character_docs::get_character_posts,
+        character_docs::get_user_characters,
+        character_docs::delete_character,
+        character_docs::like_character,
+        character_docs::dislike_character,
+
+        // Tag endpoints
+        tag_docs::autocomplete_tags,
+        tag_docs::get_tag,
+
+        // Collection endpoints
+        collection_docs::create_collection,
+        collection_docs::get_user_collections,
+        collection_docs::get_collection,
+        collection_docs::edit_collection,
+        collection_docs::delete_collection,
+        collection_docs::add_collection_post,
+        collection_docs::remove_collection_post,
+        collection_docs::reorder_collection,
+
+        // Bookmarks endpoints
+        bookmark_docs::get_bookmarks,
+        bookmark_docs::get_bookmark_folders,
+        bookmark_docs::add_bookmark,
+        bookmark_docs::remove_bookmark,
+
+        // Notifications endpoints
+        notification_docs::get_notifications,
+        notification_docs::get_unread_count,
+        notification_docs::mark_notifications_read
     ),
     components(
         schemas(
             // Posts
             crate::routes::internal::posts::types::UserPatchPost,
+            crate::routes::internal::posts::types::UploadRequest,
+            crate::routes::internal::posts::types::FinalizeUploadsRequest,
+            crate::models::upload::NewUpload,
+            crate::models::upload::UploadTicket,
+            crate::models::upload::UploadTicketsResponse,
+            crate::utils::post::PostStatus,
+            crate::models::revision::PostRevision,
+            crate::models::revision::PostSnapshot,
+            crate::models::revision::FieldChange,
 
             // Users
             crate::models::settings::UserSettings,
             crate::models::profile::DBProfile,
             crate::models::user::User,
+            crate::models::profile::ProfileSummary,
+            crate::models::reaction::Reaction,
 
             // Comments
             crate::models::comment::Comment,
-            crate::models::comment::CommentReplies,
-            crate::models::comment::Reply,
+            crate::models::comment::CommentResponse,
+            crate::models::comment::CommentPage,
+            crate::models::comment::Mention,
+            crate::models::comment::PostCommentsResponse,
+            crate::models::post::CommentSettings,
 
             // Reports
             crate::models::report::Report,
@@ -90,7 +159,37 @@ mod userassets_docs;
             crate::models::report::ReportStatus,
 
             // Sessions
-            crate::routes::internal::session::AuthResponse
+            crate::routes::internal::session::AuthResponse,
+
+            // Characters
+            crate::models::character::CharacterResponse,
+            crate::routes::internal::characters::types::UserPatchCharacter,
+            crate::routes::internal::characters::types::CharacterMediaMeta,
+
+            // Tags
+            crate::models::tag::TagSuggestion,
+            crate::models::tag::TagPageResponse,
+
+            // Collections
+            crate::models::collection::CollectionVisibility,
+            crate::models::collection::CollectionSummary,
+            crate::routes::internal::collections::types::NewCollection,
+            crate::routes::internal::collections::types::UserPatchCollection,
+            crate::routes::internal::collections::types::AddCollectionPost,
+            crate::routes::internal::collections::types::ReorderCollection,
+
+            // Bookmarks
+            crate::models::bookmark::BookmarkKind,
+            crate::models::bookmark::BookmarkResponse,
+            crate::models::bookmark::BookmarkListResponse,
+            crate::models::bookmark::BookmarkFolder,
+            crate::routes::internal::bookmarks::types::NewBookmark,
+
+            // Notifications
+            crate::models::notification::NotificationKind,
+            crate::models::notification::NotificationResponse,
+            crate::models::notification::NotificationListResponse,
+            crate::routes::internal::notifications::types::MarkReadRequest
         )
     ),
     tags(
@@ -101,7 +200,12 @@ mod userassets_docs;
         (name = "Profile", description = "All profiles-related endpoints"),
         (name = "Reporting", description = "All reporting-related endpoints"),
         (name = "Session", description = "All session-related endpoints"),
-        (name = "User Assets", description = "All userasset-related endpoints")
+        (name = "User Assets", description = "All userasset-related endpoints"),
+        (name = "Characters", description = "All character-related endpoints"),
+        (name = "Tags", description = "All tag-related endpoints"),
+        (name = "Collections", description = "All collection-related endpoints"),
+        (name = "Bookmarks", description = "All bookmark-related endpoints"),
+        (name = "Notifications", description = "All notification-related endpoints")
     )
 )]
 pub struct ApiDoc;
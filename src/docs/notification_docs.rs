@@ -0,0 +1,48 @@
+#![allow(dead_code)]
+
+use crate::models::notification::NotificationListResponse;
+use crate::routes::internal::notifications::types::MarkReadRequest;
+
+#[utoipa::path(
+    get,
+    path = "/api/notifications",
+    params(
+        ("Authorization" = String, Header, description = "Bearer token for user."),
+        ("unread_only" = Option<bool>, Query, description = "Leave out notifications already read"),
+        ("limit" = Option<u64>, Query, description = "Notifications per page, at most 100"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page")
+    ),
+    responses(
+        (status = 200, description = "Your notifications, newest first. Follows and likes on the same thing are grouped while unread, `actor_count` is the size of the group.", body = NotificationListResponse),
+        (status = 400, description = "Invalid cursor"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "Notifications"
+)]
+pub async fn get_notifications() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/notifications/unread_count",
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    responses(
+        (status = 200, description = "How many of your notifications are unread"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "Notifications"
+)]
+pub async fn get_unread_count() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/notifications/read",
+    request_body(content = MarkReadRequest, description = "Which notifications to mark read, all of them without ids"),
+    responses(
+        (status = 200, description = "How many were marked and how many are still unread"),
+        (status = 400, description = "Too many ids"),
+        (status = 401, description = "Unauthorized")
+    ),
+    params(("Authorization" = String, Header, description = "Bearer token for user.")),
+    tag = "Notifications"
+)]
+pub async fn mark_notifications_read() {}
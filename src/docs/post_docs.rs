@@ -1,20 +1,25 @@
 #![allow(dead_code)]
 
-use crate::routes::internal::posts::types::UserPatchPost;
-use crate::models::post::PostResponse;
+use crate::routes::internal::posts::types::{ FinalizeUploadsRequest, UploadRequest, UserPatchPost };
+use crate::models::upload::UploadTicketsResponse;
+use crate::models::post::{ PostDetailResponse, PostFeedResponse, PostResponse };
+use crate::models::search::SearchResponse;
 
 #[utoipa::path(
     get,
     path = "/api/posts",
     params(
-        ("query" = Option<String>, Query, description = "Search query text"),
-        ("tags" = Option<String>, Query, description = "Comma-separated list of tags"),
+        ("query" = Option<String>, Query, description = "Search text. Supports `\"exact phrases\"`, `prefix*`, `-tag` to exclude a tag, `tag:name` or `#name` to require one, `author:username` and `type:post_type`"),
+        ("tags" = Option<String>, Query, description = "Comma-separated list of tags, posts need any of them"),
         ("author" = Option<String>, Query, description = "Filter by author username"),
-        ("page" = Option<u32>, Query, description = "Page number"),
-        ("limit" = Option<u32>, Query, description = "Items per page")
+        ("page" = Option<u32>, Query, description = "Page number, starting at 1. The only way to page relevance sorted results"),
+        ("limit" = Option<u32>, Query, description = "Items per page"),
+        ("sort" = Option<String>, Query, description = "relevance (default with search text), createdAt_desc (default otherwise), createdAt_asc or likes_desc"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page, not for relevance sorting")
     ),
     responses(
-        (status = 200, description = "List of posts successfully fetched", body = [PostResponse]),
+        (status = 200, description = "Matching posts with the total, tag/author facets and highlighted snippets", body = SearchResponse),
+        (status = 400, description = "Invalid cursor"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Posts"
@@ -27,7 +32,8 @@ pub async fn search_posts() {}
     request_body(content = UserPatchPost, description = "Data for creating a post"),
     responses(
         (status = 200, description = "Post created successfully", body = PostResponse),
-        (status = 400, description = "Bad request"),
+        (status = 400, description = "Bad request, more than 10 files, a file whose content does not match its type, or a banned file"),
+        (status = 413, description = "A file or the whole upload is too large"),
         (status = 500, description = "Internal server error")
     ),
     params((
@@ -39,6 +45,43 @@ pub async fn search_posts() {}
 )]
 pub async fn create_post() {}
 
+#[utoipa::path(
+    post,
+    path = "/api/posts/{id}/uploads",
+    params(
+        ("id" = String, Path, description = "UUID of a draft or scheduled post"),
+        ("Authorization" = String, Header, description = "Bearer token for post owner.")
+    ),
+    request_body(content = UploadRequest, description = "Type, size and SHA-256 of each file"),
+    responses(
+        (status = 200, description = "Presigned URLs to upload each file to, valid for 15 minutes", body = UploadTicketsResponse),
+        (status = 400, description = "Published post, unsupported type, bad or banned hash, or more than 10 files on the post"),
+        (status = 401, description = "Not the author of this post"),
+        (status = 404, description = "Post not found"),
+        (status = 413, description = "A file is too large")
+    ),
+    tag = "Posts"
+)]
+pub async fn request_uploads() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/posts/{id}/uploads/finalize",
+    params(
+        ("id" = String, Path, description = "Post UUID"),
+        ("Authorization" = String, Header, description = "Bearer token for post owner.")
+    ),
+    request_body(content = FinalizeUploadsRequest, description = "Uploads to attach, in order"),
+    responses(
+        (status = 200, description = "The post with the uploads attached as media", body = PostResponse),
+        (status = 400, description = "Unknown or expired upload, not uploaded yet, or size, type or hash mismatch. Mismatched uploads are deleted"),
+        (status = 401, description = "Not the author of this post"),
+        (status = 404, description = "Post not found")
+    ),
+    tag = "Posts"
+)]
+pub async fn finalize_uploads() {}
+
 #[utoipa::path(
     patch,
     path = "/api/posts/edit/{id}",
@@ -59,7 +102,7 @@ pub async fn edit_post() {}
     path = "/api/posts/id/{id}",
     params(("id" = String, Path, description = "Post UUID")),
     responses(
-        (status = 200, description = "Post fetched successfully", body = PostResponse),
+        (status = 200, description = "Post fetched successfully, with the public collections it is in", body = PostDetailResponse),
         (status = 404, description = "Post not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -86,24 +129,64 @@ pub async fn delete_post() {}
     path = "/api/posts/latest",
     params(
         ("amount" = Option<u64>, Query, description = "Number of posts to fetch"),
-        ("displacement" = Option<u64>, Query, description = "Number of posts to skip")
+        ("displacement" = Option<u64>, Query, description = "Number of posts to skip (deprecated, use cursor)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page")
     ),
     responses(
-        (status = 200, description = "Latest posts fetched successfully", body = [PostResponse])
+        (status = 200, description = "Latest posts fetched successfully with a cursor; without one the bare post array, next cursor in the `x-next-cursor` header", body = PostFeedResponse),
+        (status = 400, description = "Invalid cursor")
     ),
     tag = "Posts"
 )]
 pub async fn get_latest_posts() {}
 
+#[utoipa::path(
+    get,
+    path = "/api/posts/following",
+    params(
+        ("amount" = Option<u64>, Query, description = "Number of posts to fetch, at most 100"),
+        ("displacement" = Option<u64>, Query, description = "Number of posts to skip (deprecated, use cursor)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page"),
+        ("Authorization" = String, Header, description = "Bearer token for user.")
+    ),
+    responses(
+        (status = 200, description = "Newest posts from followed accounts, NSFW hidden unless enabled in settings", body = PostFeedResponse),
+        (status = 400, description = "Invalid cursor"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "Posts"
+)]
+pub async fn get_following_posts() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/posts/drafts",
+    params(
+        ("limit" = Option<u64>, Query, description = "Number of posts to fetch, at most 100"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page"),
+        ("Authorization" = String, Header, description = "Bearer token for user.")
+    ),
+    responses(
+        (status = 200, description = "The user's drafts and scheduled posts, newest first", body = PostFeedResponse),
+        (status = 400, description = "Invalid cursor"),
+        (status = 401, description = "Unauthorized")
+    ),
+    tag = "Posts"
+)]
+pub async fn get_drafts() {}
+
 #[utoipa::path(
     get,
     path = "/api/posts/popular",
     params(
         ("amount" = Option<u64>, Query, description = "Number of posts to fetch"),
-        ("displacement" = Option<u64>, Query, description = "Number of posts to skip")
+        ("displacement" = Option<u64>, Query, description = "Number of posts to skip (deprecated, use cursor)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page"),
+        ("window" = Option<String>, Query, description = "`trending` (default) ranks all posts by their age-decayed score, `day`, `week`, `month` and `all` rank posts created in that window by total engagement")
     ),
     responses(
-        (status = 200, description = "Popular posts fetched successfully", body = [PostResponse])
+        (status = 200, description = "Popular posts fetched successfully with a cursor; without one the bare post array, next cursor in the `x-next-cursor` header", body = PostFeedResponse),
+        (status = 400, description = "Invalid cursor or window")
     ),
     tag = "Posts"
 )]
@@ -114,10 +197,12 @@ pub async fn get_popular_posts() {}
     path = "/api/posts/premium",
     params(
         ("amount" = Option<u64>, Query, description = "Number of posts to fetch"),
-        ("displacement" = Option<u64>, Query, description = "Number of posts to skip")
+        ("displacement" = Option<u64>, Query, description = "Number of posts to skip (deprecated, use cursor)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor from the previous page")
     ),
     responses(
-        (status = 200, description = "Premium posts fetched successfully", body = [PostResponse])
+        (status = 200, description = "Premium posts fetched successfully with a cursor; without one the bare post array, next cursor in the `x-next-cursor` header", body = PostFeedResponse),
+        (status = 400, description = "Invalid cursor")
     ),
     tag = "Posts"
 )]
@@ -163,7 +248,10 @@ pub async fn get_a_random_post() {}
     post,
     path = "/api/posts/{id}/like",
     params(("id" = String, Path, description = "Post UUID")),
-    responses((status = 200, description = "Post liked/unliked successfully")),
+    responses(
+        (status = 200, description = "Post liked/unliked successfully, with the new `like_count` and `dislike_count`"),
+        (status = 404, description = "Post not found")
+    ),
     tag = "Posts",
     params(("Authorization" = String, Header, description = "Bearer token for user."))
 )]
@@ -173,8 +261,62 @@ pub async fn like_post() {}
     post,
     path = "/api/posts/{id}/dislike",
     params(("id" = String, Path, description = "Post UUID")),
-    responses((status = 200, description = "Post disliked/undisliked successfully")),
+    responses(
+        (status = 200, description = "Post disliked/undisliked successfully, with the new `like_count` and `dislike_count`"),
+        (status = 404, description = "Post not found")
+    ),
     tag = "Posts",
     params(("Authorization" = String, Header, description = "Bearer token for user."))
 )]
 pub async fn dislike_post() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/posts/{id}/likes",
+    params(
+        ("id" = String, Path, description = "Post UUID"),
+        ("reaction" = Option<String>, Query, description = "`like` (default) or `dislike`, dislikes are only listed for the post's author"),
+        ("page" = Option<u64>, Query, description = "Page number, starting at 1"),
+        ("limit" = Option<u64>, Query, description = "Users per page, at most 100")
+    ),
+    responses(
+        (status = 200, description = "Users that reacted, oldest reaction first, as `users` (ProfileSummary) plus `has_more`"),
+        (status = 401, description = "Dislikes were asked for by someone other than the author"),
+        (status = 404, description = "Post not found")
+    ),
+    tag = "Posts"
+)]
+pub async fn get_post_likes() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/posts/{id}/revisions",
+    params(
+        ("id" = String, Path, description = "Post UUID"),
+        ("page" = Option<u64>, Query, description = "Page number, starting at 1"),
+        ("limit" = Option<u64>, Query, description = "Revisions per page, at most 100")
+    ),
+    responses(
+        (status = 200, description = "Edit history, newest first, as `revisions` (PostRevision) plus `has_more`"),
+        (status = 404, description = "Post not found")
+    ),
+    tag = "Posts"
+)]
+pub async fn get_post_revisions() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/posts/{id}/revisions/{revision_id}/restore",
+    params(
+        ("id" = String, Path, description = "Post UUID"),
+        ("revision_id" = String, Path, description = "Revision UUID, the post goes back to the version this edit replaced"),
+        ("Authorization" = String, Header, description = "Bearer token for the author or a moderator.")
+    ),
+    responses(
+        (status = 200, description = "Post restored, the restore is recorded as a new revision", body = PostResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Post or revision not found")
+    ),
+    tag = "Posts"
+)]
+pub async fn restore_post_revision() {}
@@ -0,0 +1,29 @@
+#![allow(dead_code)]
+
+use crate::models::tag::{ TagPageResponse, TagSuggestion };
+
+#[utoipa::path(
+    get,
+    path = "/api/tags/autocomplete",
+    params(
+        ("q" = String, Query, description = "Start of the tag typed so far, normalized the same way tags are"),
+        ("limit" = Option<u64>, Query, description = "Max suggestions, default 10, at most 50")
+    ),
+    responses(
+        (status = 200, description = "Tags starting with `q`, most used first. Aliases carry `alias_of`.", body = [TagSuggestion])
+    ),
+    tag = "Tags"
+)]
+pub async fn autocomplete_tags() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/tags/{name}",
+    params(("name" = String, Path, description = "Tag name, aliases resolve to their canonical tag")),
+    responses(
+        (status = 200, description = "Tag description, usage counts and related tags", body = TagPageResponse),
+        (status = 404, description = "Tag not found")
+    ),
+    tag = "Tags"
+)]
+pub async fn get_tag() {}
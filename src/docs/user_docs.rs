@@ -2,6 +2,7 @@
 
 use crate::routes::internal::user::types::{ PasswordResetRequest, PasswordResetConfirm };
 use crate::routes::internal::posts::types::QueryPostsParams;
+use crate::models::post::PostFeedResponse;
 
 #[utoipa::path(
     get,
@@ -10,7 +11,10 @@ use crate::routes::internal::posts::types::QueryPostsParams;
         ("username" = String, Path, description = "Username to get posts for"),
         QueryPostsParams
     ),
-    responses((status = 200, description = "Posts fetched successfully")),
+    responses(
+        (status = 200, description = "Posts fetched successfully with a cursor; without one the bare post array, next cursor in the `x-next-cursor` header. `page` starts at 0 on this endpoint", body = PostFeedResponse),
+        (status = 400, description = "Invalid cursor")
+    ),
     tag = "Users"
 )]
 pub async fn get_user_posts() {}
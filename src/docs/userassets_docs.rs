@@ -28,7 +28,7 @@ pub struct FileUploadSchema {
         ),
         (
             status = 400,
-            description = "No file data provided or invalid multipart data.",
+            description = "No file data provided, invalid multipart data, or the file is not a PNG, JPEG, WebP or GIF image.",
             body = AppError,
         ),
         (
@@ -36,6 +36,11 @@ pub struct FileUploadSchema {
             description = "Unauthorized. Missing or invalid Bearer token.",
             body = AppError,
         ),
+        (
+            status = 413,
+            description = "The image is larger than 15 MB.",
+            body = AppError,
+        ),
         (
             status = 500,
             description = "Internal server error while uploading to R2.",
@@ -63,7 +68,7 @@ pub fn upload_profile_picture() {}
         ),
         (
             status = 400,
-            description = "No file data provided or invalid multipart data.",
+            description = "No file data provided, invalid multipart data, or the file is not a PNG, JPEG, WebP or GIF image.",
             body = AppError,
         ),
         (
@@ -71,6 +76,11 @@ pub fn upload_profile_picture() {}
             description = "Unauthorized. Missing or invalid Bearer token.",
             body = AppError,
         ),
+        (
+            status = 413,
+            description = "The image is larger than 15 MB.",
+            body = AppError,
+        ),
         (
             status = 500,
             description = "Internal server error while uploading to R2.",
@@ -2,6 +2,7 @@ use actix_web::dev::Payload;
 use actix_web::{web, Error, FromRequest, HttpRequest};
 use futures_util::future::BoxFuture;
 use crate::models::session::Session;
+use crate::models::viewer::Viewer;
 use crate::state::AppState;
 use crate::utils::auth::{get_session_token_from_header, validate_and_refresh_session};
 
@@ -10,6 +11,13 @@ pub struct Auther {
     pub session: Session,
 }
 
+impl Auther {
+    /// Loads the signed-in user's content preferences
+    pub async fn viewer(&self, state: &AppState) -> Viewer {
+        session_viewer(&self.session, state).await
+    }
+}
+
 impl FromRequest for Auther {
     type Error = Error;
     type Future = BoxFuture<'static, Result<Self, Self::Error>>;
@@ -42,4 +50,59 @@ impl FromRequest for Auther {
             Ok(Auther { session })
         })
     }
-}
\ No newline at end of file
+}
+
+/// Like `Auther`, but for routes that also serve signed-out users.
+/// A missing or invalid token gives `session: None` instead of a 401.
+#[derive(Debug)]
+pub struct OptionalAuther {
+    pub session: Option<Session>,
+}
+
+impl OptionalAuther {
+    /// Loads the viewer's content preferences, anonymous viewers get the safe defaults
+    pub async fn viewer(&self, state: &AppState) -> Viewer {
+        match &self.session {
+            Some(session) => session_viewer(session, state).await,
+            None => Viewer::anonymous(),
+        }
+    }
+}
+
+async fn session_viewer(session: &Session, state: &AppState) -> Viewer {
+    // missing settings just means nothing was opted into
+    let show_nsfw = state.db.settings
+        .get_by_uuid(&session.user_uuid).await
+        .map(|settings| settings.nsfw)
+        .unwrap_or(false);
+
+    Viewer::signed_in(session.user_uuid.to_string(), show_nsfw)
+}
+
+impl FromRequest for OptionalAuther {
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let data = match req.app_data::<web::Data<AppState>>().cloned() {
+            Some(d) => d,
+            None => {
+                return Box::pin(async {
+                    Err(actix_web::error::ErrorInternalServerError("AppState missing"))
+                });
+            }
+        };
+
+        let token = match get_session_token_from_header(req) {
+            Ok(t) => t,
+            Err(_) => {
+                return Box::pin(async { Ok(OptionalAuther { session: None }) });
+            }
+        };
+
+        Box::pin(async move {
+            let session = validate_and_refresh_session(token, &data).await.ok();
+            Ok(OptionalAuther { session })
+        })
+    }
+}
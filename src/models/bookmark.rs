@@ -0,0 +1,63 @@
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::character::CharacterResponse;
+use crate::models::post::PostResponse;
+use crate::utils::uuid_as_string;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BookmarkKind {
+    Post,
+    Character,
+}
+
+impl BookmarkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookmarkKind::Post => "post",
+            BookmarkKind::Character => "character",
+        }
+    }
+}
+
+/// A private "save for later" of a post or character. Unlike likes nobody else sees these.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    #[serde(rename = "_id", with = "uuid_as_string")]
+    pub id: Uuid,
+    pub user_id: String,
+    pub kind: BookmarkKind,
+    pub target_id: String,
+    pub folder: Option<String>, // None is the default, unsorted folder
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BookmarkResponse {
+    #[serde(rename = "_id", with = "uuid_as_string")]
+    pub id: Uuid,
+    pub kind: BookmarkKind,
+    pub target_id: String,
+    pub folder: Option<String>,
+    #[serde(serialize_with = "chrono::serde::ts_milliseconds::serialize")]
+    pub created_at: DateTime<Utc>,
+    // whichever the bookmark points at, None when it is no longer visible
+    pub post: Option<PostResponse>,
+    pub character: Option<CharacterResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BookmarkListResponse {
+    pub bookmarks: Vec<BookmarkResponse>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BookmarkFolder {
+    pub name: Option<String>, // None is the unsorted folder
+    pub count: u64,
+}
@@ -1,8 +1,9 @@
 use std::collections::HashSet;
 use chrono::{ DateTime, Utc };
 use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
 use uuid::Uuid;
-use crate::{ models::media::Media, utils::uuid_as_string };
+use crate::{ models::{ media::Media, reaction::Reaction }, utils::uuid_as_string };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Character {
@@ -19,7 +20,56 @@ pub struct Character {
     pub likes: HashSet<String>,
     #[serde(default)]
     pub dislikes: HashSet<String>,
+    #[serde(default)]
+    pub like_count: u64,
+    #[serde(default)]
+    pub dislike_count: u64,
     pub media: Vec<Media>,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub created_at: DateTime<Utc>,
 }
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CharacterResponse {
+    #[serde(rename = "_id", with = "uuid_as_string")]
+    pub id: Uuid,
+    pub owner: String,
+    pub owner_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub like_count: u64,
+    pub dislike_count: u64,
+    pub viewer_reaction: Option<Reaction>, // how the requesting user reacted, None when signed out
+    pub media: Vec<Media>,
+    #[serde(serialize_with = "chrono::serde::ts_milliseconds::serialize")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&Character> for CharacterResponse {
+    fn from(value: &Character) -> Self {
+        CharacterResponse {
+            id: value.id,
+            owner: value.owner.clone(),
+            owner_id: value.owner_id,
+            name: value.name.clone(),
+            description: value.description.clone(),
+            tags: value.tags.clone(),
+            like_count: value.like_count,
+            dislike_count: value.dislike_count,
+            viewer_reaction: None,
+            media: value.media.clone(),
+            created_at: value.created_at,
+        }
+    }
+}
+
+impl CharacterResponse {
+    /// Same as `From<&Character>` with `viewer_reaction` filled in
+    pub fn for_viewer(character: &Character, viewer: Option<&str>) -> Self {
+        CharacterResponse {
+            viewer_reaction: Reaction::of(viewer, &character.likes, &character.dislikes),
+            ..CharacterResponse::from(character)
+        }
+    }
+}
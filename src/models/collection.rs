@@ -0,0 +1,119 @@
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::media::MediaResponse;
+use crate::models::post::PostFeedResponse;
+use crate::models::viewer::Viewer;
+use crate::utils::uuid_as_string;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CollectionVisibility {
+    #[default]
+    Public, // listed on the owner's profile and on its posts
+    Unlisted, // anyone with the link
+    Private, // owner only
+}
+
+/// A named, ordered group of posts owned by one user, e.g. comic pages or a favourites board.
+/// Posts can be anyone's.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    #[serde(rename = "_id", with = "uuid_as_string")]
+    pub id: Uuid,
+    #[serde(with = "uuid_as_string")]
+    pub owner_id: Uuid,
+    pub owner: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub visibility: CollectionVisibility,
+    pub posts: Vec<String>, // post ids in display order
+    pub cover_post_id: Option<String>, // falls back to the first post when unset
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Collection {
+    pub fn is_owner(&self, viewer: &Viewer) -> bool {
+        viewer.user_id() == Some(self.owner_id.to_string().as_str())
+    }
+
+    /// Private collections only exist for their owner
+    pub fn visible_to(&self, viewer: &Viewer) -> bool {
+        self.visibility != CollectionVisibility::Private || self.is_owner(viewer)
+    }
+
+    /// The post whose first image is used as the cover
+    pub fn cover_post(&self) -> Option<&String> {
+        self.cover_post_id.as_ref().or(self.posts.first())
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CollectionResponse {
+    #[serde(rename = "_id", with = "uuid_as_string")]
+    pub id: Uuid,
+    pub owner: String,
+    #[serde(with = "uuid_as_string")]
+    pub owner_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub visibility: CollectionVisibility,
+    pub post_count: usize,
+    pub cover: Option<MediaResponse>,
+    #[serde(serialize_with = "chrono::serde::ts_milliseconds::serialize")]
+    pub created_at: DateTime<Utc>,
+    #[serde(serialize_with = "chrono::serde::ts_milliseconds::serialize")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CollectionResponse {
+    pub fn new(collection: &Collection, cover: Option<MediaResponse>) -> Self {
+        CollectionResponse {
+            id: collection.id,
+            owner: collection.owner.clone(),
+            owner_id: collection.owner_id,
+            name: collection.name.clone(),
+            description: collection.description.clone(),
+            visibility: collection.visibility,
+            post_count: collection.posts.len(),
+            cover,
+            created_at: collection.created_at,
+            updated_at: collection.updated_at,
+        }
+    }
+}
+
+/// Just enough to link to a collection, shown on the posts inside it
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CollectionSummary {
+    #[serde(rename = "_id", with = "uuid_as_string")]
+    pub id: Uuid,
+    pub owner: String,
+    pub name: String,
+    pub post_count: usize,
+}
+
+impl From<&Collection> for CollectionSummary {
+    fn from(value: &Collection) -> Self {
+        CollectionSummary {
+            id: value.id,
+            owner: value.owner.clone(),
+            name: value.name.clone(),
+            post_count: value.posts.len(),
+        }
+    }
+}
+
+/// A collection with one page of its posts, in the collection's order
+#[derive(Serialize, ToSchema)]
+pub struct CollectionPageResponse {
+    pub collection: CollectionResponse,
+    pub page: u64,
+    pub limit: u64,
+    pub posts: PostFeedResponse,
+}
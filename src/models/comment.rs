@@ -4,12 +4,40 @@ use serde::{ Deserialize, Serialize };
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::models::post::CommentSettings;
+use crate::models::reaction::Reaction;
+use crate::utils::cursor::FeedSort;
 use crate::utils::uuid_as_string;
 
 #[derive(Deserialize, Clone, ToSchema)]
 pub struct CommentReqInput {
-    pub author: String,
-    pub content: String,
+    pub content: String, // the author is whoever is signed in
+}
+
+/// How a page of comments or replies is ordered
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentSort {
+    Top, // most liked first
+    Newest,
+    Oldest,
+}
+
+impl CommentSort {
+    pub fn feed_sort(&self) -> FeedSort {
+        match self {
+            CommentSort::Top => FeedSort::MostLiked,
+            CommentSort::Newest => FeedSort::Newest,
+            CommentSort::Oldest => FeedSort::Oldest,
+        }
+    }
+}
+
+/// A user `@mentioned` in a comment, resolved when the comment was written
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Mention {
+    pub user_id: String,
+    pub username: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -18,15 +46,116 @@ pub struct Comment {
     pub id: Uuid,
     #[serde(with = "uuid_as_string")]
     pub post_id: Uuid, // id of the post the comment is linked to
+    // replies point at the comment they answer and the top-level comment of their thread
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub root_id: Option<String>,
+    #[serde(default)]
+    pub depth: u32, // 0 for top-level comments
+    #[serde(default)]
+    pub reply_count: u64, // direct replies only
+    #[serde(default)]
+    pub author_id: Option<String>, // missing on comments from before authors were taken from the session
     pub author: String,
     pub content: String,
+    #[serde(default)]
+    pub mentions: Vec<Mention>,
     pub likes: HashSet<String>,
     pub dislikes: HashSet<String>,
+    #[serde(default)]
+    pub like_count: u64,
+    #[serde(default)]
+    pub dislike_count: u64,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    // deleted comments that still have replies stay as a tombstone so the thread keeps its shape
+    #[serde(default)]
+    pub deleted: bool,
+    #[serde(default)]
+    pub removed_by: Option<String>, // moderator who took it down, None when the author deleted it
+    // held until the post's author approves it, only they and the commenter see it meanwhile
+    #[serde(default)]
+    pub pending: bool,
+}
+
+pub const DELETED_PLACEHOLDER: &str = "[deleted]";
+pub const REMOVED_PLACEHOLDER: &str = "[removed]";
+
+/// A comment as clients see it, counts instead of who reacted
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CommentResponse {
+    #[serde(rename = "_id", with = "uuid_as_string")]
+    pub id: Uuid,
+    #[serde(with = "uuid_as_string")]
+    pub post_id: Uuid,
+    pub parent_id: Option<String>,
+    pub root_id: Option<String>,
+    pub depth: u32,
+    pub reply_count: u64,
+    pub author_id: Option<String>,
+    pub author: String,
+    pub content: String,
+    pub mentions: Vec<Mention>,
+    pub like_count: u64,
+    pub dislike_count: u64,
+    pub viewer_reaction: Option<Reaction>,
     pub created_at: DateTime<Utc>,
     pub edited_at: Option<DateTime<Utc>>,
+    pub deleted: bool, // author and content are placeholders when set
+    pub pending: bool, // waiting for the post author's approval
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+impl CommentResponse {
+    pub fn for_viewer(comment: &Comment, viewer: Option<&str>) -> Self {
+        let (author, content) = match (comment.deleted, &comment.removed_by) {
+            (false, _) => (comment.author.as_str(), comment.content.as_str()),
+            (true, None) => (DELETED_PLACEHOLDER, DELETED_PLACEHOLDER),
+            (true, Some(_)) => (DELETED_PLACEHOLDER, REMOVED_PLACEHOLDER),
+        };
+
+        CommentResponse {
+            id: comment.id,
+            post_id: comment.post_id,
+            parent_id: comment.parent_id.clone(),
+            root_id: comment.root_id.clone(),
+            depth: comment.depth,
+            reply_count: comment.reply_count,
+            author_id: comment.author_id.clone().filter(|_| !comment.deleted),
+            author: author.to_string(),
+            content: content.to_string(),
+            mentions: if comment.deleted { Vec::new() } else { comment.mentions.clone() },
+            like_count: comment.like_count,
+            dislike_count: comment.dislike_count,
+            viewer_reaction: Reaction::of(viewer, &comment.likes, &comment.dislikes).filter(|_| !comment.deleted),
+            created_at: comment.created_at,
+            edited_at: comment.edited_at,
+            deleted: comment.deleted,
+            pending: comment.pending,
+        }
+    }
+}
+
+/// One page of comments or replies
+#[derive(Serialize, ToSchema)]
+pub struct CommentPage {
+    pub comments: Vec<CommentResponse>,
+    pub next_cursor: Option<String>,
+}
+
+/// A page of a post's top-level comments along with how the author set them up
+#[derive(Serialize, ToSchema)]
+pub struct PostCommentsResponse {
+    #[serde(flatten)]
+    pub page: CommentPage,
+    pub pinned: Option<CommentResponse>, // only on the first page, and left out of `comments`
+    pub settings: CommentSettings,
+}
+
+// replies used to be nested inside one comment_replies document per comment,
+// these are only read to move them over to the comments collection
+#[derive(Clone, Debug, Deserialize)]
 pub struct Reply {
     #[serde(rename = "_id", with = "uuid_as_string")]
     pub id: Uuid,
@@ -36,47 +165,93 @@ pub struct Reply {
     pub dislikes: HashSet<String>,
     pub created_at: DateTime<Utc>,
     pub edited_at: Option<DateTime<Utc>>,
-    #[schema(value_type = Vec<String>, example = json!(["child_reply_id_1", "child_reply_id_2"]))]
     pub replies: Vec<Reply>,
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Deserialize)]
 pub struct CommentReplies {
-    #[serde(rename = "_id", with = "uuid_as_string")]
-    pub id: Uuid,
-    pub has_replies: bool,
-    pub sub_ids: HashSet<String>,
     pub replies: Vec<Reply>,
 }
 
+impl CommentReplies {
+    /// Every reply in the tree as a comment of `root`'s thread
+    pub fn into_comments(self, root: &Comment) -> Vec<Comment> {
+        let mut comments = Vec::new();
+        flatten_replies(self.replies, root, &root.id.to_string(), 1, &mut comments);
+        comments
+    }
+}
+
+fn flatten_replies(replies: Vec<Reply>, root: &Comment, parent_id: &str, depth: u32, out: &mut Vec<Comment>) {
+    for reply in replies {
+        let id = reply.id.to_string();
+        out.push(Comment {
+            id: reply.id,
+            post_id: root.post_id,
+            parent_id: Some(parent_id.to_string()),
+            root_id: Some(root.id.to_string()),
+            depth,
+            reply_count: reply.replies.len() as u64,
+            author_id: None,
+            author: reply.author,
+            content: reply.content,
+            mentions: Vec::new(),
+            like_count: reply.likes.len() as u64,
+            dislike_count: reply.dislikes.len() as u64,
+            likes: reply.likes,
+            dislikes: reply.dislikes,
+            created_at: reply.created_at,
+            edited_at: reply.edited_at,
+            deleted: false,
+            removed_by: None,
+            pending: false,
+        });
+        flatten_replies(reply.replies, root, &id, depth + 1, out);
+    }
+}
+
 impl Comment {
-    pub fn new(author_username: String, content: String, post_id: Uuid) -> Self {
+    pub fn new(author_id: String, author_username: String, content: String, post_id: Uuid) -> Self {
         let now = Utc::now();
         Comment {
             id: Uuid::new_v4(),
             post_id,
+            parent_id: None,
+            root_id: None,
+            depth: 0,
+            reply_count: 0,
+            author_id: Some(author_id),
             author: author_username,
             content,
+            mentions: Vec::new(),
             likes: HashSet::new(),
             dislikes: HashSet::new(),
+            like_count: 0,
+            dislike_count: 0,
             created_at: now,
             edited_at: None,
+            deleted: false,
+            removed_by: None,
+            pending: false,
         }
     }
-}
 
-impl Reply {
-    pub fn new(author_username: String, content: String) -> Self {
-        let now = Utc::now();
-        Reply {
-            id: Uuid::new_v4(),
-            author: author_username,
-            content,
-            likes: HashSet::new(),
-            dislikes: HashSet::new(),
-            created_at: now,
-            edited_at: None,
-            replies: vec![],
+    /// A reply to `parent`, one level deeper in the same thread
+    pub fn reply_to(parent: &Comment, author_id: String, author_username: String, content: String) -> Self {
+        let root_id = parent.root_id.clone().unwrap_or_else(|| parent.id.to_string());
+        Comment {
+            parent_id: Some(parent.id.to_string()),
+            root_id: Some(root_id),
+            depth: parent.depth + 1,
+            ..Comment::new(author_id, author_username, content, parent.post_id)
+        }
+    }
+
+    /// Older comments only know their author by username
+    pub fn is_author(&self, user_id: &str, username: &str) -> bool {
+        match &self.author_id {
+            Some(author_id) => author_id == user_id,
+            None => self.author == username,
         }
     }
 }
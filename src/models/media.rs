@@ -18,10 +18,36 @@ pub struct Media {
     pub size_bytes: u64,
     pub uploaded_at: DateTime<Utc>,
     pub is_nsfw: Option<bool>,
+    // smaller WebP versions for feeds, videos get a thumbnail of their poster frame
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+    #[serde(default)]
+    pub preview_url: Option<String>,
+    // a frame of a video as WebP, for the player to show before it starts
+    #[serde(default)]
+    pub poster_url: Option<String>,
+    // SHA-256 (hex) of the file as it was uploaded, None on media from before hashes were kept
+    #[serde(default)]
+    pub sha256: Option<String>,
 
     pub metadata: MediaMetadata,
 }
 
+impl Media {
+    /// Whether this media is NSFW, its own flag wins over the post's
+    pub fn is_nsfw_within(&self, post_nsfw: bool) -> bool {
+        self.is_nsfw.unwrap_or(post_nsfw)
+    }
+}
+
+/// Media as sent to a viewer
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MediaResponse {
+    #[serde(flatten)]
+    pub media: Media,
+    pub blurred: bool, // NSFW and the viewer has not opted in, clients should blur it
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "type", content = "data")]
 pub enum MediaMetadata {
@@ -40,3 +66,61 @@ pub enum MediaMetadata {
         description: Option<String>,
     },
 }
+
+/// The stored objects of one uploaded file, keyed by the SHA-256 of the bytes as uploaded.
+/// Uploading the same file again points at these instead of storing it twice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaObject {
+    #[serde(rename = "_id")]
+    pub sha256: String,
+    pub url: String,
+    pub thumbnail_url: Option<String>,
+    pub preview_url: Option<String>,
+    #[serde(default)]
+    pub poster_url: Option<String>,
+    pub content_type: String,
+    pub size_bytes: u64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub duration_secs: Option<f32>,
+    // how many media handed out point at these objects, they're deleted once it drops to zero
+    #[serde(default)]
+    pub refs: i64,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl MediaObject {
+    /// Fresh post media pointing at this object
+    pub fn to_media(&self) -> Media {
+        Media {
+            url: self.url.clone(),
+            filename: self.url.rsplit('/').next().unwrap_or_default().to_string(),
+            content_type: self.content_type.clone(),
+            size_bytes: self.size_bytes,
+            uploaded_at: Utc::now(),
+            is_nsfw: None,
+            thumbnail_url: self.thumbnail_url.clone(),
+            preview_url: self.preview_url.clone(),
+            poster_url: self.poster_url.clone(),
+            sha256: Some(self.sha256.clone()),
+
+            metadata: MediaMetadata::Post {
+                width: self.width,
+                height: self.height,
+                duration_secs: self.duration_secs,
+            },
+        }
+    }
+}
+
+/// A file moderators never want to see again, uploads with this hash are refused
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BannedHash {
+    #[serde(rename = "_id")]
+    pub sha256: String,
+    pub reason: Option<String>,
+    pub banned_by: String, // user id of the moderator
+    pub created_at: DateTime<Utc>,
+}
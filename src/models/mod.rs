@@ -6,11 +6,20 @@ pub mod profile;
 pub mod session;
 pub mod settings;
 pub mod user;
+pub mod bookmark;
 pub mod character;
+pub mod collection;
 pub mod codes;
 pub mod report;
 pub mod media;
 pub mod announcement;
+pub mod reaction;
+pub mod revision;
+pub mod viewer;
+pub mod search;
+pub mod tag;
+pub mod upload;
+pub mod notification;
 
 #[derive(serde::Serialize, ToSchema)]
 pub struct OkResponse {
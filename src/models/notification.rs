@@ -0,0 +1,98 @@
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::profile::ProfileSummary;
+use crate::utils::uuid_as_string;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    Follow,
+    PostLike,
+    CommentLike,
+    Comment,
+    Reply,
+    Mention,
+    ReportResolved,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Follow => "follow",
+            NotificationKind::PostLike => "post_like",
+            NotificationKind::CommentLike => "comment_like",
+            NotificationKind::Comment => "comment",
+            NotificationKind::Reply => "reply",
+            NotificationKind::Mention => "mention",
+            NotificationKind::ReportResolved => "report_resolved",
+        }
+    }
+
+    /// Follows and likes on the same thing collect into one notification while it is unread,
+    /// everything else carries its own content and stays separate
+    pub fn is_grouped(&self) -> bool {
+        matches!(self, NotificationKind::Follow | NotificationKind::PostLike | NotificationKind::CommentLike)
+    }
+}
+
+/// Something that happened to a user's profile, posts, comments or reports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    #[serde(rename = "_id", with = "uuid_as_string")]
+    pub id: Uuid,
+    pub user_id: String, // who gets it
+    pub kind: NotificationKind,
+    pub target_id: String, // the post, comment, report or, for follows, the followed user
+    pub post_id: Option<String>, // the post a comment event happened under
+    pub actor_ids: Vec<String>, // most recent last, capped for big groups
+    pub actor_count: u64,
+    pub preview: Option<String>, // start of the comment for comment events
+    pub read: bool,
+    // bumped whenever someone joins the group so it moves back to the top
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl Notification {
+    pub fn new(user_id: String, kind: NotificationKind, actor_id: Option<String>, target_id: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            user_id,
+            kind,
+            target_id,
+            post_id: None,
+            actor_count: actor_id.is_some() as u64,
+            actor_ids: actor_id.into_iter().collect(),
+            preview: None,
+            read: false,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// A notification with its latest actors filled in, `actor_count` is the whole group
+/// so clients can say "alice and 4 others liked your post"
+#[derive(Serialize, ToSchema)]
+pub struct NotificationResponse {
+    #[serde(rename = "_id", with = "uuid_as_string")]
+    pub id: Uuid,
+    pub kind: NotificationKind,
+    pub target_id: String,
+    pub post_id: Option<String>,
+    pub actors: Vec<ProfileSummary>, // newest first
+    pub actor_count: u64,
+    pub preview: Option<String>,
+    pub read: bool,
+    #[serde(serialize_with = "chrono::serde::ts_milliseconds::serialize")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct NotificationListResponse {
+    pub notifications: Vec<NotificationResponse>,
+    pub next_cursor: Option<String>,
+    pub unread_count: u64,
+}
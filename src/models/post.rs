@@ -1,12 +1,17 @@
 use std::collections::HashSet;
 
+use actix_web::HttpResponse;
 use chrono::{ DateTime, Utc };
 use serde::{ Deserialize, Serialize };
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::models::media::Media;
-use crate::utils::post::PostType;
+use crate::models::collection::CollectionSummary;
+use crate::models::media::{ Media, MediaResponse };
+use crate::models::reaction::Reaction;
+use crate::models::viewer::Viewer;
+use crate::utils::cursor::{ FeedCursor, FeedPage, FeedSort, NEXT_CURSOR_HEADER };
+use crate::utils::post::{ PostStatus, PostType };
 use crate::utils::uuid_as_string;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,11 +31,47 @@ pub struct Post {
     pub likes: HashSet<String>,
     #[serde(default)]
     pub dislikes: HashSet<String>,
+    // kept in step with the sets above by database::reactions
+    #[serde(default)]
+    pub like_count: u64,
+    #[serde(default)]
+    pub dislike_count: u64,
     pub media: Vec<Media>,
+    #[serde(default)]
+    pub characters: Vec<String>, // ids of the characters featured in this post
+    #[serde(default)]
+    pub views: u64,
+    // both kept up to date by the TrendingTask, see utils::trending
+    #[serde(default)]
+    pub engagement: f64,
+    #[serde(default)]
+    pub trending_score: f64,
+    #[serde(default)]
+    pub status: PostStatus,
+    #[serde(default)]
+    pub comment_settings: CommentSettings,
+    #[serde(default, with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional")]
+    pub publish_at: Option<DateTime<Utc>>, // only set while scheduled
+    #[serde(default, with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional")]
+    pub edited_at: Option<DateTime<Utc>>, // last edit that changed the content, see models::revision
+    // publish time for posts that started out as drafts or scheduled
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub created_at: DateTime<Utc>,
 }
 
+/// What the author allows in the comments under a post
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct CommentSettings {
+    #[serde(default)]
+    pub locked: bool, // no new comments or replies
+    #[serde(default)]
+    pub followers_only: bool,
+    #[serde(default)]
+    pub require_approval: bool, // a user's first comment waits until the author approves it
+    #[serde(default)]
+    pub pinned_comment_id: Option<String>, // a top-level comment shown above the rest
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AdminPatchPost {
     pub author: Option<String>,
@@ -40,8 +81,6 @@ pub struct AdminPatchPost {
     pub tags: Option<Vec<String>>,
     pub post_type: Option<PostType>,
     pub nsfw: Option<bool>,
-    pub likes: Option<HashSet<String>>,
-    pub dislikes: Option<HashSet<String>>,
     // pub media_urls: Option<Vec<String>>,
     pub created_at: Option<DateTime<Utc>>,
 }
@@ -58,13 +97,88 @@ pub struct PostResponse {
     pub tags: Vec<String>,
     pub post_type: PostType,
     pub nsfw: bool,
-    pub likes: HashSet<String>,
-    pub dislikes: HashSet<String>,
-    pub media: Vec<Media>,
+    pub like_count: u64,
+    pub dislike_count: u64,
+    pub viewer_reaction: Option<Reaction>, // how the requesting user reacted, None when signed out
+    pub bookmarked: Option<bool>, // whether the requesting user saved it, None when signed out
+    pub blurred: bool, // NSFW and the viewer has not opted in
+    pub media: Vec<MediaResponse>,
+    pub characters: Vec<String>,
+    pub views: u64,
+    pub status: PostStatus,
+    #[serde(default)]
+    pub comment_settings: CommentSettings,
+    #[serde(serialize_with = "chrono::serde::ts_milliseconds_option::serialize")]
+    pub publish_at: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "chrono::serde::ts_milliseconds_option::serialize")]
+    pub edited_at: Option<DateTime<Utc>>,
     #[serde(serialize_with = "chrono::serde::ts_milliseconds::serialize")]
     pub created_at: DateTime<Utc>,
 }
 
+/// A single post as opened on its own page, with the public collections it is in
+#[derive(Serialize, ToSchema)]
+pub struct PostDetailResponse {
+    #[serde(flatten)]
+    pub post: PostResponse,
+    pub collections: Vec<CollectionSummary>,
+}
+
+/// One page of a post feed, with the cursor for the page after it (`None` on the last page).
+#[derive(Serialize, ToSchema)]
+pub struct PostFeedResponse {
+    pub posts: Vec<PostResponse>,
+    pub next_cursor: Option<String>,
+}
+
+impl PostFeedResponse {
+    pub fn new(posts: &[Post], sort: FeedSort, limit: u64, viewer: &Viewer) -> Self {
+        PostFeedResponse {
+            posts: posts
+                .iter()
+                .map(|post| PostResponse::for_viewer(post, viewer))
+                .collect(),
+            next_cursor: FeedCursor::next_page(sort, posts, limit),
+        }
+    }
+
+    /// Feeds that predate cursors keep answering `page`/`displacement` callers with the bare
+    /// post array, the next cursor goes in a header there. Cursor callers get the envelope
+    pub fn respond(self, page: &FeedPage) -> HttpResponse {
+        match page {
+            FeedPage::After(_) => HttpResponse::Ok().json(self),
+            FeedPage::Offset(_) => {
+                let mut response = HttpResponse::Ok();
+                if let Some(cursor) = &self.next_cursor {
+                    response.insert_header((NEXT_CURSOR_HEADER, cursor.as_str()));
+                }
+                response.json(self.posts)
+            }
+        }
+    }
+}
+
+impl PostResponse {
+    /// Same as `From<&Post>`, with `viewer_reaction` filled in and NSFW blurred unless the
+    /// viewer opted in
+    pub fn for_viewer(post: &Post, viewer: &Viewer) -> Self {
+        let blur = !viewer.show_nsfw;
+        PostResponse {
+            viewer_reaction: Reaction::of(viewer.user_id(), &post.likes, &post.dislikes),
+            bookmarked: viewer.has_bookmarked(&post.id.to_string()),
+            blurred: blur && post.nsfw,
+            media: post.media
+                .iter()
+                .map(|media| MediaResponse {
+                    media: media.clone(),
+                    blurred: blur && media.is_nsfw_within(post.nsfw),
+                })
+                .collect(),
+            ..PostResponse::from(post)
+        }
+    }
+}
+
 impl From<&Post> for PostResponse {
     fn from(value: &Post) -> Self {
         PostResponse {
@@ -77,15 +191,36 @@ impl From<&Post> for PostResponse {
             tags: value.tags.clone(),
             post_type: value.post_type.clone(),
             nsfw: value.nsfw,
-            likes: value.likes.clone(),
-            dislikes: value.dislikes.clone(),
-            media: value.media.clone(),
+            like_count: value.like_count,
+            dislike_count: value.dislike_count,
+            viewer_reaction: None,
+            bookmarked: None,
+            blurred: false,
+            media: value.media
+                .iter()
+                .map(|media| MediaResponse { media: media.clone(), blurred: false })
+                .collect(),
+            characters: value.characters.clone(),
+            views: value.views,
+            status: value.status,
+            comment_settings: value.comment_settings.clone(),
+            publish_at: value.publish_at,
+            edited_at: value.edited_at,
             created_at: value.created_at,
         }
     }
 }
 
 impl Post {
+    pub fn is_published(&self) -> bool {
+        self.status == PostStatus::Published
+    }
+
+    /// Drafts and scheduled posts can only be opened by their author
+    pub fn visible_to(&self, viewer: &Viewer) -> bool {
+        self.is_published() || viewer.user_id() == Some(self.author_id.to_string().as_str())
+    }
+
     pub fn apply_patch(&mut self, patch: AdminPatchPost) {
         if let Some(author) = patch.author {
             self.author = author;
@@ -108,12 +243,6 @@ impl Post {
         if let Some(nsfw) = patch.nsfw {
             self.nsfw = nsfw;
         }
-        if let Some(likes) = patch.likes {
-            self.likes = likes;
-        }
-        if let Some(dislikes) = patch.dislikes {
-            self.dislikes = dislikes;
-        }
         // if let Some(media_urls) = patch.media_urls {
         //     self.media_urls = media_urls;
         // }
@@ -63,6 +63,29 @@ pub struct DBProfile {
     pub banner_picture: Option<String>,
 }
 
+/// Just enough of a profile to render a user in a list (likers, mentions, ...)
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProfileSummary {
+    #[serde(with = "uuid_as_string")]
+    pub id: Uuid,
+    pub username: String,
+    pub display_name: String,
+    pub verified: bool,
+    pub profile_picture: Option<String>,
+}
+
+impl From<&DBProfile> for ProfileSummary {
+    fn from(value: &DBProfile) -> Self {
+        ProfileSummary {
+            id: value.id,
+            username: value.username.clone(),
+            display_name: value.display_name.clone(),
+            verified: value.verified,
+            profile_picture: value.profile_picture.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PatchDBProfile {
     pub username: Option<String>,
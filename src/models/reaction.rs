@@ -0,0 +1,37 @@
+use std::collections::HashSet;
+
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Reaction {
+    Like,
+    Dislike,
+}
+
+impl Reaction {
+    /// (own set, opposite set, own counter, opposite counter) field names in Mongo
+    pub fn fields(&self) -> (&'static str, &'static str, &'static str, &'static str) {
+        match self {
+            Reaction::Like => ("likes", "dislikes", "like_count", "dislike_count"),
+            Reaction::Dislike => ("dislikes", "likes", "dislike_count", "like_count"),
+        }
+    }
+
+    /// What `viewer` reacted with, if anything
+    pub fn of(
+        viewer: Option<&str>,
+        likes: &HashSet<String>,
+        dislikes: &HashSet<String>
+    ) -> Option<Reaction> {
+        let viewer = viewer?;
+        if likes.contains(viewer) {
+            Some(Reaction::Like)
+        } else if dislikes.contains(viewer) {
+            Some(Reaction::Dislike)
+        } else {
+            None
+        }
+    }
+}
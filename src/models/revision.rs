@@ -0,0 +1,98 @@
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::post::Post;
+use crate::utils::uuid_as_string;
+
+/// The editable parts of a post, as they were at some point
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PostSnapshot {
+    pub title: String,
+    pub body: Option<String>,
+    pub tags: Vec<String>,
+    pub nsfw: bool,
+}
+
+impl From<&Post> for PostSnapshot {
+    fn from(value: &Post) -> Self {
+        PostSnapshot {
+            title: value.title.clone(),
+            body: value.body.clone(),
+            tags: value.tags.clone(),
+            nsfw: value.nsfw,
+        }
+    }
+}
+
+impl PostSnapshot {
+    /// The fields that differ between two versions, in a fixed order
+    pub fn diff(&self, new: &PostSnapshot) -> Vec<FieldChange> {
+        let mut changes = Vec::new();
+        if self.title != new.title {
+            changes.push(FieldChange::new("title", &self.title, &new.title));
+        }
+        if self.body != new.body {
+            changes.push(FieldChange::new("body", &self.body, &new.body));
+        }
+        if self.tags != new.tags {
+            changes.push(FieldChange::new("tags", &self.tags, &new.tags));
+        }
+        if self.nsfw != new.nsfw {
+            changes.push(FieldChange::new("nsfw", &self.nsfw, &new.nsfw));
+        }
+        changes
+    }
+
+    pub fn apply_to(&self, post: &mut Post) {
+        post.title = self.title.clone();
+        post.body = self.body.clone();
+        post.tags = self.tags.clone();
+        post.nsfw = self.nsfw;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+impl FieldChange {
+    fn new(field: &str, old: &impl Serialize, new: &impl Serialize) -> Self {
+        FieldChange {
+            field: field.to_string(),
+            old: serde_json::to_value(old).unwrap_or(Value::Null),
+            new: serde_json::to_value(new).unwrap_or(Value::Null),
+        }
+    }
+}
+
+/// One edit of a post. `previous` is the version the edit replaced, which is what
+/// restoring this revision brings back.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PostRevision {
+    #[serde(rename = "_id", with = "uuid_as_string")]
+    pub id: Uuid,
+    #[serde(with = "uuid_as_string")]
+    pub post_id: Uuid,
+    pub number: u64, // 1 for the first edit
+    #[serde(with = "uuid_as_string")]
+    pub editor_id: Uuid,
+    pub editor: String,
+    pub changes: Vec<FieldChange>,
+    pub previous: PostSnapshot,
+    pub restored_from: Option<String>, // revision id when the edit was a restore
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Who made an edit
+#[derive(Debug, Clone)]
+pub struct Editor {
+    pub id: Uuid,
+    pub username: String,
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::models::post::PostResponse;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: u64,
+}
+
+/// How many matching posts each tag/author has, across all pages of the search
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct SearchFacets {
+    pub tags: Vec<FacetCount>,
+    pub authors: Vec<FacetCount>,
+}
+
+/// Matched parts of a post, with matches wrapped in `<mark>` and everything else HTML escaped
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct SearchHighlight {
+    pub title: Option<String>,
+    pub body: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SearchResponse {
+    pub total: u64, // matches across every page, not just this one
+    pub page: u64,
+    pub limit: u64,
+    pub used_regex: bool, // true when the text index found nothing and we fell back to a regex
+    pub posts: Vec<PostResponse>,
+    pub next_cursor: Option<String>,
+    pub facets: SearchFacets,
+    pub highlights: HashMap<String, SearchHighlight>, // keyed by post id
+}
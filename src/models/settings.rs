@@ -2,7 +2,7 @@ use serde::{ Deserialize, Serialize };
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::{ models::user::User, utils::uuid_as_string };
+use crate::{ models::{ notification::NotificationKind, user::User }, utils::uuid_as_string };
 
 #[derive(ToSchema, Serialize, Deserialize, Debug, Clone)]
 #[serde(default)]
@@ -17,8 +17,43 @@ pub struct UserSettings {
 }
 
 #[derive(ToSchema, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct NotificationSettings {
     pub email: Option<bool>,
+    // which in-app notifications the user gets, all of them unless turned off
+    pub follows: bool,
+    pub likes: bool,
+    pub comments: bool,
+    pub replies: bool,
+    pub mentions: bool,
+    pub reports: bool,
+}
+
+impl NotificationSettings {
+    pub fn allows(&self, kind: NotificationKind) -> bool {
+        match kind {
+            NotificationKind::Follow => self.follows,
+            NotificationKind::PostLike | NotificationKind::CommentLike => self.likes,
+            NotificationKind::Comment => self.comments,
+            NotificationKind::Reply => self.replies,
+            NotificationKind::Mention => self.mentions,
+            NotificationKind::ReportResolved => self.reports,
+        }
+    }
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            email: Some(true),
+            follows: true,
+            likes: true,
+            comments: true,
+            replies: true,
+            mentions: true,
+            reports: true,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -26,7 +61,7 @@ pub struct PrivacySettings {}
 
 impl UserSettings {
     pub fn new_from_user(user: &User) -> Self {
-        let notifi_settings = NotificationSettings { email: Some(true) };
+        let notifi_settings = NotificationSettings::default();
 
         let privacy_settings = PrivacySettings {};
 
@@ -48,7 +83,7 @@ impl Default for UserSettings {
             theme: Some("dark".into()),
             page_length: 20,
             nsfw: false,
-            notifications: Some(NotificationSettings { email: Some(true) }),
+            notifications: Some(NotificationSettings::default()),
             privacy: Some(PrivacySettings {}),
         }
     }
@@ -0,0 +1,66 @@
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+
+use crate::models::search::FacetCount;
+
+/// An entry in the tag registry, keyed by its normalized name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    #[serde(rename = "_id")]
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub post_count: u64,
+    #[serde(default)]
+    pub character_count: u64,
+    pub alias_of: Option<String>, // set when this tag is just another spelling of a canonical one
+    #[serde(default)]
+    pub implies: Vec<String>, // tags added automatically alongside this one
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// What a tag is counted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TagKind {
+    Post,
+    Character,
+}
+
+impl TagKind {
+    pub fn count_field(&self) -> &'static str {
+        match self {
+            TagKind::Post => "post_count",
+            TagKind::Character => "character_count",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TagSuggestion {
+    pub name: String,
+    pub post_count: u64,
+    pub alias_of: Option<String>,
+}
+
+impl From<&Tag> for TagSuggestion {
+    fn from(value: &Tag) -> Self {
+        TagSuggestion {
+            name: value.name.clone(),
+            post_count: value.post_count,
+            alias_of: value.alias_of.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TagPageResponse {
+    pub name: String,
+    pub description: Option<String>,
+    pub post_count: u64,
+    pub character_count: u64,
+    pub implies: Vec<String>,
+    pub aliases: Vec<String>,
+    pub related: Vec<FacetCount>, // tags most often used together with this one
+}
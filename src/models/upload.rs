@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::utils::uuid_as_string;
+
+/// A file the client was given an upload URL for but that isn't attached to its post yet.
+/// Finalizing turns it into `Media`, otherwise it expires and the object is deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingUpload {
+    #[serde(rename = "_id", with = "uuid_as_string")]
+    pub id: Uuid,
+    pub post_id: String,
+    pub user_id: String,
+    pub key: String, // where the client puts the file, under storage::STAGING_PREFIX
+    pub content_type: String,
+    pub size_bytes: u64,
+    pub sha256: String, // lowercase hex
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+    // set while a finalize is checking the upload so a second one can't attach it again
+    #[serde(default, with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional")]
+    pub claimed_until: Option<DateTime<Utc>>,
+}
+
+/// One file the client wants to upload directly
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct NewUpload {
+    pub content_type: String,
+    pub size_bytes: u64,
+    pub sha256: String, // hex SHA-256 of the whole file, the upload is refused when it doesn't match
+}
+
+/// Where and how to upload one file, send `upload_id` to finalize once it's there
+#[derive(Serialize, ToSchema)]
+pub struct UploadTicket {
+    pub upload_id: String,
+    pub url: String,
+    pub method: String,
+    pub headers: HashMap<String, String>, // must be sent exactly as given
+    #[serde(serialize_with = "chrono::serde::ts_milliseconds::serialize")]
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UploadTicketsResponse {
+    pub uploads: Vec<UploadTicket>,
+}
@@ -0,0 +1,30 @@
+use std::collections::HashSet;
+
+/// Who is looking at a listing, and whether they opted into NSFW content.
+/// Signed-out visitors are anonymous and never see NSFW unblurred.
+#[derive(Debug, Clone, Default)]
+pub struct Viewer {
+    pub user_id: Option<String>,
+    pub show_nsfw: bool,
+    // which of the posts being shown the viewer bookmarked, see BookmarkService::annotate
+    pub bookmarked_posts: HashSet<String>,
+}
+
+impl Viewer {
+    pub fn anonymous() -> Self {
+        Viewer::default()
+    }
+
+    pub fn signed_in(user_id: String, show_nsfw: bool) -> Self {
+        Viewer { user_id: Some(user_id), show_nsfw, bookmarked_posts: HashSet::new() }
+    }
+
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    /// `None` for signed-out viewers, who can't bookmark
+    pub fn has_bookmarked(&self, post_id: &str) -> Option<bool> {
+        self.user_id.as_ref().map(|_| self.bookmarked_posts.contains(post_id))
+    }
+}
@@ -0,0 +1,103 @@
+use redis::{pipe, AsyncCommands};
+use crate::models::character::Character;
+use crate::utils::error::AppError;
+
+#[derive(Clone)]
+pub struct CharacterCache {
+    pub client: redis::Client,
+}
+
+impl CharacterCache {
+    pub fn new(client: redis::Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<Character>, AppError> {
+        let mut conn = self.client.get_multiplexed_async_connection()
+            .await
+            .map_err(|e| {
+                log::error!("Redis connection error in get: {:?}", e);
+                AppError::InternalServerError("Redis connection failed".into())
+            })?;
+
+        let value: Option<String> = conn.get(format!("character:id:{id}")).await.ok();
+
+        if let Some(json) = value {
+            serde_json::from_str(&json).map(Some).map_err(|e| {
+                log::error!("Failed to parse character from cache: {:?}", e);
+                AppError::InternalServerError("Corrupt cache data".into())
+            })
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn set(&self, character: &Character) -> Result<(), AppError> {
+        let id = character.id.to_string();
+        let mut conn = self.client.get_multiplexed_async_connection()
+            .await
+            .map_err(|e| {
+                log::error!("Redis connection error in set: {:?}", e);
+                AppError::InternalServerError("Redis connection failed".into())
+            })?;
+
+        let json = serde_json::to_string(character)
+            .map_err(|e| {
+                log::error!("Failed to serialize character: {:?}", e);
+                AppError::InternalServerError("Character serialization failed".into())
+            })?;
+
+        let _: () = conn.set_ex(format!("character:id:{id}"), json, 600)
+            .await
+            .map_err(|e| {
+                log::error!("Redis set_ex failed: {:?}", e);
+                AppError::InternalServerError("Failed to set character in cache".into())
+            })?;
+
+        Ok(())
+    }
+
+    pub async fn set_many(&self, characters: &[Character]) -> Result<(), AppError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await
+            .map_err(|e| {
+                log::error!("Redis connection failed: {:?}", e);
+                AppError::InternalServerError("Redis connection failed".into())
+            })?;
+
+        let mut pipeline = pipe();
+
+        for character in characters {
+            if let Ok(json) = serde_json::to_string(character) {
+                let key = format!("character:id:{}", character.id);
+                pipeline.set_ex(key, json, 600);
+            } else {
+                log::warn!("Failed to serialize character for caching: {:?}", character.id);
+            }
+        }
+
+        let _: Vec<()> = pipeline.query_async(&mut conn).await.map_err(|e| {
+            log::error!("Redis pipeline execution failed: {:?}", e);
+            AppError::InternalServerError("Redis pipeline failed".into())
+        })?;
+
+        Ok(())
+    }
+
+    pub async fn invalidate(&self, id: &str) -> Result<(), AppError> {
+        let mut conn = self.client.get_multiplexed_async_connection()
+            .await
+            .map_err(|e| {
+                log::error!("Redis connection error in invalidate: {:?}", e);
+                AppError::InternalServerError("Redis connection failed".into())
+            })?;
+
+        let _: () = conn.del(format!("character:id:{id}"))
+            .await
+            .map_err(|e| {
+                log::error!("Failed to delete character from cache: {:?}", e);
+                AppError::InternalServerError("Failed to delete character cache".into())
+            })?;
+
+        Ok(())
+    }
+}
@@ -1,2 +1,3 @@
 pub mod profile_cache;
-pub mod post_cache;
\ No newline at end of file
+pub mod post_cache;
+pub mod character_cache;
\ No newline at end of file
@@ -103,4 +103,72 @@ impl PostCache {
 
         Ok(())
     }
+
+    /// Gets the cached head of a user's following feed, `key` is `{user_id}:{sfw|nsfw}`
+    pub async fn get_feed_head(&self, key: &str) -> Result<Option<Vec<Post>>, AppError> {
+        let mut conn = self.client.get_multiplexed_async_connection()
+            .await
+            .map_err(|e| {
+                log::error!("Redis connection error in get_feed_head: {:?}", e);
+                AppError::InternalServerError("Redis connection failed".into())
+            })?;
+
+        let value: Option<String> = conn.get(format!("feed:following:{key}")).await.ok();
+
+        if let Some(json) = value {
+            serde_json::from_str(&json).map(Some).map_err(|e| {
+                log::error!("Failed to parse feed head from cache: {:?}", e);
+                AppError::InternalServerError("Corrupt cache data".into())
+            })
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn set_feed_head(&self, key: &str, posts: &[Post]) -> Result<(), AppError> {
+        let mut conn = self.client.get_multiplexed_async_connection()
+            .await
+            .map_err(|e| {
+                log::error!("Redis connection error in set_feed_head: {:?}", e);
+                AppError::InternalServerError("Redis connection failed".into())
+            })?;
+
+        let json = serde_json::to_string(posts)
+            .map_err(|e| {
+                log::error!("Failed to serialize feed head: {:?}", e);
+                AppError::InternalServerError("Feed serialization failed".into())
+            })?;
+
+        // kept short so new posts from followed authors show up quickly
+        let _: () = conn.set_ex(format!("feed:following:{key}"), json, 120)
+            .await
+            .map_err(|e| {
+                log::error!("Redis set_ex failed: {:?}", e);
+                AppError::InternalServerError("Failed to set feed head in cache".into())
+            })?;
+
+        Ok(())
+    }
+
+    /// Drops both the sfw and nsfw variants of a user's following feed head
+    pub async fn invalidate_feed_head(&self, user_id: &str) -> Result<(), AppError> {
+        let mut conn = self.client.get_multiplexed_async_connection()
+            .await
+            .map_err(|e| {
+                log::error!("Redis connection error in invalidate_feed_head: {:?}", e);
+                AppError::InternalServerError("Redis connection failed".into())
+            })?;
+
+        let _: () = conn.del(&[
+                format!("feed:following:{user_id}:sfw"),
+                format!("feed:following:{user_id}:nsfw"),
+            ])
+            .await
+            .map_err(|e| {
+                log::error!("Failed to delete feed head from cache: {:?}", e);
+                AppError::InternalServerError("Failed to delete feed head cache".into())
+            })?;
+
+        Ok(())
+    }
 }
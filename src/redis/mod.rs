@@ -1,3 +1,4 @@
+use crate::redis::cache::character_cache::CharacterCache;
 use crate::redis::cache::post_cache::PostCache;
 use crate::redis::cache::profile_cache::ProfileCache;
 use crate::utils::error::AppError;
@@ -8,6 +9,7 @@ pub mod cache;
 pub struct InkvaultCache {
     pub profile_cache: ProfileCache,
     pub post_cache: PostCache,
+    pub character_cache: CharacterCache,
 }
 
 impl InkvaultCache {
@@ -19,6 +21,7 @@ impl InkvaultCache {
         Ok(Self {
             profile_cache: ProfileCache::new(redis_client.clone()),
             post_cache: PostCache::new(redis_client.clone()),
+            character_cache: CharacterCache::new(redis_client.clone()),
         })
     }
 }
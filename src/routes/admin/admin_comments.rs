@@ -0,0 +1,25 @@
+use actix_web::{ delete, web, HttpResponse, Responder };
+use log::info;
+use serde_json::json;
+use crate::middleware::admin_guard::AdminGuard;
+use crate::middleware::auther::Auther;
+use crate::state::AppState;
+use crate::utils::error::AppError;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    info!("Configuring /api/admin/comments scope");
+    cfg.service(web::scope("comments").service(remove_comment));
+}
+
+// replies under it stay, the comment itself shows as [removed]
+#[delete("/{id}")]
+async fn remove_comment(
+    _guard: AdminGuard,
+    auther: Auther,
+    path: web::Path<String>,
+    state: web::Data<AppState>
+) -> Result<impl Responder, AppError> {
+    state.services.comment_service
+        .remove(&path.into_inner(), &auther.session.user_uuid.to_string()).await?;
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
@@ -0,0 +1,60 @@
+use actix_web::{ delete, get, post, web, HttpResponse, Responder };
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+use crate::middleware::admin_guard::AdminGuard;
+use crate::middleware::auther::Auther;
+use crate::state::AppState;
+use crate::utils::error::AppError;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    info!("Configuring /api/admin/media scope");
+    cfg.service(web::scope("media").service(get_hash_bans).service(ban_hash).service(unban_hash));
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HashBanQuery {
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BanHashBody {
+    pub sha256: String, // the sha256 shown on the media, or computed from the file
+    pub reason: Option<String>,
+}
+
+#[get("/bans")]
+async fn get_hash_bans(
+    _guard: AdminGuard,
+    query: web::Query<HashBanQuery>,
+    state: web::Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let limit = query.limit.unwrap_or(50).min(100);
+    let offset = query.offset.unwrap_or(0);
+    Ok(HttpResponse::Ok().json(state.services.media_service.list_bans(offset, limit as i64).await?))
+}
+
+// files with this hash are refused from now on, what's already posted stays until removed
+#[post("/bans")]
+async fn ban_hash(
+    _guard: AdminGuard,
+    auther: Auther,
+    body: web::Json<BanHashBody>,
+    state: web::Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let body = body.into_inner();
+    let ban = state.services.media_service
+        .ban_hash(&body.sha256, body.reason, &auther.session.user_uuid.to_string()).await?;
+    Ok(HttpResponse::Ok().json(ban))
+}
+
+#[delete("/bans/{sha256}")]
+async fn unban_hash(
+    _guard: AdminGuard,
+    path: web::Path<String>,
+    state: web::Data<AppState>
+) -> Result<impl Responder, AppError> {
+    state.services.media_service.unban_hash(&path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
@@ -81,9 +81,16 @@ pub async fn update_report_status(
     let report_id = path.into_inner();
     let new_status = ReportStatus::try_from(body.status.clone())?;
 
-    let updated = state.db.reporting.update_status(report_id, new_status).await?;
+    let resolved = matches!(new_status, ReportStatus::RESOLVED);
+    let updated = state.db.reporting.update_status(report_id.clone(), new_status).await?;
 
     if updated {
+        if resolved {
+            match state.db.reporting.fetch_by_id(&report_id).await {
+                Ok(report) => state.services.notification_service.report_resolved(&report).await,
+                Err(e) => log::warn!("Failed to load report {} to notify its creator: {:?}", report_id, e),
+            }
+        }
         Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "success" })))
     } else {
         Err(AppError::ReportNotFound)
@@ -0,0 +1,84 @@
+use actix_web::{ delete, patch, post, web, HttpResponse, Responder };
+use serde::Deserialize;
+use serde_json::json;
+use crate::middleware::admin_guard::AdminGuard;
+use crate::state::AppState;
+use crate::utils::error::AppError;
+
+pub fn config(web: &mut web::ServiceConfig) {
+    web.service(
+        web::scope("/tags")
+            .service(patch_tag)
+            .service(add_alias)
+            .service(remove_alias)
+            .service(add_implication)
+            .service(remove_implication)
+    );
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchTagBody {
+    pub description: Option<String>, // empty or missing clears it
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TagTargetBody {
+    pub tag: String,
+}
+
+#[patch("/{name}")]
+async fn patch_tag(
+    path: web::Path<String>,
+    body: web::Json<PatchTagBody>,
+    _guard: AdminGuard,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let name = path.into_inner();
+    state.services.tag_service.set_description(&name, body.into_inner().description).await?;
+    Ok(HttpResponse::Ok().json(state.services.tag_service.get_page(&name).await?))
+}
+
+// makes {name} an alias of body.tag, retagging everything that used it
+#[post("/{name}/alias")]
+async fn add_alias(
+    path: web::Path<String>,
+    body: web::Json<TagTargetBody>,
+    _guard: AdminGuard,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    state.services.tag_service.add_alias(&path.into_inner(), &body.tag).await?;
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
+
+#[delete("/{name}/alias")]
+async fn remove_alias(
+    path: web::Path<String>,
+    _guard: AdminGuard,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    state.services.tag_service.remove_alias(&path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
+
+// makes {name} imply body.tag, also applied to everything already tagged {name}
+#[post("/{name}/implies")]
+async fn add_implication(
+    path: web::Path<String>,
+    body: web::Json<TagTargetBody>,
+    _guard: AdminGuard,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    state.services.tag_service.add_implication(&path.into_inner(), &body.tag).await?;
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
+
+#[delete("/{name}/implies/{implied}")]
+async fn remove_implication(
+    path: web::Path<(String, String)>,
+    _guard: AdminGuard,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let (name, implied) = path.into_inner();
+    state.services.tag_service.remove_implication(&name, &implied).await?;
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
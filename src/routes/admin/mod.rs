@@ -7,6 +7,9 @@ mod admin_profiles;
 mod admin_posts;
 mod admin_reporting;
 mod admin_announcements;
+mod admin_tags;
+mod admin_media;
+mod admin_comments;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     info!("Configuring admin routes under /api/admin");
@@ -19,7 +22,10 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .configure(admin_profiles::config)
             .configure(admin_posts::config)
             .configure(admin_reporting::config)
-            .configure(admin_announcements::config),
+            .configure(admin_announcements::config)
+            .configure(admin_tags::config)
+            .configure(admin_media::config)
+            .configure(admin_comments::config),
     );
 }
 
@@ -0,0 +1,106 @@
+use actix_web::web::{ self, Data, Path, Payload, Query };
+use actix_web::{ get, put, HttpRequest, HttpResponse, Responder };
+use futures::StreamExt;
+use log::info;
+use mime_guess::from_path;
+use serde::Deserialize;
+
+use crate::services::storage::{ Storage, StorageError, MAX_DIRECT_VIDEO_SIZE_BYTES, STAGING_PREFIX };
+use crate::utils::hash::hash_bytes_sha256;
+use crate::state::AppState;
+use crate::utils::error::AppError;
+
+// only does anything with STORAGE_BACKEND=local, otherwise files come from the CDN
+pub fn config(cfg: &mut web::ServiceConfig) {
+    info!("Configuring /files scope");
+    cfg.service(web::scope("/files").service(get_file).service(put_file));
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PresignedParams {
+    pub expires: i64,
+    pub size: u64,
+    pub sha256: String,
+    pub signature: String,
+}
+
+fn storage_error(e: StorageError) -> AppError {
+    match e {
+        StorageError::InvalidKey(_) => AppError::BadRequest("Invalid file path".into()),
+        e => AppError::InternalServerError(e.to_string()),
+    }
+}
+
+#[get("/{key:.*}")]
+async fn get_file(path: Path<String>, state: Data<AppState>) -> Result<impl Responder, AppError> {
+    let Some(local) = state.storage.as_local() else {
+        return Ok(HttpResponse::NotFound().body("Route not found"));
+    };
+
+    let key = path.into_inner();
+    // unchecked uploads stay private until they are finalized
+    if key.starts_with(STAGING_PREFIX) {
+        return Ok(HttpResponse::NotFound().body("File not found"));
+    }
+    let file = local.path_for(&key).map_err(storage_error)?;
+    let bytes = match tokio::fs::read(&file).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(HttpResponse::NotFound().body("File not found"));
+        }
+        Err(e) => {
+            return Err(storage_error(e.into()));
+        }
+    };
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(from_path(&key).first_or_octet_stream())
+            .insert_header(("X-Content-Type-Options", "nosniff"))
+            .body(bytes)
+    )
+}
+
+// target of the links from LocalStorage::presign_upload
+#[put("/{key:.*}")]
+async fn put_file(
+    req: HttpRequest,
+    path: Path<String>,
+    query: Query<PresignedParams>,
+    mut payload: Payload,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let Some(local) = state.storage.as_local() else {
+        return Ok(HttpResponse::NotFound().body("Route not found"));
+    };
+
+    let key = path.into_inner();
+    let content_type = req
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    if !local.verify_upload(&key, &content_type, query.size, &query.sha256, query.expires, &query.signature) {
+        return Err(AppError::Unauthorized("Upload link is invalid or expired".into()));
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|_| AppError::BadRequest("Failed to read upload".into()))?;
+        if (body.len() + chunk.len()) as u64 > query.size || body.len() + chunk.len() > MAX_DIRECT_VIDEO_SIZE_BYTES {
+            return Err(AppError::BadRequest("Upload is larger than signed for".into()));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    if body.len() as u64 != query.size {
+        return Err(AppError::BadRequest("Upload is smaller than signed for".into()));
+    }
+    if hash_bytes_sha256(&body) != query.sha256.to_ascii_lowercase() {
+        return Err(AppError::BadRequest("Upload does not match the signed SHA-256".into()));
+    }
+
+    local.upload(&key, &body, &content_type).await.map_err(storage_error)?;
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,70 @@
+use crate::middleware::auther::Auther;
+use crate::models::bookmark::{ BookmarkKind, BookmarkListResponse };
+use crate::routes::internal::bookmarks::types::{ BookmarkListParams, NewBookmark };
+use crate::state::AppState;
+use crate::utils::cursor::FeedCursor;
+use crate::utils::error::AppError;
+use actix_web::web::{ Data, Json, Path, Query };
+use actix_web::{ delete, get, post, HttpResponse, Responder };
+use serde_json::json;
+
+#[get("")]
+async fn get_bookmarks(
+    auther: Auther,
+    query: Query<BookmarkListParams>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let viewer = auther.viewer(&state).await;
+    let query = query.into_inner();
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let cursor = query.cursor.as_deref().map(FeedCursor::decode).transpose()?;
+    let folder = query.folder.map(|f| Some(f.trim().to_string()).filter(|f| !f.is_empty()));
+
+    let (bookmarks, next_cursor) = state.services.bookmark_service
+        .list(&viewer, query.kind, folder, cursor.as_ref(), limit).await?;
+
+    Ok(HttpResponse::Ok().json(BookmarkListResponse { bookmarks, next_cursor }))
+}
+
+#[get("/folders")]
+async fn get_bookmark_folders(auther: Auther, state: Data<AppState>) -> Result<impl Responder, AppError> {
+    let folders = state.services.bookmark_service.folders(&auther.session.user_uuid.to_string()).await?;
+    Ok(HttpResponse::Ok().json(folders))
+}
+
+#[post("")]
+async fn add_bookmark(
+    auther: Auther,
+    payload: Json<NewBookmark>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let viewer = auther.viewer(&state).await;
+    let payload = payload.into_inner();
+
+    let bookmark = state.services.bookmark_service
+        .add(&viewer, payload.kind, &payload.target_id, payload.folder).await?;
+
+    Ok(
+        HttpResponse::Ok().json(
+            json!({
+            "_id": bookmark.id.to_string(),
+            "kind": bookmark.kind,
+            "target_id": bookmark.target_id,
+            "folder": bookmark.folder,
+        })
+        )
+    )
+}
+
+#[delete("/{kind}/{target_id}")]
+async fn remove_bookmark(
+    auther: Auther,
+    path: Path<(BookmarkKind, String)>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let (kind, target_id) = path.into_inner();
+    state.services.bookmark_service
+        .remove(&auther.session.user_uuid.to_string(), kind, &target_id).await?;
+
+    Ok(HttpResponse::Ok().json(json!({ "message": "Bookmark removed" })))
+}
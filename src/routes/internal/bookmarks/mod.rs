@@ -0,0 +1,23 @@
+use actix_web::web;
+use log::info;
+use crate::routes::internal::bookmarks::handler::{
+    add_bookmark,
+    get_bookmark_folders,
+    get_bookmarks,
+    remove_bookmark,
+};
+
+pub mod handler;
+pub mod types;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    info!("Configuring /api/bookmarks scope");
+    cfg.service(
+        web
+            ::scope("bookmarks")
+            .service(get_bookmark_folders)
+            .service(get_bookmarks)
+            .service(add_bookmark)
+            .service(remove_bookmark)
+    );
+}
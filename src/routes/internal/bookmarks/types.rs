@@ -0,0 +1,20 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::models::bookmark::BookmarkKind;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewBookmark {
+    pub kind: BookmarkKind,
+    pub target_id: String,
+    pub folder: Option<String>, // unsorted when missing, also moves an existing bookmark
+}
+
+// params for listing the signed-in user's bookmarks
+#[derive(Debug, Deserialize)]
+pub struct BookmarkListParams {
+    pub kind: Option<BookmarkKind>,
+    pub folder: Option<String>, // empty = unsorted only, missing = every folder
+    pub limit: Option<u64>,
+    pub cursor: Option<String>, // next_cursor from the previous page
+}
@@ -0,0 +1,250 @@
+use crate::middleware::auther::{ Auther, OptionalAuther };
+use crate::models::bookmark::BookmarkKind;
+use crate::models::character::{ Character, CharacterResponse };
+use crate::models::media::MediaMetadata;
+use crate::models::reaction::Reaction;
+use crate::models::tag::TagKind;
+use crate::models::post::PostFeedResponse;
+use crate::routes::internal::characters::types::{
+    CharacterListParams,
+    CharacterMediaMeta,
+    CharacterSearchQuery,
+    UserPatchCharacter,
+};
+use crate::routes::internal::posts::upload::parse_multipart;
+use crate::state::AppState;
+use crate::utils::cursor::{ FeedPage, FeedSort };
+use crate::utils::error::AppError;
+use crate::utils::tags::parse_tag_list;
+use actix_multipart::Multipart;
+use actix_web::web::{ Data, Json, Path, Query };
+use actix_web::{ delete, get, patch, post, HttpResponse, Responder };
+use chrono::Utc;
+use serde_json::json;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+#[get("/search")]
+pub async fn search_characters(
+    query: Query<CharacterSearchQuery>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let limit = query.limit.unwrap_or(20).min(100);
+    let page = query.page.unwrap_or(1).max(1);
+    let skip = (page - 1).saturating_mul(limit);
+
+    let tags = parse_tag_list(query.tags.as_deref().unwrap_or_default());
+
+    let characters = state.services.character_service.search_by_tags(tags, limit, skip).await?;
+    let response: Vec<CharacterResponse> = characters.iter().map(CharacterResponse::from).collect();
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[post("/new")]
+async fn create_character(
+    auther: Auther,
+    payload: Multipart,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let user = state.db.users.get_by_uuid(&session.user_uuid).await?;
+
+    let (mut fields, media_files) = parse_multipart(payload).await?;
+
+    // extract and validate required form fields
+    let character_uuid = Uuid::new_v4();
+    let name = fields.remove("name").ok_or(AppError::BadRequest("Missing name".into()))?;
+    if name.trim().is_empty() {
+        return Err(AppError::BadRequest("Character name cannot be empty".into()));
+    }
+    let description = fields.remove("description");
+    let tags = fields
+        .remove("tags")
+        .map(|s| parse_tag_list(&s))
+        .unwrap_or_default();
+    let tags = state.services.tag_service.resolve(&tags).await?;
+    let mut media_meta = fields
+        .remove("media_meta")
+        .map(|s| { serde_json::from_str::<Vec<CharacterMediaMeta>>(&s).unwrap_or_default() })
+        .unwrap_or_default()
+        .into_iter();
+
+    // upload reference sheets to storage
+    let mut media = Vec::new();
+    for file in media_files {
+        let mut sheet = state.services.media_service.store(file.content_type, file.data.freeze()).await?;
+
+        let meta = media_meta.next().unwrap_or_default();
+        sheet.metadata = MediaMetadata::Character {
+            character_id: character_uuid.to_string(),
+            pose: meta.pose,
+            emotion: meta.emotion,
+            notes: meta.notes,
+        };
+        media.push(sheet);
+    }
+
+    let character = Character {
+        id: character_uuid,
+        owner: user.username,
+        owner_id: session.user_uuid,
+        name,
+        description,
+        tags,
+        likes: HashSet::new(),
+        dislikes: HashSet::new(),
+        like_count: 0,
+        dislike_count: 0,
+        media,
+        created_at: Utc::now(),
+    };
+
+    state.services.character_service.create(&character).await?;
+    state.services.tag_service.record_usage(TagKind::Character, &[], &character.tags).await;
+    Ok(HttpResponse::Ok().json(CharacterResponse::from(&character)))
+}
+
+#[patch("/edit/{id}")]
+async fn edit_character(
+    auther: Auther,
+    path: Path<String>,
+    payload: Json<UserPatchCharacter>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let character_id = path.into_inner();
+
+    let mut character = state.services.character_service.get_by_id(&character_id).await?;
+
+    if character.owner_id != session.user_uuid {
+        return Err(AppError::Unauthorized("You are not the owner of this character".into()));
+    }
+
+    if let Some(name) = &payload.name {
+        if name.trim().is_empty() {
+            return Err(AppError::BadRequest("Character name cannot be empty".into()));
+        }
+        character.name = name.clone();
+    }
+    if let Some(description) = &payload.description {
+        character.description = description.clone();
+    }
+    let old_tags = character.tags.clone();
+    if let Some(tags) = &payload.tags {
+        character.tags = state.services.tag_service.resolve(tags).await?;
+    }
+
+    state.services.character_service.save(&character).await?;
+    state.services.tag_service.record_usage(TagKind::Character, &old_tags, &character.tags).await;
+
+    Ok(HttpResponse::Ok().json(CharacterResponse::from(&character)))
+}
+
+#[get("/id/{id}")]
+async fn get_character_by_id(
+    auther: OptionalAuther,
+    path: Path<String>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let viewer = auther.viewer(&state).await;
+    let id = path.into_inner();
+    let character = state.services.character_service.get_by_id(&id).await?;
+    Ok(HttpResponse::Ok().json(CharacterResponse::for_viewer(&character, viewer.user_id())))
+}
+
+#[get("/id/{id}/posts")]
+async fn get_character_posts(
+    auther: OptionalAuther,
+    path: Path<String>,
+    query: Query<CharacterListParams>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let mut viewer = auther.viewer(&state).await;
+    let character = state.services.character_service.get_by_id(&path.into_inner()).await?;
+    let limit = query.limit.unwrap_or(20).min(100);
+    let page = query.page.unwrap_or(1).max(1);
+    let feed_page = FeedPage::from_params(query.cursor.as_deref(), (page - 1).saturating_mul(limit))?;
+
+    let posts = state.services.post_service
+        .get_all_by_character(&character.id.to_string(), limit, &feed_page, &viewer).await?;
+    state.services.bookmark_service.annotate(&mut viewer, &posts).await?;
+    Ok(HttpResponse::Ok().json(PostFeedResponse::new(&posts, FeedSort::Newest, limit, &viewer)))
+}
+
+#[get("/by/{username}")]
+async fn get_user_characters(
+    path: Path<String>,
+    query: Query<CharacterListParams>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let username = path.into_inner();
+    let limit = query.limit.unwrap_or(20).min(100);
+    let page = query.page.unwrap_or(1).max(1);
+    let skip = (page - 1).saturating_mul(limit);
+
+    let characters = state.services.character_service.get_all_by_owner(&username, limit, skip).await?;
+    let response: Vec<CharacterResponse> = characters.iter().map(CharacterResponse::from).collect();
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[delete("/delete/{id}")]
+async fn delete_character(
+    auther: Auther,
+    path: Path<String>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let character = state.services.character_service.get_by_id(&path.into_inner()).await?;
+    if character.owner_id != session.user_uuid {
+        return Err(AppError::Unauthorized("You are not the owner of this character".into()));
+    }
+
+    let character_id = character.id.to_string();
+    state.services.character_service.delete(&character_id).await?;
+    state.services.bookmark_service.remove_target(BookmarkKind::Character, &character_id).await?;
+    state.services.tag_service.record_usage(TagKind::Character, &character.tags, &[]).await;
+    state.services.post_service.remove_character_ref(&character_id).await?;
+
+    if let Err(e) = state.services.media_service.release(&character.media).await {
+        log::warn!("Failed to release media of character {}: {:?}", character_id, e);
+    }
+    if let Err(e) = state.storage.delete_prefix(&format!("characterassets/{}/", character_id)).await {
+        log::warn!("Failed to delete assets of character {}: {:?}", character_id, e);
+    }
+
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
+
+#[post("/{id}/like")]
+async fn like_character(
+    auther: Auther,
+    path: Path<String>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let user_id = auther.session.user_uuid.to_string();
+    let (liked, character) = state.services.character_service
+        .react(&path.into_inner(), &user_id, Reaction::Like).await?;
+
+    Ok(
+        HttpResponse::Ok().json(
+            json!({ "liked": liked, "like_count": character.like_count, "dislike_count": character.dislike_count })
+        )
+    )
+}
+
+#[post("/{id}/dislike")]
+async fn dislike_character(
+    auther: Auther,
+    path: Path<String>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let user_id = auther.session.user_uuid.to_string();
+    let (disliked, character) = state.services.character_service
+        .react(&path.into_inner(), &user_id, Reaction::Dislike).await?;
+
+    Ok(
+        HttpResponse::Ok().json(
+            json!({ "disliked": disliked, "like_count": character.like_count, "dislike_count": character.dislike_count })
+        )
+    )
+}
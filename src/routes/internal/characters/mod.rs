@@ -0,0 +1,33 @@
+use actix_web::web;
+use log::info;
+use crate::routes::internal::characters::handler::{
+    create_character,
+    delete_character,
+    dislike_character,
+    edit_character,
+    get_character_by_id,
+    get_character_posts,
+    get_user_characters,
+    like_character,
+    search_characters,
+};
+
+pub mod handler;
+pub mod types;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    info!("Configuring /api/characters scope");
+    cfg.service(
+        web
+            ::scope("characters")
+            .service(search_characters)
+            .service(create_character)
+            .service(edit_character)
+            .service(get_character_by_id)
+            .service(get_character_posts)
+            .service(get_user_characters)
+            .service(delete_character)
+            .service(like_character)
+            .service(dislike_character)
+    );
+}
@@ -0,0 +1,34 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+// params for listing a user's characters
+#[derive(Debug, Deserialize)]
+pub struct CharacterListParams {
+    pub limit: Option<u64>, // max characters per request
+    pub page: Option<u64>, // which page of results, starting at 1
+    pub cursor: Option<String>, // next_cursor from the previous page, only used by post feeds
+}
+
+// params for searching characters by tag
+#[derive(Debug, Deserialize)]
+pub struct CharacterSearchQuery {
+    pub tags: Option<String>, // comma separated, character must have all of them
+    pub limit: Option<u64>,
+    pub page: Option<u64>,
+}
+
+// per-file metadata sent as a JSON array in the `media_meta` form field,
+// matched to the uploaded files by index
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct CharacterMediaMeta {
+    pub pose: Option<String>,
+    pub emotion: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UserPatchCharacter {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>, // some(None) = clear description, None = leave unchanged
+    pub tags: Option<Vec<String>>,
+}
@@ -0,0 +1,166 @@
+use crate::middleware::auther::{ Auther, OptionalAuther };
+use crate::models::collection::CollectionPageResponse;
+use crate::models::post::PostFeedResponse;
+use crate::models::viewer::Viewer;
+use crate::routes::internal::collections::types::{
+    AddCollectionPost,
+    CollectionListParams,
+    NewCollection,
+    ReorderCollection,
+    UserPatchCollection,
+};
+use crate::state::AppState;
+use crate::utils::cursor::FeedSort;
+use crate::utils::error::AppError;
+use actix_web::web::{ Data, Json, Path, Query };
+use actix_web::{ delete, get, patch, post, put, HttpResponse, Responder };
+use serde_json::json;
+
+#[post("/new")]
+async fn create_collection(
+    auther: Auther,
+    payload: Json<NewCollection>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let profile = state.services.profile_service.get_by_uuid(&session.user_uuid).await?;
+    let payload = payload.into_inner();
+
+    let collection = state.services.collection_service
+        .create(session.user_uuid, profile.username, &payload.name, payload.description, payload.visibility).await?;
+
+    let owner = owner_viewer(&session.user_uuid.to_string());
+    let response = state.services.collection_service.to_responses(&[collection], &owner).await?;
+    Ok(HttpResponse::Ok().json(&response[0]))
+}
+
+#[get("/by/{username}")]
+async fn get_user_collections(
+    auther: OptionalAuther,
+    path: Path<String>,
+    query: Query<CollectionListParams>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let viewer = auther.viewer(&state).await;
+    let profile = state.services.profile_service.get_by_username(&path.into_inner()).await?;
+    let limit = query.limit.unwrap_or(20).min(100);
+    let page = query.page.unwrap_or(1).max(1);
+
+    let collections = state.services.collection_service
+        .get_by_owner(&profile.id.to_string(), &viewer, (page - 1).saturating_mul(limit), limit).await?;
+    let response = state.services.collection_service.to_responses(&collections, &viewer).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[get("/id/{id}")]
+async fn get_collection(
+    auther: OptionalAuther,
+    path: Path<String>,
+    query: Query<CollectionListParams>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let mut viewer = auther.viewer(&state).await;
+    let collection = state.services.collection_service.get(&path.into_inner(), &viewer).await?;
+    let limit = query.limit.unwrap_or(20).min(100);
+    let page = query.page.unwrap_or(1).max(1);
+
+    let posts = state.services.collection_service
+        .get_posts(&collection, (page - 1).saturating_mul(limit), limit, &viewer).await?;
+    state.services.bookmark_service.annotate(&mut viewer, &posts).await?;
+    let mut response = state.services.collection_service.to_responses(&[collection], &viewer).await?;
+
+    Ok(
+        HttpResponse::Ok().json(CollectionPageResponse {
+            collection: response.remove(0),
+            page,
+            limit,
+            // collections are paged by position, there's no cursor to hand out
+            posts: PostFeedResponse { next_cursor: None, ..PostFeedResponse::new(&posts, FeedSort::Newest, limit, &viewer) },
+        })
+    )
+}
+
+#[patch("/id/{id}")]
+async fn edit_collection(
+    auther: Auther,
+    path: Path<String>,
+    payload: Json<UserPatchCollection>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let mut collection = state.services.collection_service
+        .get_owned(&path.into_inner(), &session.user_uuid).await?;
+    let payload = payload.into_inner();
+
+    state.services.collection_service
+        .update(&mut collection, payload.name, payload.description, payload.visibility, payload.cover_post_id).await?;
+
+    let owner = owner_viewer(&session.user_uuid.to_string());
+    let response = state.services.collection_service.to_responses(&[collection], &owner).await?;
+    Ok(HttpResponse::Ok().json(&response[0]))
+}
+
+#[delete("/id/{id}")]
+async fn delete_collection(
+    auther: Auther,
+    path: Path<String>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let collection = state.services.collection_service
+        .get_owned(&path.into_inner(), &session.user_uuid).await?;
+
+    state.services.collection_service.delete(&collection.id.to_string()).await?;
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
+
+#[post("/id/{id}/posts")]
+async fn add_collection_post(
+    auther: Auther,
+    path: Path<String>,
+    payload: Json<AddCollectionPost>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let collection = state.services.collection_service
+        .get_owned(&path.into_inner(), &session.user_uuid).await?;
+
+    let owner = owner_viewer(&session.user_uuid.to_string());
+    state.services.collection_service
+        .add_post(&collection, &payload.post_id, payload.position, &owner).await?;
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
+
+#[delete("/id/{id}/posts/{post_id}")]
+async fn remove_collection_post(
+    auther: Auther,
+    path: Path<(String, String)>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let (id, post_id) = path.into_inner();
+    let mut collection = state.services.collection_service.get_owned(&id, &session.user_uuid).await?;
+
+    state.services.collection_service.remove_post(&mut collection, &post_id).await?;
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
+
+#[put("/id/{id}/order")]
+async fn reorder_collection(
+    auther: Auther,
+    path: Path<String>,
+    payload: Json<ReorderCollection>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let collection = state.services.collection_service
+        .get_owned(&path.into_inner(), &session.user_uuid).await?;
+
+    state.services.collection_service.reorder(&collection, payload.into_inner().post_ids).await?;
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
+
+// owners see their own collections' covers unblurred
+fn owner_viewer(user_id: &str) -> Viewer {
+    Viewer::signed_in(user_id.to_string(), true)
+}
@@ -0,0 +1,31 @@
+use actix_web::web;
+use log::info;
+use crate::routes::internal::collections::handler::{
+    add_collection_post,
+    create_collection,
+    delete_collection,
+    edit_collection,
+    get_collection,
+    get_user_collections,
+    remove_collection_post,
+    reorder_collection,
+};
+
+pub mod handler;
+pub mod types;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    info!("Configuring /api/collections scope");
+    cfg.service(
+        web
+            ::scope("collections")
+            .service(create_collection)
+            .service(get_user_collections)
+            .service(get_collection)
+            .service(edit_collection)
+            .service(delete_collection)
+            .service(add_collection_post)
+            .service(remove_collection_post)
+            .service(reorder_collection)
+    );
+}
@@ -0,0 +1,38 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::models::collection::CollectionVisibility;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewCollection {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub visibility: CollectionVisibility, // public by default
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UserPatchCollection {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>, // some(None) = clear description, None = leave unchanged
+    pub visibility: Option<CollectionVisibility>,
+    pub cover_post_id: Option<Option<String>>, // some(None) = back to the first post
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddCollectionPost {
+    pub post_id: String,
+    pub position: Option<usize>, // 0 based, appended when missing
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReorderCollection {
+    pub post_ids: Vec<String>, // every post in the collection, in the new order
+}
+
+// params for paging through collections or the posts in one
+#[derive(Debug, Deserialize)]
+pub struct CollectionListParams {
+    pub page: Option<u64>, // starting at 1
+    pub limit: Option<u64>,
+}
@@ -1,41 +1,36 @@
-use actix_web::{get, post, web::{self, Data, Path}, HttpResponse, Responder};
+use actix_web::{delete, get, patch, post, web::{self, Data, Path, Query}, HttpResponse, Responder};
+use serde_json::json;
 use uuid::Uuid;
 use std::str::FromStr;
 
 use crate::{
-    middleware::auther::Auther,
-    models::comment::{Comment, CommentReqInput, Reply},
+    middleware::auther::{Auther, OptionalAuther},
+    models::comment::{CommentReqInput, CommentResponse},
+    models::reaction::Reaction,
     state::AppState,
     utils::error::AppError,
 };
-use crate::routes::internal::comment::utils::toggle_reaction;
 use super::types::*;
 #[get("/fetch/{id}")]
 pub async fn get_comments(
+    auther: OptionalAuther,
     path: Path<String>,
+    query: Query<CommentPageParams>,
     state: Data<AppState>,
 ) -> Result<impl Responder, AppError> {
     let post_id = path.into_inner();
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let viewer = auther.viewer(&state).await;
 
-    let comments = state.db.comments.get_for_post(&post_id).await?;
-
-    let mut result = Vec::new();
-    for comment in comments {
-        let replies_doc = state.db.comment_replies.get_replies(&comment.id.to_string()).await;
-        let has_replies = replies_doc.map(|r| r.has_replies).unwrap_or(false);
-
-        result.push(CommentWithRepliesFlag {
-            comment,
-            has_replies,
-        });
-    }
-
-    Ok(HttpResponse::Ok().json(result))
+    let page = state.services.comment_service
+        .list_for_post(&post_id, &viewer, query.sort, query.cursor.as_deref(), limit)
+        .await?;
+    Ok(HttpResponse::Ok().json(page))
 }
 
 #[post("/create/{id}")]
 pub async fn post_comment(
-    _author: Auther,
+    author: Auther,
     path: Path<String>,
     data: web::Json<CommentReqInput>,
     state: Data<AppState>,
@@ -43,10 +38,104 @@ pub async fn post_comment(
     let post_id = Uuid::from_str(&path.into_inner())
         .map_err(|_| AppError::BadRequest("Invalid post_id".into()))?;
 
-    let comment = Comment::new(data.author.clone(), data.content.clone(), post_id);
-    state.db.comments.create(&comment).await?;
-    state.db.comment_replies.create(&comment.id).await?;
-    Ok(HttpResponse::Ok().json(comment))
+    let viewer = author.viewer(&state).await;
+    let user = state.db.users.get_by_uuid(&author.session.user_uuid).await?;
+    let comment = state.services.comment_service
+        .create(&viewer, user.username, data.into_inner().content, post_id)
+        .await?;
+    Ok(HttpResponse::Ok().json(CommentResponse::for_viewer(&comment, viewer.user_id())))
+}
+
+#[patch("/{id}")]
+pub async fn edit_comment(
+    author: Auther,
+    path: Path<String>,
+    data: web::Json<CommentEditInput>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user = state.db.users.get_by_uuid(&author.session.user_uuid).await?;
+    let viewer_id = author.session.user_uuid.to_string();
+    let comment = state.services.comment_service
+        .edit(&path.into_inner(), &viewer_id, &user.username, data.into_inner().content)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(CommentResponse::for_viewer(&comment, Some(&viewer_id))))
+}
+
+#[delete("/{id}")]
+pub async fn delete_comment(
+    author: Auther,
+    path: Path<String>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user = state.db.users.get_by_uuid(&author.session.user_uuid).await?;
+    state.services.comment_service
+        .delete(&path.into_inner(), &author.session.user_uuid.to_string(), &user.username)
+        .await?;
+    Ok(HttpResponse::Ok().json(json!({ "success": true })))
+}
+
+#[get("/pending/{post_id}")]
+pub async fn get_pending_comments(
+    author: Auther,
+    path: Path<String>,
+    query: Query<CommentPageParams>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let viewer = author.viewer(&state).await;
+    let page = state.services.comment_service
+        .list_pending(&path.into_inner(), &viewer, query.cursor.as_deref(), limit)
+        .await?;
+    Ok(HttpResponse::Ok().json(page))
+}
+
+#[post("/{id}/approve")]
+pub async fn approve_comment(
+    author: Auther,
+    path: Path<String>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let viewer = author.viewer(&state).await;
+    let comment = state.services.comment_service.approve(&path.into_inner(), &viewer).await?;
+    Ok(HttpResponse::Ok().json(CommentResponse::for_viewer(&comment, viewer.user_id())))
+}
+
+#[patch("/settings/{post_id}")]
+pub async fn update_comment_settings(
+    author: Auther,
+    path: Path<String>,
+    data: web::Json<CommentSettingsPatch>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let viewer = author.viewer(&state).await;
+    let patch = data.into_inner();
+    let settings = state.services.comment_service
+        .update_settings(&path.into_inner(), &viewer, patch.locked, patch.followers_only, patch.require_approval)
+        .await?;
+    Ok(HttpResponse::Ok().json(settings))
+}
+
+#[post("/{id}/pin")]
+pub async fn pin_comment(
+    author: Auther,
+    path: Path<String>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let viewer = author.viewer(&state).await;
+    let settings = state.services.comment_service.pin(&path.into_inner(), &viewer).await?;
+    Ok(HttpResponse::Ok().json(settings))
+}
+
+#[delete("/pin/{post_id}")]
+pub async fn unpin_comment(
+    author: Auther,
+    path: Path<String>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let viewer = author.viewer(&state).await;
+    let settings = state.services.comment_service.unpin(&path.into_inner(), &viewer).await?;
+    Ok(HttpResponse::Ok().json(settings))
 }
 
 #[post("/{id}/like")]
@@ -55,13 +144,11 @@ pub async fn like_comment(
     path: Path<String>,
     state: Data<AppState>,
 ) -> Result<impl Responder, AppError> {
-    let session = author.session;
-    let user_id = session.user_uuid.to_string();
-    let mut comment = state.db.comments.get_by_id(&path.into_inner()).await?;
-
-    let liked = toggle_reaction(&mut comment.likes, &mut comment.dislikes, &user_id, true);
+    let viewer = author.viewer(&state).await;
+    let liked = state.services.comment_service
+        .react(&path.into_inner(), &viewer, Reaction::Like)
+        .await?;
 
-    state.db.comments.save(&comment).await?;
     Ok(HttpResponse::Ok().json(ToggleResponse { liked }))
 }
 
@@ -71,13 +158,11 @@ pub async fn dislike_comment(
     path: Path<String>,
     state: Data<AppState>,
 ) -> Result<impl Responder, AppError> {
-    let session = author.session;
-    let user_id = session.user_uuid.to_string();
-    let mut comment = state.db.comments.get_by_id(&path.into_inner()).await?;
-
-    let disliked = toggle_reaction(&mut comment.likes, &mut comment.dislikes, &user_id, false);
+    let viewer = author.viewer(&state).await;
+    let disliked = state.services.comment_service
+        .react(&path.into_inner(), &viewer, Reaction::Dislike)
+        .await?;
 
-    state.db.comments.save(&comment).await?;
     Ok(HttpResponse::Ok().json(DislikeResponse { disliked }))
 }
 
@@ -88,25 +173,31 @@ pub async fn reply_to_comment(
     state: Data<AppState>,
     data: web::Json<CommentReplyInput>,
 ) -> Result<impl Responder, AppError> {
-    let  parent_id = path.into_inner();
+    let parent_id = path.into_inner();
 
-    let session = author.session;
-    let user_id = session.user_uuid.to_string();
-    let user = state.db.users.get_by_uuid(&Uuid::from_str(&*user_id).unwrap()).await?;
+    let viewer = author.viewer(&state).await;
+    let user = state.db.users.get_by_uuid(&author.session.user_uuid).await?;
 
-    let reply = Reply::new(user.username, data.content.clone());
-    state.db.comment_replies.add_reply_by_parent_id(&parent_id, &reply).await?;
+    let reply = state.services.comment_service
+        .reply(&parent_id, &viewer, user.username, data.into_inner().content)
+        .await?;
 
-    Ok(HttpResponse::Ok().json(reply))
+    Ok(HttpResponse::Ok().json(CommentResponse::for_viewer(&reply, viewer.user_id())))
 }
 
 #[get("/reply/{comment_id}")]
 pub async fn get_replies(
+    auther: OptionalAuther,
     path: Path<String>,
+    query: Query<CommentPageParams>,
     state: Data<AppState>,
 ) -> Result<impl Responder, AppError> {
-    let replies = state.db.comment_replies.get_replies(&path.into_inner()).await?;
-    Ok(HttpResponse::Ok().json(replies))
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let viewer = auther.viewer(&state).await;
+    let page = state.services.comment_service
+        .list_replies(&path.into_inner(), &viewer, query.sort, query.cursor.as_deref(), limit)
+        .await?;
+    Ok(HttpResponse::Ok().json(page))
 }
 
 #[post("/reply/{id}/like")]
@@ -116,10 +207,10 @@ pub async fn like_reply(
     state: Data<AppState>,
 ) -> Result<impl Responder, AppError> {
     let reply_id = path.into_inner();
-    let session = author.session;
-    let user_id = session.user_uuid.to_string();
+    let viewer = author.viewer(&state).await;
 
-    let updated = state.db.comment_replies.like_reply(&reply_id, &user_id).await?;
+    // replies are comments of their own now, this stays for older clients
+    let updated = state.services.comment_service.react(&reply_id, &viewer, Reaction::Like).await?;
     Ok(HttpResponse::Ok().json(ToggleResponse { liked: updated }))
 }
 
@@ -130,9 +221,8 @@ pub async fn dislike_reply(
     state: Data<AppState>,
 ) -> Result<impl Responder, AppError> {
     let reply_id = path.into_inner();
-    let session = author.session;
-    let user_id = session.user_uuid.to_string();
+    let viewer = author.viewer(&state).await;
 
-    let updated = state.db.comment_replies.dislike_reply(&reply_id, &user_id).await?;
+    let updated = state.services.comment_service.react(&reply_id, &viewer, Reaction::Dislike).await?;
     Ok(HttpResponse::Ok().json(DislikeResponse { disliked: updated }))
 }
@@ -1,19 +1,25 @@
 use actix_web::web;
 use log::info;
 use crate::routes::internal::comment::handlers::{
+    approve_comment,
+    delete_comment,
     dislike_comment,
     dislike_reply,
+    edit_comment,
     get_comments,
+    get_pending_comments,
     get_replies,
     like_comment,
     like_reply,
+    pin_comment,
     post_comment,
     reply_to_comment,
+    unpin_comment,
+    update_comment_settings,
 };
 
 mod handlers;
 pub mod types;
-mod utils;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     info!("Configuring /api/comment scope");
@@ -24,6 +30,13 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .service(dislike_comment)
             .service(get_comments)
             .service(post_comment)
+            .service(edit_comment)
+            .service(delete_comment)
+            .service(get_pending_comments)
+            .service(approve_comment)
+            .service(update_comment_settings)
+            .service(pin_comment)
+            .service(unpin_comment)
             .service(reply_to_comment)
             .service(get_replies)
             .service(like_reply)
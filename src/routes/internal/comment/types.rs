@@ -1,16 +1,28 @@
 use serde::{ Deserialize, Serialize };
 use utoipa::ToSchema;
-use crate::models::comment::Comment;
+use crate::models::comment::CommentSort;
 #[derive(Deserialize, Clone, ToSchema)]
 pub struct CommentReplyInput {
     pub content: String,
 }
 
-#[derive(Serialize, ToSchema)]
-pub struct CommentWithRepliesFlag {
-    #[serde(flatten)]
-    pub comment: Comment,
-    pub has_replies: bool,
+#[derive(Deserialize, Clone, ToSchema)]
+pub struct CommentEditInput {
+    pub content: String,
+}
+
+#[derive(Deserialize, Clone, ToSchema)]
+pub struct CommentSettingsPatch {
+    pub locked: Option<bool>,
+    pub followers_only: Option<bool>,
+    pub require_approval: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct CommentPageParams {
+    pub sort: Option<CommentSort>, // top, newest or oldest
+    pub limit: Option<u64>,
+    pub cursor: Option<String>, // next_cursor from the previous page
 }
 
 #[derive(Serialize, ToSchema)]
@@ -9,6 +9,11 @@ pub mod comment;
 pub mod reporting;
 pub mod posts;
 pub mod settings;
+pub mod characters;
+pub mod tags;
+pub mod collections;
+pub mod bookmarks;
+pub mod notifications;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     info!("Configuring internal routes under /api");
@@ -20,5 +25,10 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         .configure(user::config)
         .configure(comment::config)
         .configure(reporting::config)
-        .configure(settings::config);
+        .configure(settings::config)
+        .configure(characters::config)
+        .configure(tags::config)
+        .configure(collections::config)
+        .configure(bookmarks::config)
+        .configure(notifications::config);
 }
@@ -0,0 +1,51 @@
+use crate::middleware::auther::Auther;
+use crate::routes::internal::notifications::types::{ MarkReadRequest, NotificationListParams };
+use crate::state::AppState;
+use crate::utils::cursor::FeedCursor;
+use crate::utils::error::AppError;
+use actix_web::web::{ Data, Json, Query };
+use actix_web::{ get, post, HttpResponse, Responder };
+use serde_json::json;
+
+// more ids than a page holds is a client bug
+const MAX_MARK_READ_IDS: usize = 100;
+
+#[get("")]
+async fn get_notifications(
+    auther: Auther,
+    query: Query<NotificationListParams>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let query = query.into_inner();
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let cursor = query.cursor.as_deref().map(FeedCursor::decode).transpose()?;
+
+    let notifications = state.services.notification_service
+        .list(&auther.session.user_uuid.to_string(), query.unread_only, cursor.as_ref(), limit).await?;
+
+    Ok(HttpResponse::Ok().json(notifications))
+}
+
+#[get("/unread_count")]
+async fn get_unread_count(auther: Auther, state: Data<AppState>) -> Result<impl Responder, AppError> {
+    let count = state.services.notification_service.unread_count(&auther.session.user_uuid.to_string()).await?;
+    Ok(HttpResponse::Ok().json(json!({ "unread_count": count })))
+}
+
+#[post("/read")]
+async fn mark_notifications_read(
+    auther: Auther,
+    payload: Json<MarkReadRequest>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let payload = payload.into_inner();
+    if payload.ids.as_ref().is_some_and(|ids| ids.len() > MAX_MARK_READ_IDS) {
+        return Err(AppError::BadRequest(format!("At most {} notifications can be marked at once", MAX_MARK_READ_IDS)));
+    }
+
+    let user_id = auther.session.user_uuid.to_string();
+    let marked = state.services.notification_service.mark_read(&user_id, payload.ids.as_deref()).await?;
+    let unread = state.services.notification_service.unread_count(&user_id).await?;
+
+    Ok(HttpResponse::Ok().json(json!({ "marked": marked, "unread_count": unread })))
+}
@@ -0,0 +1,21 @@
+use actix_web::web;
+use log::info;
+use crate::routes::internal::notifications::handler::{
+    get_notifications,
+    get_unread_count,
+    mark_notifications_read,
+};
+
+pub mod handler;
+pub mod types;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    info!("Configuring /api/notifications scope");
+    cfg.service(
+        web
+            ::scope("notifications")
+            .service(get_unread_count)
+            .service(get_notifications)
+            .service(mark_notifications_read)
+    );
+}
@@ -0,0 +1,16 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+// params for listing the signed-in user's notifications
+#[derive(Debug, Deserialize)]
+pub struct NotificationListParams {
+    #[serde(default)]
+    pub unread_only: bool,
+    pub limit: Option<u64>,
+    pub cursor: Option<String>, // next_cursor from the previous page
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MarkReadRequest {
+    pub ids: Option<Vec<String>>, // every unread notification when missing
+}
@@ -1,125 +1,163 @@
-use crate::middleware::auther::Auther;
-use crate::models::media::{ Media, MediaMetadata };
-use crate::models::post::{ Post, PostResponse };
-use crate::routes::internal::posts::types::{ LatestPostParams, PostSearchQuery, UserPatchPost };
+use crate::middleware::auther::{ Auther, OptionalAuther };
+use crate::models::bookmark::BookmarkKind;
+use crate::models::post::{ CommentSettings, Post, PostDetailResponse, PostFeedResponse, PostResponse };
+use crate::models::profile::ProfileSummary;
+use crate::models::reaction::Reaction;
+use crate::models::revision::{ Editor, PostSnapshot };
+use crate::models::search::SearchResponse;
+use crate::models::tag::TagKind;
+use crate::models::upload::UploadTicketsResponse;
+use crate::models::viewer::Viewer;
+use crate::routes::internal::posts::types::{
+    DraftListParams,
+    LatestPostParams,
+    PopularPostParams,
+    PostSearchQuery,
+    ReactorListParams,
+    RevisionListParams,
+    FinalizeUploadsRequest,
+    UploadRequest,
+    UserPatchPost,
+};
 use crate::routes::internal::posts::upload::parse_multipart;
+use crate::services::internal::search_service::SearchSort;
 use crate::state::AppState;
+use crate::utils::cursor::{ FeedPage, FeedSort };
 use crate::utils::error::AppError;
-use crate::utils::post::PostType;
+use crate::utils::post::{ PostStatus, PostType };
+use crate::utils::search::ParsedQuery;
+use crate::utils::tags::parse_tag_list;
+use crate::utils::trending::PopularWindow;
 use actix_multipart::Multipart;
 use actix_web::web::{ Data, Json, Path, Query };
 use actix_web::{ delete, get, patch, post, HttpResponse, Responder };
-use bson::doc;
-use chrono::Utc;
-use futures_util::TryStreamExt;
-use mongodb::options::FindOptions;
+use chrono::{ DateTime, Utc };
 use serde_json::json;
 use std::collections::HashSet;
 use uuid::Uuid;
 
 #[get("")]
 pub async fn search_posts(
+    auther: OptionalAuther,
     query: Query<PostSearchQuery>,
     state: Data<AppState>
 ) -> Result<impl Responder, AppError> {
-    let page = query.page.unwrap_or(1);
-    let limit = query.limit.unwrap_or(20).min(100);
-    let skip = (page - 1) * limit;
+    let mut viewer = auther.viewer(&state).await;
+    let page = query.page.unwrap_or(1).max(1) as u64;
+    let limit = query.limit.unwrap_or(20).min(100) as u64;
+    let feed_page = FeedPage::from_params(query.cursor.as_deref(), (page - 1).saturating_mul(limit))?;
+
+    // the `tags` and `author` params are shorthands for the query syntax
+    let mut parsed = ParsedQuery::parse(query.query.as_deref().unwrap_or_default());
+    if let Some(ref author) = query.author {
+        parsed.authors.push(author.clone());
+    }
+    let any_tags = parse_tag_list(query.tags.as_deref().unwrap_or_default());
 
-    let mut filter = doc! {};
+    let sort = match query.sort.as_deref() {
+        Some("createdAt_asc") => SearchSort::Feed(FeedSort::Oldest),
+        Some("createdAt_desc") => SearchSort::Feed(FeedSort::Newest),
+        Some("likes_desc") => SearchSort::Feed(FeedSort::MostLiked),
+        Some("relevance") => SearchSort::Relevance,
+        _ if parsed.has_text() => SearchSort::Relevance,
+        _ => SearchSort::Feed(FeedSort::Newest),
+    };
 
-    // try $text search if query provided
-    let mut used_regex = false;
-    if let Some(ref q) = query.query {
-        filter.insert("$text", doc! { "$search": q });
-    }
+    let results = state.services.search_service
+        .search(&parsed, any_tags, sort, &feed_page, limit, &viewer).await?;
+    state.services.bookmark_service.annotate(&mut viewer, &results.posts).await?;
 
-    // tags
-    if let Some(ref tag_str) = query.tags {
-        let tags: Vec<&str> = tag_str.split(',').collect();
-        filter.insert("tags", doc! { "$in": tags });
-    }
+    Ok(
+        HttpResponse::Ok().json(SearchResponse {
+            total: results.total,
+            page,
+            limit,
+            used_regex: results.used_regex,
+            posts: results.posts
+                .iter()
+                .map(|post| PostResponse::for_viewer(post, &viewer))
+                .collect(),
+            next_cursor: results.next_cursor,
+            facets: results.facets,
+            highlights: results.highlights,
+        })
+    )
+}
 
-    // author
-    if let Some(ref author_name) = query.author {
-        let author = state.services.profile_service.get_by_username(author_name).await?;
-        filter.insert("author_id", author.id.to_string());
+// how many characters a single post can feature
+const MAX_POST_CHARACTERS: usize = 10;
+
+/// Checks the characters an author wants to feature in a post.
+///
+/// Every id has to point at an existing character. Authors can always feature their own
+/// characters, someone else's character can only be featured if its owner follows the author.
+async fn validate_character_refs(
+    state: &AppState,
+    author_id: &Uuid,
+    ids: Vec<String>
+) -> Result<Vec<String>, AppError> {
+    let mut seen = HashSet::new();
+    let ids: Vec<String> = ids
+        .into_iter()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty() && seen.insert(id.clone()))
+        .collect();
+
+    if ids.len() > MAX_POST_CHARACTERS {
+        return Err(
+            AppError::BadRequest(format!("A post can feature at most {} characters", MAX_POST_CHARACTERS))
+        );
     }
 
-    // sorting
-    let sort = match query.sort.as_deref() {
-        Some("createdAt_asc") => doc! { "created_at": 1 },
-        Some("createdAt_desc") => doc! { "created_at": -1 },
-        Some("likes_desc") => doc! { "likes": -1 },
-        _ => doc! { "created_at": -1 },
-    };
+    for id in &ids {
+        let character = state.services.character_service
+            .get_by_id(id).await
+            .map_err(|e| match e {
+                AppError::CharacterNotFound =>
+                    AppError::BadRequest(format!("Character {} does not exist", id)),
+                other => other,
+            })?;
+
+        if character.owner_id == *author_id {
+            continue;
+        }
 
-    // run query
-    let mut cursor = state.db.posts.coll
-        .find(
-            filter.clone(),
-            FindOptions::builder()
-                .skip(Some(skip.into()))
-                .limit(Some(limit.into()))
-                .sort(sort.clone())
-                .build()
-        ).await
-        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
-
-    let mut posts = Vec::new();
-    while
-        let Some(result) = cursor
-            .try_next().await
-            .map_err(|e| AppError::InternalServerError(e.to_string()))?
-    {
-        posts.push(result);
+        let owner = state.services.profile_service.get_by_uuid(&character.owner_id).await?;
+        if !owner.following.contains(&author_id.to_string()) {
+            return Err(
+                AppError::Unauthorized(
+                    format!("You are not allowed to feature {}'s character {}", owner.username, character.name)
+                )
+            );
+        }
     }
 
-    if posts.is_empty() {
-        if let Some(ref q) = query.query {
-            used_regex = true;
-            let regex_filter =
-                doc! {
-                "$or": [
-                    { "title": { "$regex": q, "$options": "i" } },
-                    { "content": { "$regex": q, "$options": "i" } }
-                ]
-            };
-
-            let mut cursor = state.db.posts.coll
-                .find(
-                    regex_filter,
-                    FindOptions::builder()
-                        .skip(Some(skip.into()))
-                        .limit(Some(limit.into()))
-                        .sort(sort)
-                        .build()
-                ).await
-                .map_err(|e| AppError::InternalServerError(e.to_string()))?;
-
-            while
-                let Some(result) = cursor
-                    .try_next().await
-                    .map_err(|e| AppError::InternalServerError(e.to_string()))?
-            {
-                posts.push(result);
+    Ok(ids)
+}
+
+/// Works out the status a post ends up with. A `publish_at` on its own means scheduled,
+/// and scheduling needs a time in the future.
+fn resolve_publish_state(
+    status: Option<PostStatus>,
+    publish_at: Option<DateTime<Utc>>
+) -> Result<(PostStatus, Option<DateTime<Utc>>), AppError> {
+    let status = status.unwrap_or(match publish_at {
+        Some(_) => PostStatus::Scheduled,
+        None => PostStatus::Published,
+    });
+
+    match status {
+        PostStatus::Scheduled => {
+            let publish_at = publish_at.ok_or(
+                AppError::BadRequest("Scheduled posts need a publish_at time".into())
+            )?;
+            if publish_at <= Utc::now() {
+                return Err(AppError::BadRequest("publish_at must be in the future".into()));
             }
+            Ok((status, Some(publish_at)))
         }
+        PostStatus::Draft | PostStatus::Published => Ok((status, None)),
     }
-
-    // respond
-    let response: Vec<PostResponse> = posts.iter().map(PostResponse::from).collect();
-    Ok(
-        HttpResponse::Ok().json(
-            json!({
-        "total": response.len(),
-        "page": page,
-        "limit": limit,
-        "used_regex": used_regex, // debug flag
-        "posts": response,
-    })
-        )
-    )
 }
 
 #[post("/new")]
@@ -139,8 +177,9 @@ async fn create_post(
     let body = fields.remove("body");
     let tags = fields
         .remove("tags")
-        .map(|s| { serde_json::from_str::<Vec<String>>(&s).unwrap_or_default() })
+        .map(|s| parse_tag_list(&s))
         .unwrap_or_default();
+    let tags = state.services.tag_service.resolve(&tags).await?;
     let mut nsfw = fields
         .get("nsfw")
         .map(|v| v == "true")
@@ -150,35 +189,38 @@ async fn create_post(
         nsfw = true;
     }
 
+    let characters = fields
+        .remove("characters")
+        .map(|s| { serde_json::from_str::<Vec<String>>(&s).unwrap_or_default() })
+        .unwrap_or_default();
+    let characters = validate_character_refs(&state, &session.user_uuid, characters).await?;
+
+    let status = match fields.remove("status").as_deref() {
+        None | Some("") => None,
+        Some("draft") => Some(PostStatus::Draft),
+        Some("scheduled") => Some(PostStatus::Scheduled),
+        Some("published") => Some(PostStatus::Published),
+        Some(other) => {
+            return Err(AppError::BadRequest(format!("Unknown post status {}", other)));
+        }
+    };
+    let publish_at = match fields.remove("publish_at").filter(|s| !s.is_empty()) {
+        Some(raw) =>
+            Some(
+                DateTime::parse_from_rfc3339(&raw)
+                    .map_err(|_| AppError::BadRequest("publish_at must be an RFC 3339 time".into()))?
+                    .with_timezone(&Utc)
+            ),
+        None => None,
+    };
+    let (status, publish_at) = resolve_publish_state(status, publish_at)?;
+
     let post_type = PostType::Generic;
 
-    // upload media to R2
+    // upload media to storage, images get their variants on the way
     let mut media = Vec::new();
     for file in media_files {
-        state.r2
-            .upload_post_asset(post_uuid, post_type.clone(), &file.filename, &file.data).await
-            .map_err(|_| AppError::InternalServerError("Failed to upload media".into()))?;
-
-        let url = format!(
-            "https://{}/postassets/{}/{}",
-            state.cdn_domain,
-            post_uuid,
-            file.filename
-        );
-        media.push(Media {
-            url,
-            filename: file.filename,
-            content_type: file.content_type,
-            size_bytes: file.data.len() as u64,
-            uploaded_at: Utc::now(),
-            is_nsfw: None,
-
-            metadata: MediaMetadata::Post {
-                width: None,
-                height: None,
-                duration_secs: None,
-            },
-        });
+        media.push(state.services.media_service.store(file.content_type, file.data.freeze()).await?);
     }
 
     let post = Post {
@@ -192,11 +234,25 @@ async fn create_post(
         nsfw,
         likes: HashSet::new(),
         dislikes: HashSet::new(),
+        like_count: 0,
+        dislike_count: 0,
         media,
+        characters,
+        views: 0,
+        engagement: 0.0,
+        trending_score: 0.0,
+        status,
+        comment_settings: CommentSettings::default(),
+        publish_at,
+        edited_at: None,
         created_at: Utc::now(),
     };
 
     state.services.post_service.create(&post).await?;
+    // drafts and scheduled posts count their tags once they go live
+    if post.is_published() {
+        state.services.tag_service.record_usage(TagKind::Post, &[], &post.tags).await;
+    }
     Ok(HttpResponse::Ok().json(PostResponse::from(&post)))
 }
 
@@ -217,6 +273,7 @@ async fn edit_post(
     if post.author_id != session.user_uuid {
         return Err(AppError::Unauthorized("You are not the author of this post".into()))?;
     }
+    let before = PostSnapshot::from(&post);
 
     if let Some(title) = &payload.title {
         post.title = title.clone();
@@ -224,27 +281,124 @@ async fn edit_post(
     if let Some(body) = &payload.body {
         post.body = body.clone();
     }
+    let old_tags = post.tags.clone();
     if let Some(tags) = &payload.tags {
-        post.tags = tags.clone();
+        post.tags = state.services.tag_service.resolve(tags).await?;
     }
     if let Some(nsfw) = payload.nsfw {
         post.nsfw = nsfw;
     }
+    if let Some(characters) = &payload.characters {
+        post.characters = validate_character_refs(&state, &session.user_uuid, characters.clone()).await?;
+    }
 
-    // Save changes
-    state.services.post_service.save(&post).await?;
+    let was_published = post.is_published();
+    let was_status = post.status;
+    if payload.status.is_some() || payload.publish_at.is_some() {
+        let requested = payload.status.unwrap_or(match payload.publish_at {
+            Some(_) => PostStatus::Scheduled,
+            None => post.status,
+        });
 
-    Ok(HttpResponse::Ok().json(PostResponse::from(&post)))
+        if was_published && requested != PostStatus::Published {
+            return Err(AppError::BadRequest("Published posts can't go back to drafts".into()));
+        }
+        if !was_published {
+            let (status, publish_at) = resolve_publish_state(
+                Some(requested),
+                payload.publish_at.or(post.publish_at)
+            )?;
+            post.status = status;
+            post.publish_at = publish_at;
+
+            // a post counts as created when it goes live
+            if post.is_published() {
+                post.created_at = Utc::now();
+            }
+        }
+    }
+
+    // the publish task may have moved it already, that one wins
+    if !was_published && (payload.status.is_some() || payload.publish_at.is_some()) {
+        state.services.post_service.set_publish_state(&post, was_status).await?;
+    }
+
+    // Save changes, keeping the old version as a revision
+    let editor = Editor { id: session.user_uuid, username: post.author.clone() };
+    state.services.post_service.save_edit(&before, &mut post, &editor, None).await?;
+    if !was_published && post.is_published() {
+        state.services.post_service.push_latest(&post).await;
+    }
+    state.services.tag_service
+        .record_usage(TagKind::Post, counted_tags(was_published, &old_tags), counted_tags(post.is_published(), &post.tags)).await;
+
+    // authors always see their own post unblurred
+    let mut author = Viewer::signed_in(session.user_uuid.to_string(), true);
+    state.services.bookmark_service.annotate(&mut author, std::slice::from_ref(&post)).await?;
+    Ok(HttpResponse::Ok().json(PostResponse::for_viewer(&post, &author)))
+}
+
+// large files skip our memory: the client PUTs them straight to storage, then finalizes
+#[post("/{id}/uploads")]
+async fn request_uploads(
+    auther: Auther,
+    path: Path<String>,
+    payload: Json<UploadRequest>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let post = state.services.post_service.get_by_id(&path.into_inner()).await?;
+
+    let uploads = state.services.media_service
+        .presign(&post, &session.user_uuid.to_string(), payload.into_inner().files).await?;
+    Ok(HttpResponse::Ok().json(UploadTicketsResponse { uploads }))
+}
+
+#[post("/{id}/uploads/finalize")]
+async fn finalize_uploads(
+    auther: Auther,
+    path: Path<String>,
+    payload: Json<FinalizeUploadsRequest>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let post = state.services.post_service.get_by_id(&path.into_inner()).await?;
+    if payload.upload_ids.is_empty() {
+        return Err(AppError::BadRequest("No uploads to finalize".into()));
+    }
+
+    let post = state.services.media_service
+        .finalize(&post, &session.user_uuid.to_string(), &payload.upload_ids).await?;
+
+    let mut author = Viewer::signed_in(session.user_uuid.to_string(), true);
+    state.services.bookmark_service.annotate(&mut author, std::slice::from_ref(&post)).await?;
+    Ok(HttpResponse::Ok().json(PostResponse::for_viewer(&post, &author)))
 }
 
 #[get("/id/{id}")]
 async fn get_post_by_id(
+    auther: OptionalAuther,
     path: Path<String>,
     state: Data<AppState>
 ) -> Result<impl Responder, AppError> {
+    let mut viewer = auther.viewer(&state).await;
     let id = path.into_inner();
     let post = state.services.post_service.get_by_id(&id).await?;
-    Ok(HttpResponse::Ok().json(PostResponse::from(&post)))
+    if !post.visible_to(&viewer) {
+        return Err(AppError::PostNotFound);
+    }
+    if post.is_published() {
+        state.services.post_service.add_view(&id).await.ok(); // a missed view is not worth failing over
+    }
+
+    let collections = state.services.collection_service.get_public_containing(&id).await?;
+    state.services.bookmark_service.annotate(&mut viewer, std::slice::from_ref(&post)).await?;
+    Ok(
+        HttpResponse::Ok().json(PostDetailResponse {
+            post: PostResponse::for_viewer(&post, &viewer),
+            collections,
+        })
+    )
 }
 
 #[delete("/delete/{id}")]
@@ -261,78 +415,157 @@ async fn delete_post(
     }
 
     state.services.post_service.delete(&post.id.to_string()).await?;
+    state.services.tag_service.record_usage(TagKind::Post, counted_tags(post.is_published(), &post.tags), &[]).await;
+    state.services.collection_service.remove_post_everywhere(&post.id.to_string()).await?;
+    state.services.bookmark_service.remove_target(BookmarkKind::Post, &post.id.to_string()).await?;
+    state.services.media_service.remove_post(&post.id.to_string()).await?;
+
+    // the post is gone either way, anything left behind is picked up by the orphan sweep
+    if let Err(e) = state.services.media_service.release(&post.media).await {
+        log::warn!("Failed to release media of post {}: {:?}", post.id, e);
+    }
+    if let Err(e) = state.storage.delete_prefix(&format!("postassets/{}/", post.id)).await {
+        log::warn!("Failed to delete assets of post {}: {:?}", post.id, e);
+    }
 
     Ok(HttpResponse::Ok().json(json!({ "success": true })))
 }
 
 #[get("/latest")]
 async fn get_latest_posts(
+    auther: OptionalAuther,
     state: Data<AppState>,
     query: Query<LatestPostParams>
 ) -> Result<impl Responder, AppError> {
+    let mut viewer = auther.viewer(&state).await;
     let amount = query.amount.unwrap_or(50);
-    let displacement = query.displacement.unwrap_or(0);
-    let posts = state.services.post_service.get_latest(amount, displacement).await?;
-    let response: Vec<PostResponse> = posts.iter().map(PostResponse::from).collect();
-    Ok(HttpResponse::Ok().json(response))
+    let page = FeedPage::from_params(query.cursor.as_deref(), query.displacement.unwrap_or(0))?;
+    let posts = state.services.post_service.get_latest(amount, &page, &viewer).await?;
+    state.services.bookmark_service.annotate(&mut viewer, &posts).await?;
+    Ok(PostFeedResponse::new(&posts, FeedSort::Newest, amount, &viewer).respond(&page))
+}
+
+#[get("/drafts")]
+async fn get_drafts(
+    auther: Auther,
+    state: Data<AppState>,
+    query: Query<DraftListParams>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let limit = query.limit.unwrap_or(20).min(100);
+    let page = FeedPage::from_params(query.cursor.as_deref(), 0)?;
+
+    let posts = state.services.post_service.get_drafts(&session.user_uuid.to_string(), limit, &page).await?;
+
+    // authors always see their own posts unblurred
+    let mut author = Viewer::signed_in(session.user_uuid.to_string(), true);
+    state.services.bookmark_service.annotate(&mut author, &posts).await?;
+    Ok(HttpResponse::Ok().json(PostFeedResponse::new(&posts, FeedSort::Newest, limit, &author)))
+}
+
+#[get("/following")]
+async fn get_following_posts(
+    auther: Auther,
+    state: Data<AppState>,
+    query: Query<LatestPostParams>
+) -> Result<impl Responder, AppError> {
+    let session = auther.session;
+    let amount = query.amount.unwrap_or(50).min(100);
+    let page = FeedPage::from_params(query.cursor.as_deref(), query.displacement.unwrap_or(0))?;
+
+    let profile = state.services.profile_service.get_by_uuid(&session.user_uuid).await?;
+    let settings = state.db.settings.get_by_uuid(&session.user_uuid).await?;
+
+    let mut viewer = Viewer::signed_in(session.user_uuid.to_string(), settings.nsfw);
+    let posts = state.services.post_service
+        .get_following(&session.user_uuid.to_string(), profile.following.into_iter().collect(), viewer.show_nsfw, amount, &page)
+        .await?;
+    state.services.bookmark_service.annotate(&mut viewer, &posts).await?;
+
+    Ok(HttpResponse::Ok().json(PostFeedResponse::new(&posts, FeedSort::Newest, amount, &viewer)))
 }
 
 #[get("/popular")]
 async fn get_popular_posts(
+    auther: OptionalAuther,
     state: Data<AppState>,
-    query: Query<LatestPostParams>
+    query: Query<PopularPostParams>
 ) -> Result<impl Responder, AppError> {
+    let mut viewer = auther.viewer(&state).await;
     let amount = query.amount.unwrap_or(50);
-    let displacement = query.displacement.unwrap_or(0);
+    let page = FeedPage::from_params(query.cursor.as_deref(), query.displacement.unwrap_or(0))?;
 
-    let posts = state.services.post_service.get_popular(amount, displacement, None).await?;
+    let posts = state.services.post_service.get_popular(amount, &page, query.window, None, &viewer).await?;
+    state.services.bookmark_service.annotate(&mut viewer, &posts).await?;
 
-    let response: Vec<PostResponse> = posts.iter().map(PostResponse::from).collect();
-    Ok(HttpResponse::Ok().json(response))
+    let sort = match query.window {
+        PopularWindow::Trending => FeedSort::Trending,
+        _ => FeedSort::Top,
+    };
+    Ok(PostFeedResponse::new(&posts, sort, amount, &viewer).respond(&page))
 }
 
 #[get("/premium")]
 async fn get_premium_posts(
+    auther: OptionalAuther,
     state: Data<AppState>,
     query: Query<LatestPostParams>
 ) -> Result<impl Responder, AppError> {
+    let mut viewer = auther.viewer(&state).await;
     let amount = query.amount.unwrap_or(50);
-    let displacement = query.displacement.unwrap_or(0);
+    let page = FeedPage::from_params(query.cursor.as_deref(), query.displacement.unwrap_or(0))?;
 
-    let posts = state.services.post_service.get_premium(amount, displacement, None).await?;
+    let posts = state.services.post_service.get_premium(amount, &page, None, &viewer).await?;
+    state.services.bookmark_service.annotate(&mut viewer, &posts).await?;
 
-    let response: Vec<PostResponse> = posts.iter().map(PostResponse::from).collect();
-    Ok(HttpResponse::Ok().json(response))
+    Ok(PostFeedResponse::new(&posts, FeedSort::Newest, amount, &viewer).respond(&page))
 }
 
 #[get("/random")]
 async fn get_random_posts(
+    auther: OptionalAuther,
     state: Data<AppState>,
     query: Query<LatestPostParams>
 ) -> Result<impl Responder, AppError> {
+    let mut viewer = auther.viewer(&state).await;
     let amount = query.amount.unwrap_or(50);
 
-    let posts = state.services.post_service.get_random(amount, None).await?;
+    let posts = state.services.post_service.get_random(amount, None, &viewer).await?;
+    state.services.bookmark_service.annotate(&mut viewer, &posts).await?;
 
-    let response: Vec<PostResponse> = posts.iter().map(PostResponse::from).collect();
+    let response: Vec<PostResponse> = posts
+        .iter()
+        .map(|post| PostResponse::for_viewer(post, &viewer))
+        .collect();
     Ok(HttpResponse::Ok().json(response))
 }
 
 #[get("/by/{username}/{short_id}")]
 async fn get_post(
+    auther: OptionalAuther,
     path: Path<(String, String)>,
     state: Data<AppState>
 ) -> Result<impl Responder, AppError> {
+    let mut viewer = auther.viewer(&state).await;
     let (username, short_id) = path.into_inner();
     let post = state.services.post_service.find_by_author_and_short_id(&username, &short_id).await?;
-    Ok(HttpResponse::Ok().json(PostResponse::from(&post)))
+    if !post.visible_to(&viewer) {
+        return Err(AppError::PostNotFound);
+    }
+    if post.is_published() {
+        state.services.post_service.add_view(&post.id.to_string()).await.ok();
+    }
+    state.services.bookmark_service.annotate(&mut viewer, std::slice::from_ref(&post)).await?;
+    Ok(HttpResponse::Ok().json(PostResponse::for_viewer(&post, &viewer)))
 }
 
 #[get("/random")]
-async fn get_a_random_post(state: Data<AppState>) -> Result<impl Responder, AppError> {
-    let posts = state.services.post_service.get_random(1, None).await?;
+async fn get_a_random_post(auther: OptionalAuther, state: Data<AppState>) -> Result<impl Responder, AppError> {
+    let mut viewer = auther.viewer(&state).await;
+    let posts = state.services.post_service.get_random(1, None, &viewer).await?;
+    state.services.bookmark_service.annotate(&mut viewer, &posts).await?;
     if let Some(post) = posts.first() {
-        Ok(HttpResponse::Ok().json(PostResponse::from(post)))
+        Ok(HttpResponse::Ok().json(PostResponse::for_viewer(post, &viewer)))
     } else {
         Err(AppError::PostNotFound)
     }
@@ -344,20 +577,21 @@ async fn like_post(
     path: Path<String>,
     state: Data<AppState>
 ) -> Result<impl Responder, AppError> {
-    let session = auther.session;
-    let user_id = session.user_uuid.to_string();
-    let mut post = state.services.post_service.get_by_id(&path.into_inner()).await?;
-
-    // like toggle logic
-    let liked = post.likes.insert(user_id.clone());
+    let viewer = auther.viewer(&state).await;
+    let user_id = auther.session.user_uuid.to_string();
+    let post_id = path.into_inner();
+    state.services.post_service.get_visible(&post_id, &viewer).await?;
+    let (liked, post) = state.services.post_service
+        .react(&post_id, &user_id, Reaction::Like).await?;
     if liked {
-        post.dislikes.remove(&user_id); // remove old dislike
-    } else {
-        post.likes.remove(&user_id); // undo like
+        state.services.notification_service.post_liked(&post, &user_id).await;
     }
 
-    state.services.post_service.save(&post).await?;
-    Ok(HttpResponse::Ok().json(json!({ "liked": liked })))
+    Ok(
+        HttpResponse::Ok().json(
+            json!({ "liked": liked, "like_count": post.like_count, "dislike_count": post.dislike_count })
+        )
+    )
 }
 
 #[post("/{id}/dislike")]
@@ -365,19 +599,120 @@ async fn dislike_post(
     auther: Auther,
     path: Path<String>,
     state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let viewer = auther.viewer(&state).await;
+    let user_id = auther.session.user_uuid.to_string();
+    let post_id = path.into_inner();
+    state.services.post_service.get_visible(&post_id, &viewer).await?;
+    let (disliked, post) = state.services.post_service
+        .react(&post_id, &user_id, Reaction::Dislike).await?;
+
+    Ok(
+        HttpResponse::Ok().json(
+            json!({ "disliked": disliked, "like_count": post.like_count, "dislike_count": post.dislike_count })
+        )
+    )
+}
+
+#[get("/{id}/revisions")]
+async fn get_post_revisions(
+    auther: OptionalAuther,
+    path: Path<String>,
+    query: Query<RevisionListParams>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let viewer = auther.viewer(&state).await;
+    let id = path.into_inner();
+    let post = state.services.post_service.get_by_id(&id).await?;
+    if !post.visible_to(&viewer) {
+        return Err(AppError::PostNotFound);
+    }
+
+    let limit = query.limit.unwrap_or(20).min(100);
+    let page = query.page.unwrap_or(1).max(1);
+    let revisions = state.services.post_service.get_revisions(&id, (page - 1).saturating_mul(limit), limit).await?;
+
+    Ok(
+        HttpResponse::Ok().json(
+            json!({
+        "page": page,
+        "limit": limit,
+        "revisions": revisions,
+        "has_more": revisions.len() as u64 == limit,
+    })
+        )
+    )
+}
+
+// puts the post back to how it was before the given revision, author or moderator only
+#[post("/{id}/revisions/{revision_id}/restore")]
+async fn restore_post_revision(
+    auther: Auther,
+    path: Path<(String, String)>,
+    state: Data<AppState>
 ) -> Result<impl Responder, AppError> {
     let session = auther.session;
-    let user_id = session.user_uuid.to_string();
-    let mut post = state.services.post_service.get_by_id(&path.into_inner()).await?;
+    let (id, revision_id) = path.into_inner();
+    let post = state.services.post_service.get_by_id(&id).await?;
+    let profile = state.services.profile_service.get_by_uuid(&session.user_uuid).await?;
 
-    // dislike toggle logic
-    let disliked = post.dislikes.insert(user_id.clone());
-    if disliked {
-        post.likes.remove(&user_id); // remove old like
-    } else {
-        post.dislikes.remove(&user_id); // undo dislike
+    if post.author_id != session.user_uuid && !profile.role.iter().any(|r| r.can_edit_others()) {
+        return Err(AppError::Unauthorized("You are not allowed to edit this post".into()));
     }
 
-    state.services.post_service.save(&post).await?;
-    Ok(HttpResponse::Ok().json(json!({ "disliked": disliked })))
+    let editor = Editor { id: session.user_uuid, username: profile.username.clone() };
+    let (post, old_tags) = state.services.post_service.restore_revision(&id, &revision_id, &editor).await?;
+    let published = post.is_published();
+    state.services.tag_service
+        .record_usage(TagKind::Post, counted_tags(published, &old_tags), counted_tags(published, &post.tags)).await;
+
+    let mut viewer = Viewer::signed_in(session.user_uuid.to_string(), true);
+    state.services.bookmark_service.annotate(&mut viewer, std::slice::from_ref(&post)).await?;
+    Ok(HttpResponse::Ok().json(PostResponse::for_viewer(&post, &viewer)))
+}
+
+#[get("/{id}/likes")]
+async fn get_post_likes(
+    auther: OptionalAuther,
+    path: Path<String>,
+    query: Query<ReactorListParams>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let viewer = auther.viewer(&state).await;
+    let limit = query.limit.unwrap_or(50).min(100);
+    let page = query.page.unwrap_or(1).max(1);
+    let reaction = query.reaction.unwrap_or(Reaction::Like);
+
+    let post_id = path.into_inner();
+    let post = state.services.post_service.get_visible(&post_id, &viewer).await?;
+    // who disliked a post is only for its author to see
+    if reaction == Reaction::Dislike && viewer.user_id() != Some(post.author_id.to_string().as_str()) {
+        return Err(AppError::Unauthorized("Only the author can see who disliked this post".into()));
+    }
+    let ids = state.services.post_service
+        .get_reactors(&post_id, reaction, (page - 1).saturating_mul(limit), limit).await?;
+
+    // get_many does not keep order, put the profiles back in reaction order
+    let profiles = state.services.profile_service.get_many(vec![], ids.clone()).await?;
+    let users: Vec<ProfileSummary> = ids
+        .iter()
+        .filter_map(|id| profiles.iter().find(|p| p.id.to_string() == *id))
+        .map(ProfileSummary::from)
+        .collect();
+
+    Ok(
+        HttpResponse::Ok().json(
+            json!({
+        "page": page,
+        "limit": limit,
+        "users": users,
+        "has_more": ids.len() as u64 == limit,
+    })
+        )
+    )
+}
+
+// only published posts count towards tag usage
+fn counted_tags(published: bool, tags: &[String]) -> &[String] {
+    if published { tags } else { &[] }
 }
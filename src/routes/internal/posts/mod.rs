@@ -5,19 +5,26 @@ use crate::routes::internal::posts::handler::{
     delete_post,
     dislike_post,
     edit_post,
+    finalize_uploads,
+    get_drafts,
     get_a_random_post,
+    get_following_posts,
     get_latest_posts,
     get_popular_posts,
     get_post,
+    get_post_likes,
+    get_post_revisions,
     get_post_by_id,
     get_premium_posts,
     get_random_posts,
     like_post,
+    request_uploads,
+    restore_post_revision,
     search_posts,
 };
 
 pub mod handler;
-mod upload;
+pub mod upload;
 pub mod types;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
@@ -28,10 +35,17 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .service(search_posts)
             .service(edit_post)
             .service(create_post)
+            .service(request_uploads)
+            .service(finalize_uploads)
             .service(get_latest_posts)
+            .service(get_following_posts)
+            .service(get_drafts)
             .service(get_post)
             .service(like_post)
             .service(dislike_post)
+            .service(get_post_likes)
+            .service(get_post_revisions)
+            .service(restore_post_revision)
             .service(delete_post)
             .service(get_post_by_id)
             .service(get_popular_posts)
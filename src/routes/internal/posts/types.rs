@@ -1,30 +1,71 @@
+use chrono::{ DateTime, Utc };
 use serde::Deserialize;
 use utoipa::{ ToSchema, IntoParams };
 
+use crate::models::reaction::Reaction;
+use crate::models::upload::NewUpload;
+use crate::utils::post::PostStatus;
+use crate::utils::trending::PopularWindow;
+
 // params for grabbing latest posts
 #[derive(Debug, Deserialize)]
 pub struct LatestPostParams {
     pub amount: Option<u64>, // how many posts to grab
-    pub displacement: Option<u64>, // basically "skip this many posts"
+    pub displacement: Option<u64>, // basically "skip this many posts" (deprecated, use cursor)
+    pub cursor: Option<String>, // next_cursor from the previous page
+}
+
+// params for the popular feed, same as latest plus the ranking window
+#[derive(Debug, Deserialize)]
+pub struct PopularPostParams {
+    pub amount: Option<u64>,
+    pub displacement: Option<u64>, // deprecated, use cursor
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub window: PopularWindow, // trending (default), day, week, month or all
+}
+
+// params for listing the signed in user's drafts and scheduled posts
+#[derive(Debug, Deserialize)]
+pub struct DraftListParams {
+    pub limit: Option<u64>,
+    pub cursor: Option<String>, // next_cursor from the previous page
+}
+
+// params for listing a post's edit history
+#[derive(Debug, Deserialize)]
+pub struct RevisionListParams {
+    pub page: Option<u64>, // starting at 1
+    pub limit: Option<u64>,
+}
+
+// params for listing who reacted to a post
+#[derive(Debug, Deserialize)]
+pub struct ReactorListParams {
+    pub reaction: Option<Reaction>, // like (default) or dislike, dislikes only for the author
+    pub page: Option<u64>, // starting at 1
+    pub limit: Option<u64>,
 }
 
 // params if we wanna query posts with more options
 #[derive(Debug, Deserialize, IntoParams)]
 pub struct QueryPostsParams {
     pub limit: Option<u64>, // max posts per request
-    pub page: Option<String>, // what page we're on (could’ve been u32 but meh)
+    pub page: Option<String>, // what page we're on, starting at 1 (deprecated, use cursor)
     pub tags: Option<String>, // filter posts by tag(s)
+    pub cursor: Option<String>, // next_cursor from the previous page
 }
 
 // params for searching posts in a more detailed way
 #[derive(Debug, Deserialize)]
 pub struct PostSearchQuery {
-    pub query: Option<String>, // text search (keywords n stuff)
+    pub query: Option<String>, // text search, see utils::search::ParsedQuery for the syntax
     pub tags: Option<String>, // filter by tags again
     pub author: Option<String>, // filter by author name/id
-    pub page: Option<u32>, // which page of results
+    pub page: Option<u32>, // which page of results, starting at 1 (deprecated, use cursor)
     pub limit: Option<u32>, // how many per page
-    pub sort: Option<String>, // sorting method (newest, oldest, etc.)
+    pub sort: Option<String>, // relevance, createdAt_desc, createdAt_asc or likes_desc
+    pub cursor: Option<String>, // next_cursor from the previous page
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -33,4 +74,18 @@ pub struct UserPatchPost {
     pub body: Option<Option<String>>, // some(None) = clear body, None = leave unchanged
     pub tags: Option<Vec<String>>,
     pub nsfw: Option<bool>,
+    pub characters: Option<Vec<String>>, // ids of featured characters, replaces the current list
+    pub status: Option<PostStatus>, // only drafts and scheduled posts can change status
+    pub publish_at: Option<DateTime<Utc>>, // required when scheduling, implies `scheduled` on its own
+}
+
+// files to get presigned upload URLs for, see MediaService::presign
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UploadRequest {
+    pub files: Vec<NewUpload>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FinalizeUploadsRequest {
+    pub upload_ids: Vec<String>, // attached in this order
 }
@@ -5,11 +5,17 @@ use mime_guess::from_path;
 use std::collections::HashMap;
 
 use crate::utils::error::AppError;
-use crate::services::r2::{ALLOWED_IMAGE_TYPES, ALLOWED_VIDEO_TYPES, MAX_IMAGE_SIZE_BYTES, MAX_VIDEO_SIZE_BYTES};
+use crate::utils::sniff::{ is_allowed_type, validate_upload };
+use crate::services::storage::{
+    ALLOWED_VIDEO_TYPES,
+    MAX_FILES_PER_REQUEST,
+    MAX_IMAGE_SIZE_BYTES,
+    MAX_REQUEST_SIZE_BYTES,
+    MAX_VIDEO_SIZE_BYTES,
+};
 
 // lil struct to hold file info when someone uploads something
 pub struct UploadedFile {
-    pub filename: String,       // name of the file
     pub content_type: String,   // like "image/png" or "text/plain"
     pub data: BytesMut,         // the actual file data in memory
 }
@@ -19,6 +25,7 @@ pub async fn parse_multipart(
 ) -> Result<(HashMap<String, String>, Vec<UploadedFile>), AppError> {
     let mut fields = HashMap::new();
     let mut media_files = Vec::new();
+    let mut total_bytes = 0;
 
     while let Some(item) = payload.next().await {
         let mut field = item.map_err(|_| AppError::BadRequest("Invalid multipart".into()))?;
@@ -27,36 +34,45 @@ pub async fn parse_multipart(
         match cd.as_ref().and_then(|cd| cd.get_name()) {
             Some("media") => {
                 if let Some(filename) = cd.and_then(|cd| cd.get_filename().map(str::to_owned)) {
-                    let content_type = field
+                    if media_files.len() >= MAX_FILES_PER_REQUEST {
+                        return Err(AppError::BadRequest(format!(
+                            "At most {} files can be uploaded at once",
+                            MAX_FILES_PER_REQUEST
+                        )));
+                    }
+
+                    // only a hint, the bytes get the final say below
+                    let declared_type = field
                         .content_type()
-                        .map(|ct| ct.to_string())
+                        .map(|ct| ct.essence_str().to_string())
                         .unwrap_or_else(|| from_path(&filename).first_or_octet_stream().to_string());
 
-                    let is_image = ALLOWED_IMAGE_TYPES.contains(&content_type.as_str());
-                    let is_video = ALLOWED_VIDEO_TYPES.contains(&content_type.as_str());
-
-                    if !is_image && !is_video {
+                    if !is_allowed_type(&declared_type) {
                         return Err(AppError::BadRequest(format!(
                             "Unsupported file type: {}",
-                            content_type
+                            declared_type
                         )));
                     }
+                    let is_video = ALLOWED_VIDEO_TYPES.contains(&declared_type.as_str());
+                    let limit = if is_video { MAX_VIDEO_SIZE_BYTES } else { MAX_IMAGE_SIZE_BYTES };
 
                     let mut file_data = BytesMut::new();
                     while let Some(chunk) = field.next().await {
                         let data = chunk.map_err(|_| AppError::BadRequest("Read chunk failure".into()))?;
 
-                        let limit = if is_video { MAX_VIDEO_SIZE_BYTES } else { MAX_IMAGE_SIZE_BYTES };
-
                         if file_data.len() + data.len() > limit {
-                            return Err(AppError::FileToBig(limit.to_string()));
+                            return Err(AppError::FileToBig((limit / 1024 / 1024).to_string()));
                         }
+                        total_bytes = add_to_request_size(total_bytes, data.len())?;
                         file_data.extend_from_slice(&data);
                     }
 
+                    // the client's filename never reaches storage, files are stored by hash
+                    let (content_type, len) = validate_upload(&declared_type, &file_data)?;
+                    file_data.truncate(len);
+
                     media_files.push(UploadedFile {
-                        filename,
-                        content_type,
+                        content_type: content_type.to_string(),
                         data: file_data,
                     });
                 }
@@ -65,6 +81,7 @@ pub async fn parse_multipart(
                 let mut value = Vec::new();
                 while let Some(chunk) = field.next().await {
                     let data = chunk.map_err(|_| AppError::BadRequest("Read form field failed".into()))?;
+                    total_bytes = add_to_request_size(total_bytes, data.len())?;
                     value.extend_from_slice(&data);
                 }
                 fields.insert(name.to_string(), String::from_utf8(value).unwrap_or_default());
@@ -75,3 +92,11 @@ pub async fn parse_multipart(
 
     Ok((fields, media_files))
 }
+
+fn add_to_request_size(total: usize, more: usize) -> Result<usize, AppError> {
+    let total = total + more;
+    if total > MAX_REQUEST_SIZE_BYTES {
+        return Err(AppError::FileToBig((MAX_REQUEST_SIZE_BYTES / 1024 / 1024).to_string()));
+    }
+    Ok(total)
+}
@@ -162,6 +162,7 @@ pub async fn follow_profile(
         user_profile.following.remove(&to_follow_profile.id.to_string());
         state.services.profile_service.save(&to_follow_profile.id, &to_follow_profile).await?;
         state.services.profile_service.save(&user_profile.id, &user_profile).await?;
+        state.services.post_service.invalidate_following(&user_id.to_string()).await.ok();
         Ok(HttpResponse::Ok().json(serde_json::json!({ "followed": false })))
     } else {
         // Follow: add to both sets
@@ -169,6 +170,9 @@ pub async fn follow_profile(
         user_profile.following.insert(to_follow_profile.id.to_string());
         state.services.profile_service.save(&to_follow_profile.id, &to_follow_profile).await?;
         state.services.profile_service.save(&user_profile.id, &user_profile).await?;
+        state.services.post_service.invalidate_following(&user_id.to_string()).await.ok();
+        state.services.notification_service
+            .followed(&to_follow_profile.id.to_string(), &user_id.to_string()).await;
         Ok(HttpResponse::Ok().json(serde_json::json!({ "followed": true })))
     }
 }
@@ -0,0 +1,21 @@
+use crate::routes::internal::tags::types::TagAutocompleteQuery;
+use crate::state::AppState;
+use crate::utils::error::AppError;
+use actix_web::web::{ Data, Path, Query };
+use actix_web::{ get, HttpResponse, Responder };
+
+#[get("/autocomplete")]
+pub async fn autocomplete_tags(
+    query: Query<TagAutocompleteQuery>,
+    state: Data<AppState>
+) -> Result<impl Responder, AppError> {
+    let limit = query.limit.unwrap_or(10).min(50);
+    let suggestions = state.services.tag_service.autocomplete(&query.q, limit).await?;
+    Ok(HttpResponse::Ok().json(suggestions))
+}
+
+#[get("/{name}")]
+pub async fn get_tag(path: Path<String>, state: Data<AppState>) -> Result<impl Responder, AppError> {
+    let page = state.services.tag_service.get_page(&path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(page))
+}
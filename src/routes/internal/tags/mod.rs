@@ -0,0 +1,11 @@
+use actix_web::web;
+use log::info;
+use crate::routes::internal::tags::handler::{ autocomplete_tags, get_tag };
+
+pub mod handler;
+pub mod types;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    info!("Configuring /api/tags scope");
+    cfg.service(web::scope("tags").service(autocomplete_tags).service(get_tag));
+}
@@ -0,0 +1,8 @@
+use serde::Deserialize;
+
+// params for tag autocomplete
+#[derive(Debug, Deserialize)]
+pub struct TagAutocompleteQuery {
+    pub q: String, // what the user typed so far
+    pub limit: Option<u64>,
+}
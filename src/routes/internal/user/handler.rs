@@ -8,9 +8,9 @@ use bson::doc;
 use chrono::Utc;
 use serde_json::json;
 use crate::auth::password::hash_password;
-use crate::middleware::auther::Auther;
+use crate::middleware::auther::{Auther, OptionalAuther};
 use crate::models::codes::{Code, CodeType};
-use crate::models::post::PostResponse;
+use crate::models::post::PostFeedResponse;
 use crate::models::profile::DBProfile;
 use crate::models::settings::UserSettings;
 use crate::models::user::User;
@@ -18,44 +18,46 @@ use crate::routes::internal::posts::types::QueryPostsParams;
 use crate::routes::internal::session::{create_session, AuthResponse};
 use crate::routes::internal::user::types::{PasswordResetConfirm, PasswordResetRequest};
 use crate::state::AppState;
+use crate::utils::cursor::{FeedPage, FeedSort};
 use crate::utils::error::AppError;
 use crate::utils::error::AppError::Unauthorized;
+use crate::utils::tags::parse_tag_list;
 use crate::utils::json::json_merge;
 
 #[get("/{username}/posts")]
 pub async fn get_user_posts(
+    auther: OptionalAuther,
     state: Data<AppState>,
     path: Path<String>,
     query: Query<QueryPostsParams>,
 ) -> impl Responder {
+    let mut viewer = auther.viewer(&state).await;
     let username = path.into_inner();
 
     let limit = query.limit.unwrap_or(20);
+    // pages start at 0 here, unlike the other feeds, older clients rely on it
     let page = query
         .page
         .as_ref()
         .and_then(|p| p.parse::<u64>().ok())
         .unwrap_or(0);
-    let skip = page * limit;
-
-    let tags: Option<Vec<String>> = query.tags.as_ref().map(|tags_str| {
-        tags_str
-            .split(',')
-            .map(|s| s.trim().to_lowercase())
-            .filter(|s| !s.is_empty())
-            .collect()
-    });
-
-    let result = state.db.posts
-        .get_all_by_user(&*username, limit, skip, tags)
-        .await
-        .map(|posts| {
-            let responses: Vec<_> = posts.iter().map(PostResponse::from).collect();
-            HttpResponse::Ok().json(responses)
-        })
-        .unwrap_or_else(|e| e.error_response());
-
-    result
+    let skip = page.saturating_mul(limit);
+    let feed_page = match FeedPage::from_params(query.cursor.as_deref(), skip) {
+        Ok(feed_page) => feed_page,
+        Err(e) => return e.error_response(),
+    };
+
+    let tags: Option<Vec<String>> = query.tags.as_deref().map(parse_tag_list);
+
+    let posts = match state.services.post_service.get_all_by_user(&username, limit, &feed_page, tags, &viewer).await {
+        Ok(posts) => posts,
+        Err(e) => return e.error_response(),
+    };
+    if let Err(e) = state.services.bookmark_service.annotate(&mut viewer, &posts).await {
+        return e.error_response();
+    }
+
+    PostFeedResponse::new(&posts, FeedSort::Newest, limit, &viewer).respond(&feed_page)
 }
 
 /**
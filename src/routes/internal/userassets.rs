@@ -7,6 +7,9 @@ use utoipa::ToSchema;
 
 use crate::{ state::AppState, utils::{ error::AppError } };
 use crate::middleware::auther::Auther;
+use crate::services::storage::{ ALLOWED_IMAGE_TYPES, MAX_IMAGE_SIZE_BYTES };
+use crate::utils::hash::hash_bytes_sha256;
+use crate::utils::sniff::validate_untyped_upload;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     info!("Configuring /api/media scope");
@@ -31,19 +34,23 @@ pub async fn upload_profile_picture(
     let user_id = session.user_uuid;
     let user = state.db.users.get_by_uuid(&user_id).await?;
 
-    let file_data = get_payload_byes(payload).await?;
+    let mut file_data = get_payload_byes(payload).await?;
 
     // if no data was sent
     if file_data.is_empty() {
         return Err(AppError::BadRequest("No file data provided".into()));
     }
 
-    // get content type from file data
-    let uploaded_url = state.r2
+    // the type comes from the bytes themselves, whatever the client claims
+    let (content_type, len) = validate_untyped_upload(&file_data, ALLOWED_IMAGE_TYPES)?;
+    file_data.truncate(len);
+    state.services.media_service.ensure_not_banned(&hash_bytes_sha256(&file_data)).await?;
+    let uploaded_url = state.storage
         .upload_user_asset(
-            &user.username,
-            crate::services::r2::UserAssetType::ProfilePicture,
-            &*file_data.to_vec()
+            &user_id.to_string(),
+            crate::services::storage::UserAssetType::ProfilePicture,
+            &file_data,
+            content_type
         ).await
         .map_err(|e| {
             eprintln!("Error uploading to R2: {:?}", e);
@@ -51,8 +58,9 @@ pub async fn upload_profile_picture(
         })?;
 
     let mut profile = state.db.profiles.get_by_username(&user.username).await?;
-    profile.profile_picture = Some(uploaded_url.clone());
+    let previous = profile.profile_picture.replace(uploaded_url.clone());
     state.services.profile_service.save(&user_id, &profile).await?;
+    delete_replaced_asset(&state, previous.as_deref(), &uploaded_url).await;
 
     Ok(
         HttpResponse::Ok().json(UpdateUserAssetResponse {
@@ -74,19 +82,23 @@ pub async fn upload_banner(
     let user_id = session.user_uuid;
     let user = state.db.users.get_by_uuid(&user_id).await?;
 
-    let file_data = get_payload_byes(payload).await?;
+    let mut file_data = get_payload_byes(payload).await?;
 
     // if no data was sent
     if file_data.is_empty() {
         return Err(AppError::BadRequest("No file data provided".into()));
     }
 
-    // get content type from file data
-    let uploaded_url = state.r2
+    // the type comes from the bytes themselves, whatever the client claims
+    let (content_type, len) = validate_untyped_upload(&file_data, ALLOWED_IMAGE_TYPES)?;
+    file_data.truncate(len);
+    state.services.media_service.ensure_not_banned(&hash_bytes_sha256(&file_data)).await?;
+    let uploaded_url = state.storage
         .upload_user_asset(
-            &user.username,
-            crate::services::r2::UserAssetType::Banner,
-            &*file_data.to_vec()
+            &user_id.to_string(),
+            crate::services::storage::UserAssetType::Banner,
+            &file_data,
+            content_type
         ).await
         .map_err(|e| {
             eprintln!("Error uploading to R2: {:?}", e);
@@ -94,8 +106,9 @@ pub async fn upload_banner(
         })?;
 
     let mut profile = state.db.profiles.get_by_username(&user.username).await?;
-    profile.banner_picture = Some(uploaded_url.clone());
+    let previous = profile.banner_picture.replace(uploaded_url.clone());
     state.services.profile_service.save(&user_id, &profile).await?;
+    delete_replaced_asset(&state, previous.as_deref(), &uploaded_url).await;
 
     Ok(
         HttpResponse::Ok().json(UpdateUserAssetResponse {
@@ -104,16 +117,40 @@ pub async fn upload_banner(
     )
 }
 
+// the same image hashes to the same key, so only a different url leaves something behind
+async fn delete_replaced_asset(state: &AppState, previous: Option<&str>, current: &str) {
+    let Some(previous) = previous.filter(|url| *url != current) else {
+        return;
+    };
+    let Some(key) = state.storage.key_from_url(previous).filter(|key| key.starts_with("userassets/")) else {
+        return;
+    };
+
+    if let Err(e) = state.storage.delete(&key).await {
+        log::warn!("Failed to delete replaced asset {}: {:?}", key, e);
+    }
+}
+
 async fn get_payload_byes(mut payload: Multipart) -> Result<BytesMut, AppError> {
     let mut file_data = BytesMut::new();
+    let mut files = 0;
     while let Some(field) = payload.next().await {
         let mut field = field.map_err(|e| {
             eprint!("Error processing multipart: {:?}", e);
             AppError::BadRequest("Invalid multipart data".into())
         })?;
 
+        // a profile picture or banner is exactly one file
+        files += 1;
+        if files > 1 {
+            return Err(AppError::BadRequest("Only one file can be uploaded".into()));
+        }
+
         while let Some(chunk) = field.next().await {
             let data = chunk.map_err(|_| AppError::BadRequest("Failed to read chunk".into()))?;
+            if file_data.len() + data.len() > MAX_IMAGE_SIZE_BYTES {
+                return Err(AppError::FileToBig((MAX_IMAGE_SIZE_BYTES / 1024 / 1024).to_string()));
+            }
             file_data.extend_from_slice(&data);
         }
     }
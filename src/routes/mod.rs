@@ -1,6 +1,7 @@
 pub mod api_test;
 pub mod internal;
 pub mod admin;
+pub mod files;
 
 use actix_web::web;
 use log::info;
@@ -16,4 +17,7 @@ pub fn config(cfg: &mut web::ServiceConfig) {
 
     // Optional: still include this if it's a separate root route
     cfg.service(api_test::api_test);
+
+    // files kept by the local storage backend
+    cfg.configure(files::config);
 }
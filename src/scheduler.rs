@@ -5,6 +5,10 @@ use tokio::task::JoinHandle;
 use tokio::time::interval;
 use crate::state::AppState;
 use crate::task::cleanup::CleanupTask;
+use crate::task::orphans::OrphanSweepTask;
+use crate::task::publish::PublishTask;
+use crate::task::trending::TrendingTask;
+use crate::task::uploads::PendingUploadExpiryTask;
 use crate::task::ScheduledTask;
 
 pub struct Scheduler {
@@ -21,7 +25,11 @@ impl Scheduler {
     }
     
     pub fn start_all(&mut self) {
-        self.spawn_task(CleanupTask)
+        self.spawn_task(CleanupTask);
+        self.spawn_task(TrendingTask);
+        self.spawn_task(PublishTask);
+        self.spawn_task(OrphanSweepTask);
+        self.spawn_task(PendingUploadExpiryTask);
     }
 
 
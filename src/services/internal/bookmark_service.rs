@@ -0,0 +1,179 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::database::repos::bookmark_repo::BookmarkRepository;
+use crate::models::bookmark::{ Bookmark, BookmarkFolder, BookmarkKind, BookmarkResponse };
+use crate::models::character::CharacterResponse;
+use crate::models::post::{ Post, PostResponse };
+use crate::models::viewer::Viewer;
+use crate::services::internal::character_service::CharacterService;
+use crate::services::internal::post_service::PostService;
+use crate::utils::cursor::FeedCursor;
+use crate::utils::error::AppError;
+
+pub const MAX_BOOKMARKS_PER_USER: u64 = 10_000;
+const MAX_FOLDER_LEN: usize = 50;
+
+#[derive(Clone)]
+pub struct BookmarkService {
+    repo: BookmarkRepository,
+    posts: PostService,
+    characters: CharacterService,
+}
+
+impl BookmarkService {
+    pub fn new(repo: BookmarkRepository, posts: PostService, characters: CharacterService) -> Self {
+        Self { repo, posts, characters }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        self.repo.ensure_indexes().await
+    }
+
+    /// Bookmarks a post or character, or moves an existing bookmark to `folder`
+    pub async fn add(
+        &self,
+        viewer: &Viewer,
+        kind: BookmarkKind,
+        target_id: &str,
+        folder: Option<String>
+    ) -> Result<Bookmark, AppError> {
+        let user_id = viewer.user_id().ok_or(AppError::Unauthorized("Sign in to bookmark".into()))?;
+        let folder = normalize_folder(folder)?;
+
+        if let Some(mut existing) = self.repo.get(user_id, kind, target_id).await? {
+            self.repo.set_folder(&existing.id.to_string(), folder.as_deref()).await?;
+            existing.folder = folder;
+            return Ok(existing);
+        }
+
+        match kind {
+            BookmarkKind::Post => {
+                let post = self.posts.get_by_id(target_id).await?;
+                if !post.visible_to(viewer) {
+                    return Err(AppError::PostNotFound);
+                }
+            }
+            BookmarkKind::Character => {
+                self.characters.get_by_id(target_id).await?;
+            }
+        }
+
+        if self.repo.count_by_user(user_id).await? >= MAX_BOOKMARKS_PER_USER {
+            return Err(AppError::BadRequest(format!("You can have at most {} bookmarks", MAX_BOOKMARKS_PER_USER)));
+        }
+
+        let bookmark = Bookmark {
+            id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            kind,
+            target_id: target_id.to_string(),
+            folder,
+            created_at: Utc::now(),
+        };
+        self.repo.create(&bookmark).await?;
+        Ok(bookmark)
+    }
+
+    pub async fn remove(&self, user_id: &str, kind: BookmarkKind, target_id: &str) -> Result<(), AppError> {
+        if !self.repo.delete(user_id, kind, target_id).await? {
+            return Err(AppError::BookmarkNotFound);
+        }
+        Ok(())
+    }
+
+    /// Called when a post or character is deleted
+    pub async fn remove_target(&self, kind: BookmarkKind, target_id: &str) -> Result<(), AppError> {
+        self.repo.delete_by_target(kind, target_id).await
+    }
+
+    /// One page of the viewer's bookmarks with what they point at, plus the next cursor
+    pub async fn list(
+        &self,
+        viewer: &Viewer,
+        kind: Option<BookmarkKind>,
+        folder: Option<Option<String>>,
+        after: Option<&FeedCursor>,
+        limit: u64
+    ) -> Result<(Vec<BookmarkResponse>, Option<String>), AppError> {
+        let user_id = viewer.user_id().ok_or(AppError::Unauthorized("Sign in to see bookmarks".into()))?;
+        let bookmarks = self.repo.list(user_id, kind, folder, after, limit).await?;
+
+        let post_ids: Vec<String> = bookmarks
+            .iter()
+            .filter(|b| b.kind == BookmarkKind::Post)
+            .map(|b| b.target_id.clone())
+            .collect();
+        let posts = self.posts.get_many(&post_ids).await?;
+
+        // everything listed here is bookmarked by definition
+        let mut viewer = viewer.clone();
+        viewer.bookmarked_posts.extend(post_ids);
+
+        let mut responses = Vec::with_capacity(bookmarks.len());
+        for bookmark in &bookmarks {
+            let (post, character) = match bookmark.kind {
+                BookmarkKind::Post => {
+                    let post = posts
+                        .iter()
+                        .find(|p| p.id.to_string() == bookmark.target_id)
+                        .filter(|p| p.visible_to(&viewer))
+                        .map(|p| PostResponse::for_viewer(p, &viewer));
+                    (post, None)
+                }
+                BookmarkKind::Character => {
+                    let character = self.characters
+                        .get_by_id(&bookmark.target_id).await
+                        .ok()
+                        .map(|c| CharacterResponse::for_viewer(&c, viewer.user_id()));
+                    (None, character)
+                }
+            };
+
+            responses.push(BookmarkResponse {
+                id: bookmark.id,
+                kind: bookmark.kind,
+                target_id: bookmark.target_id.clone(),
+                folder: bookmark.folder.clone(),
+                created_at: bookmark.created_at,
+                post,
+                character,
+            });
+        }
+
+        let next_cursor = match bookmarks.last() {
+            Some(last) if (bookmarks.len() as u64) >= limit =>
+                Some(FeedCursor::Time { k: last.created_at.timestamp_millis(), id: last.id.to_string() }.encode()),
+            _ => None,
+        };
+
+        Ok((responses, next_cursor))
+    }
+
+    pub async fn folders(&self, user_id: &str) -> Result<Vec<BookmarkFolder>, AppError> {
+        self.repo.folders(user_id).await
+    }
+
+    /// Marks which of `posts` the viewer bookmarked so responses can carry the flag
+    pub async fn annotate(&self, viewer: &mut Viewer, posts: &[Post]) -> Result<(), AppError> {
+        let Some(user_id) = viewer.user_id.clone() else {
+            return Ok(());
+        };
+
+        let ids: Vec<String> = posts
+            .iter()
+            .map(|p| p.id.to_string())
+            .collect();
+        let bookmarked = self.repo.bookmarked_among(&user_id, BookmarkKind::Post, &ids).await?;
+        viewer.bookmarked_posts.extend(bookmarked);
+        Ok(())
+    }
+}
+
+fn normalize_folder(folder: Option<String>) -> Result<Option<String>, AppError> {
+    let folder = folder.map(|f| f.trim().to_string()).filter(|f| !f.is_empty());
+    if folder.as_ref().is_some_and(|f| f.chars().count() > MAX_FOLDER_LEN) {
+        return Err(AppError::BadRequest(format!("Folder names can be at most {} characters", MAX_FOLDER_LEN)));
+    }
+    Ok(folder)
+}
@@ -0,0 +1,91 @@
+use crate::database::repos::character_repo::CharacterRepository;
+use crate::models::character::Character;
+use crate::models::reaction::Reaction;
+use crate::redis::cache::character_cache::CharacterCache;
+use crate::utils::error::AppError;
+
+#[derive(Clone)]
+pub struct CharacterService {
+    repo: CharacterRepository,
+    cache: CharacterCache,
+}
+
+impl CharacterService {
+    pub fn new(repo: CharacterRepository, cache: CharacterCache) -> Self {
+        Self { repo, cache }
+    }
+
+    /// Gets a character by ID, checking Redis cache first.
+    pub async fn get_by_id(&self, id: &str) -> Result<Character, AppError> {
+        if let Some(cached) = self.cache.get(id).await? {
+            log::debug!("Cache hit for character {id}");
+            return Ok(cached);
+        }
+
+        log::debug!("Cache miss for character {id}, loading from Mongo");
+        let character = self.repo.get_by_id(id).await?;
+        self.cache.set(&character).await.ok(); // cache set failure is non-fatal
+        Ok(character)
+    }
+
+    /// Creates a character and caches it straight away
+    pub async fn create(&self, character: &Character) -> Result<(), AppError> {
+        self.repo.create(character).await?;
+        self.cache.set(character).await.ok();
+        Ok(())
+    }
+
+    /// Saves a character and updates the cache
+    pub async fn save(&self, character: &Character) -> Result<(), AppError> {
+        self.repo.save(character).await?;
+        self.cache.set(character).await.ok();
+        Ok(())
+    }
+
+    /// Toggles a reaction on a character, returning whether it is now set and the updated character
+    pub async fn react(&self, id: &str, user_id: &str, reaction: Reaction) -> Result<(bool, Character), AppError> {
+        let (set, character) = self.repo.toggle_reaction(id, user_id, reaction).await?;
+        self.cache.set(&character).await.ok();
+        Ok((set, character))
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), AppError> {
+        self.repo.delete(id).await?;
+        self.cache.invalidate(id).await?;
+        Ok(())
+    }
+
+    /// Gets every character owned by a user, newest first
+    pub async fn get_all_by_owner(&self, owner: &str, limit: u64, skip: u64) -> Result<Vec<Character>, AppError> {
+        let characters = self.repo.get_all_by_owner(owner, limit, skip).await?;
+        self.cache.set_many(&characters).await.ok();
+        Ok(characters)
+    }
+
+    /// Finds characters that have all of the given tags
+    pub async fn search_by_tags(&self, tags: Vec<String>, limit: u64, skip: u64) -> Result<Vec<Character>, AppError> {
+        let characters = self.repo.search_by_tags(tags, limit, skip).await?;
+        self.cache.set_many(&characters).await.ok();
+        Ok(characters)
+    }
+
+    /// Swaps one tag for another on every character, dropping the cached copies it touched
+    pub async fn retag(&self, from: &str, to: &str) -> Result<(), AppError> {
+        for id in self.repo.retag(from, to).await? {
+            self.cache.invalidate(&id).await.ok();
+        }
+        Ok(())
+    }
+
+    /// Adds an implied tag to every character carrying `tag`
+    pub async fn add_implied_tag(&self, tag: &str, implied: &str) -> Result<(), AppError> {
+        for id in self.repo.add_implied_tag(tag, implied).await? {
+            self.cache.invalidate(&id).await.ok();
+        }
+        Ok(())
+    }
+
+    pub async fn count_tagged(&self, tag: &str) -> Result<u64, AppError> {
+        self.repo.count_tagged(tag).await
+    }
+}
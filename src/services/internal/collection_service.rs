@@ -0,0 +1,273 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::database::repos::collection_repo::CollectionRepository;
+use crate::models::collection::{ Collection, CollectionResponse, CollectionSummary, CollectionVisibility };
+use crate::models::media::MediaResponse;
+use crate::models::post::Post;
+use crate::models::viewer::Viewer;
+use crate::services::internal::post_service::PostService;
+use crate::utils::error::AppError;
+
+pub const MAX_COLLECTIONS_PER_USER: u64 = 200;
+pub const MAX_POSTS_PER_COLLECTION: usize = 500;
+const MAX_NAME_LEN: usize = 100;
+const MAX_DESCRIPTION_LEN: usize = 2000;
+// how many collections are listed on a post
+const POST_COLLECTIONS_LIMIT: u64 = 10;
+
+#[derive(Clone)]
+pub struct CollectionService {
+    repo: CollectionRepository,
+    posts: PostService,
+}
+
+impl CollectionService {
+    pub fn new(repo: CollectionRepository, posts: PostService) -> Self {
+        Self { repo, posts }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        self.repo.ensure_indexes().await
+    }
+
+    pub async fn create(
+        &self,
+        owner_id: Uuid,
+        owner: String,
+        name: &str,
+        description: Option<String>,
+        visibility: CollectionVisibility
+    ) -> Result<Collection, AppError> {
+        if self.repo.count_by_owner(&owner_id.to_string()).await? >= MAX_COLLECTIONS_PER_USER {
+            return Err(
+                AppError::BadRequest(format!("You can have at most {} collections", MAX_COLLECTIONS_PER_USER))
+            );
+        }
+
+        let now = Utc::now();
+        let collection = Collection {
+            id: Uuid::new_v4(),
+            owner_id,
+            owner,
+            name: validate_name(name)?,
+            description: validate_description(description)?,
+            visibility,
+            posts: Vec::new(),
+            cover_post_id: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        self.repo.create(&collection).await?;
+        Ok(collection)
+    }
+
+    /// Gets a collection the viewer is allowed to see
+    pub async fn get(&self, id: &str, viewer: &Viewer) -> Result<Collection, AppError> {
+        let collection = self.repo.get_by_id(id).await?;
+        if !collection.visible_to(viewer) {
+            return Err(AppError::CollectionNotFound);
+        }
+        Ok(collection)
+    }
+
+    /// Gets a collection for changing it, only its owner may
+    pub async fn get_owned(&self, id: &str, owner_id: &Uuid) -> Result<Collection, AppError> {
+        let collection = self.repo.get_by_id(id).await?;
+        if collection.owner_id != *owner_id {
+            return Err(AppError::Unauthorized("You are not the owner of this collection".into()));
+        }
+        Ok(collection)
+    }
+
+    pub async fn update(
+        &self,
+        collection: &mut Collection,
+        name: Option<String>,
+        description: Option<Option<String>>,
+        visibility: Option<CollectionVisibility>,
+        cover_post_id: Option<Option<String>>
+    ) -> Result<(), AppError> {
+        if let Some(name) = name {
+            collection.name = validate_name(&name)?;
+        }
+        if let Some(description) = description {
+            collection.description = validate_description(description)?;
+        }
+        if let Some(visibility) = visibility {
+            collection.visibility = visibility;
+        }
+        if let Some(cover_post_id) = cover_post_id {
+            if cover_post_id.as_ref().is_some_and(|id| !collection.posts.contains(id)) {
+                return Err(AppError::BadRequest("The cover has to be a post in the collection".into()));
+            }
+            collection.cover_post_id = cover_post_id;
+        }
+
+        self.repo.save_details(collection).await
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), AppError> {
+        self.repo.delete(id).await
+    }
+
+    /// Adds a published post the owner can see, at `position` or the end
+    pub async fn add_post(
+        &self,
+        collection: &Collection,
+        post_id: &str,
+        position: Option<usize>,
+        owner: &Viewer
+    ) -> Result<(), AppError> {
+        let post = self.posts.get_by_id(post_id).await?;
+        if !post.visible_to(owner) {
+            return Err(AppError::PostNotFound);
+        }
+        if collection.posts.contains(&post_id.to_string()) {
+            return Err(AppError::BadRequest("The post is already in this collection".into()));
+        }
+
+        let position = position.map(|p| p.min(collection.posts.len()));
+        let added = self.repo
+            .add_post(&collection.id.to_string(), post_id, position, MAX_POSTS_PER_COLLECTION).await?;
+        if !added {
+            return Err(
+                AppError::BadRequest(format!("A collection can hold at most {} posts", MAX_POSTS_PER_COLLECTION))
+            );
+        }
+
+        Ok(())
+    }
+
+    pub async fn remove_post(&self, collection: &mut Collection, post_id: &str) -> Result<(), AppError> {
+        if !self.repo.remove_post(&collection.id.to_string(), post_id).await? {
+            return Err(AppError::PostNotFound);
+        }
+
+        if collection.cover_post_id.as_deref() == Some(post_id) {
+            collection.cover_post_id = None;
+            self.repo.save_details(collection).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reorders the posts. `order` has to hold exactly the posts already in the collection.
+    pub async fn reorder(&self, collection: &Collection, order: Vec<String>) -> Result<(), AppError> {
+        let mut current = collection.posts.clone();
+        let mut requested = order.clone();
+        current.sort();
+        requested.sort();
+        if current != requested {
+            return Err(AppError::BadRequest("The new order must contain every post in the collection once".into()));
+        }
+
+        if !self.repo.set_order(&collection.id.to_string(), &collection.posts, &order).await? {
+            return Err(AppError::BadRequest("The collection changed in the meantime, reload and try again".into()));
+        }
+
+        Ok(())
+    }
+
+    /// A user's collections, including unlisted and private ones for the owner
+    pub async fn get_by_owner(
+        &self,
+        owner_id: &str,
+        viewer: &Viewer,
+        skip: u64,
+        limit: u64
+    ) -> Result<Vec<Collection>, AppError> {
+        let is_owner = viewer.user_id() == Some(owner_id);
+        self.repo.get_by_owner(owner_id, is_owner, skip, limit).await
+    }
+
+    /// One page of a collection's posts in order, leaving out posts the viewer can't open
+    pub async fn get_posts(
+        &self,
+        collection: &Collection,
+        skip: u64,
+        limit: u64,
+        viewer: &Viewer
+    ) -> Result<Vec<Post>, AppError> {
+        let ids: Vec<String> = collection.posts
+            .iter()
+            .skip(skip as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect();
+
+        let posts = self.posts.get_many(&ids).await?;
+        Ok(
+            posts
+                .into_iter()
+                .filter(|p| p.visible_to(viewer))
+                .collect()
+        )
+    }
+
+    /// Public collections a post is in, shown on the post
+    pub async fn get_public_containing(&self, post_id: &str) -> Result<Vec<CollectionSummary>, AppError> {
+        let collections = self.repo.get_public_containing(post_id, POST_COLLECTIONS_LIMIT).await?;
+        Ok(collections.iter().map(CollectionSummary::from).collect())
+    }
+
+    /// Called when a post is deleted
+    pub async fn remove_post_everywhere(&self, post_id: &str) -> Result<(), AppError> {
+        self.repo.remove_post_everywhere(post_id).await
+    }
+
+    /// Builds responses with their covers, loading every cover post in one go
+    pub async fn to_responses(
+        &self,
+        collections: &[Collection],
+        viewer: &Viewer
+    ) -> Result<Vec<CollectionResponse>, AppError> {
+        let cover_ids: Vec<String> = collections
+            .iter()
+            .filter_map(|c| c.cover_post().cloned())
+            .collect();
+        let covers = self.posts.get_many(&cover_ids).await?;
+
+        Ok(
+            collections
+                .iter()
+                .map(|collection| {
+                    let cover = collection
+                        .cover_post()
+                        .and_then(|id| covers.iter().find(|p| p.id.to_string() == *id))
+                        .filter(|post| post.visible_to(viewer))
+                        .and_then(|post| {
+                            let media = post.media.first()?;
+                            Some(MediaResponse {
+                                media: media.clone(),
+                                blurred: !viewer.show_nsfw && media.is_nsfw_within(post.nsfw),
+                            })
+                        });
+                    CollectionResponse::new(collection, cover)
+                })
+                .collect()
+        )
+    }
+}
+
+fn validate_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Collection name cannot be empty".into()));
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(AppError::BadRequest(format!("Collection name can be at most {} characters", MAX_NAME_LEN)));
+    }
+    Ok(name.to_string())
+}
+
+fn validate_description(description: Option<String>) -> Result<Option<String>, AppError> {
+    let description = description.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+    if description.as_ref().is_some_and(|d| d.chars().count() > MAX_DESCRIPTION_LEN) {
+        return Err(
+            AppError::BadRequest(format!("Description can be at most {} characters", MAX_DESCRIPTION_LEN))
+        );
+    }
+    Ok(description)
+}
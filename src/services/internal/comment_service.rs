@@ -0,0 +1,425 @@
+use chrono::{ Duration, Utc };
+use uuid::Uuid;
+
+use crate::database::repos::comment_repo::{ CommentFilter, CommentRepository };
+use crate::models::comment::{ Comment, CommentPage, CommentResponse, CommentSort, Mention, PostCommentsResponse };
+use crate::models::post::{ CommentSettings, Post };
+use crate::models::reaction::Reaction;
+use crate::models::viewer::Viewer;
+use crate::services::internal::notification_service::NotificationService;
+use crate::services::internal::post_service::PostService;
+use crate::services::internal::profile_service::ProfileService;
+use crate::utils::cursor::{ FeedCursor, FeedSort };
+use crate::utils::error::AppError;
+use crate::utils::mentions::parse_mentions;
+
+// replies to a comment this deep are refused, threads past it are unreadable anyway
+pub const MAX_COMMENT_DEPTH: u32 = 8;
+// authors can fix their comments for this long after posting
+pub const COMMENT_EDIT_WINDOW_MINS: i64 = 30;
+
+#[derive(Clone)]
+pub struct CommentService {
+    repo: CommentRepository,
+    posts: PostService,
+    profiles: ProfileService,
+    notifications: NotificationService,
+}
+
+impl CommentService {
+    pub fn new(
+        repo: CommentRepository,
+        posts: PostService,
+        profiles: ProfileService,
+        notifications: NotificationService
+    ) -> Self {
+        Self { repo, posts, profiles, notifications }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        self.repo.ensure_indexes().await
+    }
+
+    /// A post's top-level comments, newest first unless sorted otherwise. The pinned comment
+    /// comes separately on the first page
+    pub async fn list_for_post(
+        &self,
+        post_id: &str,
+        viewer: &Viewer,
+        sort: Option<CommentSort>,
+        cursor: Option<&str>,
+        limit: u64
+    ) -> Result<PostCommentsResponse, AppError> {
+        let post = self.visible_post(post_id, viewer).await?;
+        let settings = post.comment_settings.clone();
+        let sort = sort.unwrap_or(CommentSort::Newest).feed_sort();
+        let after = cursor.map(FeedCursor::decode).transpose()?;
+
+        let shown = CommentFilter {
+            viewer: viewer.user_id(),
+            show_pending: is_post_author(&post, viewer),
+            exclude: settings.pinned_comment_id.as_deref(),
+        };
+        let comments = self.repo.get_top_level(post_id, &shown, sort, after.as_ref(), limit).await?;
+
+        let pinned = match (&settings.pinned_comment_id, &after) {
+            (Some(id), None) => self.repo.get_by_id(id).await.ok(),
+            _ => None,
+        };
+        Ok(PostCommentsResponse {
+            page: page(comments, sort, viewer, limit),
+            pinned: pinned.map(|comment| CommentResponse::for_viewer(&comment, viewer.user_id())),
+            settings,
+        })
+    }
+
+    /// The direct replies to a comment, oldest first unless sorted otherwise
+    pub async fn list_replies(
+        &self,
+        parent_id: &str,
+        viewer: &Viewer,
+        sort: Option<CommentSort>,
+        cursor: Option<&str>,
+        limit: u64
+    ) -> Result<CommentPage, AppError> {
+        let parent = self.repo.get_by_id(parent_id).await?;
+        let post = self.visible_post(&parent.post_id.to_string(), viewer).await?;
+        let sort = sort.unwrap_or(CommentSort::Oldest).feed_sort();
+        let after = cursor.map(FeedCursor::decode).transpose()?;
+
+        let shown = CommentFilter {
+            viewer: viewer.user_id(),
+            show_pending: is_post_author(&post, viewer),
+            exclude: None,
+        };
+        let comments = self.repo.get_replies(parent_id, &shown, sort, after.as_ref(), limit).await?;
+        Ok(page(comments, sort, viewer, limit))
+    }
+
+    /// Comments on the author's post that are waiting for approval
+    pub async fn list_pending(
+        &self,
+        post_id: &str,
+        viewer: &Viewer,
+        cursor: Option<&str>,
+        limit: u64
+    ) -> Result<CommentPage, AppError> {
+        self.authored_post(post_id, viewer).await?;
+        let after = cursor.map(FeedCursor::decode).transpose()?;
+        let comments = self.repo.get_pending(post_id, after.as_ref(), limit).await?;
+        Ok(page(comments, FeedSort::Oldest, viewer, limit))
+    }
+
+    pub async fn create(
+        &self,
+        viewer: &Viewer,
+        username: String,
+        content: String,
+        post_id: Uuid
+    ) -> Result<Comment, AppError> {
+        let user_id = viewer.user_id().ok_or(AppError::Unauthorized("Sign in to comment".into()))?;
+        validate_content(&content)?;
+        let post = self.visible_post(&post_id.to_string(), viewer).await?;
+        let pending = self.admit(&post, user_id).await?;
+
+        let mut comment = Comment::new(user_id.to_string(), username, content, post_id);
+        comment.pending = pending;
+        comment.mentions = self.resolve_mentions(&comment.content).await?;
+        self.repo.create(&comment).await?;
+        self.announce(&post, None, &comment).await;
+        Ok(comment)
+    }
+
+    pub async fn reply(
+        &self,
+        parent_id: &str,
+        viewer: &Viewer,
+        username: String,
+        content: String
+    ) -> Result<Comment, AppError> {
+        let user_id = viewer.user_id().ok_or(AppError::Unauthorized("Sign in to comment".into()))?;
+        validate_content(&content)?;
+        let parent = self.repo.get_by_id(parent_id).await?;
+        if parent.deleted {
+            return Err(AppError::BadRequest("You can't reply to a deleted comment".into()));
+        }
+        if parent.pending {
+            return Err(AppError::BadRequest("You can't reply to a comment waiting for approval".into()));
+        }
+        if parent.depth >= MAX_COMMENT_DEPTH {
+            return Err(AppError::BadRequest(format!("Replies can't be nested more than {} deep", MAX_COMMENT_DEPTH)));
+        }
+        let post = self.visible_post(&parent.post_id.to_string(), viewer).await?;
+        let pending = self.admit(&post, user_id).await?;
+
+        let mut reply = Comment::reply_to(&parent, user_id.to_string(), username, content);
+        reply.pending = pending;
+        reply.mentions = self.resolve_mentions(&reply.content).await?;
+        self.repo.create_reply(&reply).await?;
+        self.announce(&post, Some(&parent), &reply).await;
+        Ok(reply)
+    }
+
+    /// Changes the text of the author's own comment while the edit window is open
+    pub async fn edit(&self, comment_id: &str, user_id: &str, username: &str, content: String) -> Result<Comment, AppError> {
+        let mut comment = self.repo.get_by_id(comment_id).await?;
+        if comment.deleted {
+            return Err(AppError::CommentNotFound);
+        }
+        if !comment.is_author(user_id, username) {
+            return Err(AppError::Unauthorized("You are not the author of this comment".into()));
+        }
+        if Utc::now() - comment.created_at > Duration::minutes(COMMENT_EDIT_WINDOW_MINS) {
+            return Err(
+                AppError::BadRequest(format!("Comments can only be edited for {} minutes after posting", COMMENT_EDIT_WINDOW_MINS))
+            );
+        }
+        validate_content(&content)?;
+
+        let now = Utc::now();
+        let mentions = self.resolve_mentions(&content).await?;
+        self.repo.set_content(comment_id, &content, &mentions, now).await?;
+        comment.content = content;
+        comment.mentions = mentions;
+        comment.edited_at = Some(now);
+        Ok(comment)
+    }
+
+    /// Deletes the author's own comment, or any comment under the post's author's own post
+    pub async fn delete(&self, comment_id: &str, user_id: &str, username: &str) -> Result<(), AppError> {
+        let comment = self.repo.get_by_id(comment_id).await?;
+        if comment.deleted {
+            return Err(AppError::CommentNotFound);
+        }
+        if comment.is_author(user_id, username) {
+            return self.discard(comment, None).await;
+        }
+
+        let post = self.posts.get_by_id(&comment.post_id.to_string()).await?;
+        if post.author_id.to_string() != user_id {
+            return Err(AppError::Unauthorized("You are not the author of this comment".into()));
+        }
+        self.discard(comment, Some(user_id)).await
+    }
+
+    /// Lets a held comment on the author's post through
+    pub async fn approve(&self, comment_id: &str, viewer: &Viewer) -> Result<Comment, AppError> {
+        let comment = self.repo.get_by_id(comment_id).await?;
+        let post = self.authored_post(&comment.post_id.to_string(), viewer).await?;
+        let comment = self.repo
+            .approve(comment_id).await?
+            .ok_or(AppError::BadRequest("Comment is not waiting for approval".into()))?;
+
+        // the author heard about it when it was held
+        let parent = match &comment.parent_id {
+            Some(id) => self.repo.get_by_id(id).await.ok(),
+            None => None,
+        };
+        self.notifications.comment_posted(&post, parent.as_ref(), &comment, viewer.user_id()).await;
+        Ok(comment)
+    }
+
+    /// Changes who may comment on the author's post. The pinned comment is left as it is
+    pub async fn update_settings(
+        &self,
+        post_id: &str,
+        viewer: &Viewer,
+        locked: Option<bool>,
+        followers_only: Option<bool>,
+        require_approval: Option<bool>
+    ) -> Result<CommentSettings, AppError> {
+        let post = self.authored_post(post_id, viewer).await?;
+        let mut settings = post.comment_settings;
+        settings.locked = locked.unwrap_or(settings.locked);
+        settings.followers_only = followers_only.unwrap_or(settings.followers_only);
+        settings.require_approval = require_approval.unwrap_or(settings.require_approval);
+
+        let post = self.posts.set_comment_settings(post_id, &settings).await?;
+        Ok(post.comment_settings)
+    }
+
+    /// Pins a top-level comment above the rest of its post's, replacing any pinned before
+    pub async fn pin(&self, comment_id: &str, viewer: &Viewer) -> Result<CommentSettings, AppError> {
+        let comment = self.repo.get_by_id(comment_id).await?;
+        if comment.deleted || comment.pending {
+            return Err(AppError::CommentNotFound);
+        }
+        if comment.parent_id.is_some() {
+            return Err(AppError::BadRequest("Only top-level comments can be pinned".into()));
+        }
+        self.set_pinned(&comment.post_id.to_string(), Some(comment_id.to_string()), viewer).await
+    }
+
+    pub async fn unpin(&self, post_id: &str, viewer: &Viewer) -> Result<CommentSettings, AppError> {
+        self.set_pinned(post_id, None, viewer).await
+    }
+
+    async fn set_pinned(&self, post_id: &str, comment_id: Option<String>, viewer: &Viewer) -> Result<CommentSettings, AppError> {
+        let post = self.authored_post(post_id, viewer).await?;
+        let mut settings = post.comment_settings;
+        settings.pinned_comment_id = comment_id;
+        let post = self.posts.set_comment_settings(post_id, &settings).await?;
+        Ok(post.comment_settings)
+    }
+
+    /// Takes any comment down as a moderator
+    pub async fn remove(&self, comment_id: &str, moderator_id: &str) -> Result<(), AppError> {
+        let comment = self.repo.get_by_id(comment_id).await?;
+        if comment.removed_by.is_some() {
+            return Err(AppError::CommentNotFound);
+        }
+        log::info!("Comment {} by {} removed by {}", comment_id, comment.author, moderator_id);
+        self.discard(comment, Some(moderator_id)).await
+    }
+
+    // comments with replies become tombstones, the rest are deleted outright along with
+    // any tombstones above them that no longer have replies to hold up
+    async fn discard(&self, comment: Comment, removed_by: Option<&str>) -> Result<(), AppError> {
+        let id = comment.id.to_string();
+        if comment.parent_id.is_none() {
+            self.posts.unpin_comment(&comment.post_id.to_string(), &id).await?;
+        }
+        if comment.reply_count > 0 {
+            return self.repo.tombstone(&id, removed_by).await;
+        }
+
+        self.repo.delete_by_id(&id).await?;
+        // held replies were never counted on their parent
+        let mut parent_id = comment.parent_id.filter(|_| !comment.pending);
+        while let Some(id) = parent_id {
+            let Some(parent) = self.repo.uncount_reply(&id).await? else {
+                break;
+            };
+            if !parent.deleted || parent.reply_count > 0 {
+                break;
+            }
+            self.repo.delete_by_id(&id).await?;
+            parent_id = parent.parent_id;
+        }
+        Ok(())
+    }
+
+    // comments on posts the viewer can't see don't exist as far as they're concerned
+    async fn visible_post(&self, post_id: &str, viewer: &Viewer) -> Result<Post, AppError> {
+        let post = self.posts.get_by_id(post_id).await?;
+        if !post.visible_to(viewer) {
+            return Err(AppError::PostNotFound);
+        }
+        Ok(post)
+    }
+
+    async fn authored_post(&self, post_id: &str, viewer: &Viewer) -> Result<Post, AppError> {
+        let post = self.posts.get_by_id(post_id).await?;
+        if !is_post_author(&post, viewer) {
+            return Err(AppError::Unauthorized("You are not the author of this post".into()));
+        }
+        Ok(post)
+    }
+
+    // applies the author's comment settings to someone commenting, returning whether the
+    // comment has to wait for approval. authors are never held back on their own post
+    async fn admit(&self, post: &Post, user_id: &str) -> Result<bool, AppError> {
+        if post.author_id.to_string() == user_id {
+            return Ok(false);
+        }
+        let settings = &post.comment_settings;
+        if settings.locked {
+            return Err(AppError::BadRequest("Comments are locked on this post".into()));
+        }
+        if settings.followers_only {
+            let uuid = Uuid::parse_str(user_id).map_err(|_| AppError::UserNotFound)?;
+            let profile = self.profiles.get_by_uuid(&uuid).await?;
+            if !profile.following.contains(&post.author_id.to_string()) {
+                return Err(AppError::Unauthorized("Only followers of the author can comment on this post".into()));
+            }
+        }
+        if settings.require_approval {
+            return Ok(!self.repo.has_approved(&post.id.to_string(), user_id).await?);
+        }
+        Ok(false)
+    }
+
+    // held comments only go to the post's author until they're approved
+    async fn announce(&self, post: &Post, parent: Option<&Comment>, comment: &Comment) {
+        if comment.pending {
+            self.notifications.comment_held(post, comment).await;
+        } else {
+            self.notifications.comment_posted(post, parent, comment, None).await;
+        }
+    }
+
+    // usernames that don't belong to anyone are left as plain text
+    async fn resolve_mentions(&self, content: &str) -> Result<Vec<Mention>, AppError> {
+        let usernames = parse_mentions(content);
+        if usernames.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let profiles = self.profiles.get_many(usernames.clone(), Vec::new()).await?;
+        Ok(
+            usernames
+                .iter()
+                .filter_map(|username| profiles.iter().find(|p| p.username.eq_ignore_ascii_case(username)))
+                .map(|profile| Mention { user_id: profile.id.to_string(), username: profile.username.clone() })
+                .collect()
+        )
+    }
+
+    /// Toggles a reaction on a comment or reply, returning whether it is now set. Only comments
+    /// the viewer can see and that aren't waiting for approval take reactions
+    pub async fn react(&self, comment_id: &str, viewer: &Viewer, reaction: Reaction) -> Result<bool, AppError> {
+        let user_id = viewer.user_id().ok_or(AppError::Unauthorized("Sign in to react".into()))?;
+        let comment = self.repo.get_by_id(comment_id).await?;
+        if comment.deleted {
+            return Err(AppError::CommentNotFound);
+        }
+        let post = self.visible_post(&comment.post_id.to_string(), viewer).await?;
+        if comment.pending {
+            // held comments only show to their author and the post's
+            if comment.author_id.as_deref() != Some(user_id) && !is_post_author(&post, viewer) {
+                return Err(AppError::CommentNotFound);
+            }
+            return Err(AppError::BadRequest("Comments can't be reacted to until they are approved".into()));
+        }
+
+        let (set, comment) = self.repo.toggle_reaction(comment_id, user_id, reaction).await?;
+        if set && reaction == Reaction::Like {
+            self.notifications.comment_liked(&comment, user_id).await;
+        }
+        Ok(set)
+    }
+}
+
+fn is_post_author(post: &Post, viewer: &Viewer) -> bool {
+    viewer.user_id() == Some(post.author_id.to_string().as_str())
+}
+
+fn validate_content(content: &str) -> Result<(), AppError> {
+    if content.trim().is_empty() {
+        return Err(AppError::BadRequest("Comment can't be empty".into()));
+    }
+    Ok(())
+}
+
+// the cursor is only handed out when the page came back full
+fn page(comments: Vec<Comment>, sort: FeedSort, viewer: &Viewer, limit: u64) -> CommentPage {
+    let next_cursor = comments
+        .last()
+        .filter(|_| (comments.len() as u64) >= limit)
+        .map(|last| {
+            let id = last.id.to_string();
+            let cursor = match sort {
+                FeedSort::MostLiked => FeedCursor::Score { k: last.like_count as f64, id },
+                _ => FeedCursor::Time { k: last.created_at.timestamp_millis(), id },
+            };
+            cursor.encode()
+        });
+
+    CommentPage {
+        comments: comments
+            .iter()
+            .map(|comment| CommentResponse::for_viewer(comment, viewer.user_id()))
+            .collect(),
+        next_cursor,
+    }
+}
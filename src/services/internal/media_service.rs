@@ -0,0 +1,529 @@
+use std::collections::{ HashMap, HashSet };
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::web::Bytes;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::database::repos::character_repo::CharacterRepository;
+use crate::database::repos::hash_ban_repo::HashBanRepository;
+use crate::database::repos::media_repo::MediaIndexRepository;
+use crate::database::repos::post_repo::PostRepository;
+use crate::database::repos::upload_repo::PendingUploadRepository;
+use crate::models::media::{ BannedHash, Media, MediaObject };
+use crate::models::post::Post;
+use crate::models::upload::{ NewUpload, PendingUpload, UploadTicket };
+use crate::services::internal::post_service::PostService;
+use crate::services::storage::{
+    Storage,
+    StorageError,
+    ALLOWED_IMAGE_TYPES,
+    MAX_DIRECT_VIDEO_SIZE_BYTES,
+    MAX_FILES_PER_REQUEST,
+    MAX_IMAGE_SIZE_BYTES,
+    STAGING_PREFIX,
+};
+use crate::utils::error::AppError;
+use crate::utils::hash::{ hash_bytes_sha256, is_sha256_hex };
+use crate::utils::image::process_image;
+use crate::utils::sniff::{ canonical_type, extension_for, is_allowed_type, validate_upload, validate_upload_start };
+use crate::utils::video::{ extract_poster, probe_video };
+
+// how long the client has to start the upload
+const UPLOAD_URL_MINUTES: u64 = 15;
+// how long an upload can sit unfinalized before it's thrown away
+const PENDING_UPLOAD_HOURS: i64 = 1;
+// a finalize that died halfway frees its uploads after this long
+const CLAIM_MINUTES: i64 = 15;
+// enough to sniff the type and spot markup without pulling a whole video
+const SNIFF_BYTES: u64 = 64 * 1024;
+const EXPIRE_BATCH_SIZE: i64 = 500;
+
+fn storage_error(e: StorageError) -> AppError {
+    AppError::InternalServerError(e.to_string())
+}
+
+/// Stores media, either sent through us or uploaded straight to storage with a presigned URL.
+/// Files are kept once under `media/` by the SHA-256 they were uploaded with, the same file
+/// uploaded again reuses what's there.
+#[derive(Clone)]
+pub struct MediaService {
+    storage: Arc<dyn Storage>,
+    uploads: PendingUploadRepository,
+    index: MediaIndexRepository,
+    bans: HashBanRepository,
+    posts: PostRepository,
+    characters: CharacterRepository,
+    post_service: PostService,
+}
+
+impl MediaService {
+    pub fn new(
+        storage: Arc<dyn Storage>,
+        uploads: PendingUploadRepository,
+        index: MediaIndexRepository,
+        bans: HashBanRepository,
+        posts: PostRepository,
+        characters: CharacterRepository,
+        post_service: PostService
+    ) -> Self {
+        Self { storage, uploads, index, bans, posts, characters, post_service }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        self.uploads.ensure_indexes().await?;
+        self.posts.ensure_media_hash_index().await?;
+        self.characters.ensure_media_hash_index().await
+    }
+
+    /// Stores one uploaded file and returns it as post media. Images are decoded for their size,
+    /// stripped of EXIF/GPS and get thumbnail + preview WebP variants next to the original.
+    pub async fn store(&self, content_type: String, data: Bytes) -> Result<Media, AppError> {
+        let sha256 = hash_bytes_sha256(&data);
+        Ok(self.store_object(&sha256, content_type, data).await?.to_media())
+    }
+
+    /// Refuses files moderators banned
+    pub async fn ensure_not_banned(&self, sha256: &str) -> Result<(), AppError> {
+        if self.bans.is_banned(sha256).await? {
+            return Err(AppError::BadRequest("This file is not allowed".into()));
+        }
+        Ok(())
+    }
+
+    pub async fn ban_hash(&self, sha256: &str, reason: Option<String>, moderator_id: &str) -> Result<BannedHash, AppError> {
+        if !is_sha256_hex(sha256) {
+            return Err(AppError::BadRequest("sha256 must be a hex SHA-256 digest".into()));
+        }
+        let ban = BannedHash {
+            sha256: sha256.to_ascii_lowercase(),
+            reason: reason.filter(|r| !r.trim().is_empty()),
+            banned_by: moderator_id.to_string(),
+            created_at: Utc::now(),
+        };
+        self.bans.ban(&ban).await?;
+        Ok(ban)
+    }
+
+    pub async fn unban_hash(&self, sha256: &str) -> Result<(), AppError> {
+        if !self.bans.unban(&sha256.to_ascii_lowercase()).await? {
+            return Err(AppError::HashBanNotFound);
+        }
+        Ok(())
+    }
+
+    pub async fn list_bans(&self, skip: u64, limit: i64) -> Result<Vec<BannedHash>, AppError> {
+        self.bans.list(skip, limit).await
+    }
+
+    /// Which of the given hashes some post or character still uses
+    pub async fn hashes_in_use(&self, hashes: &[String]) -> Result<HashSet<String>, AppError> {
+        let mut used: HashSet<String> = self.posts.media_hashes_in_use(hashes).await?.into_iter().collect();
+        used.extend(self.characters.media_hashes_in_use(hashes).await?);
+        Ok(used)
+    }
+
+    /// Gives back the references of media removed from their post or character and deletes
+    /// the objects nothing uses anymore
+    pub async fn release(&self, media: &[Media]) -> Result<(), AppError> {
+        let mut released: HashMap<String, i64> = HashMap::new();
+        for hash in media.iter().filter_map(|m| m.sha256.clone()) {
+            *released.entry(hash).or_default() += 1;
+        }
+        if released.is_empty() {
+            return Ok(());
+        }
+        for (hash, count) in &released {
+            self.index.drop_refs(hash, *count).await?;
+        }
+
+        // counts from before references were kept may be off, so what's still in use stays
+        let hashes: Vec<String> = released.into_keys().collect();
+        let used = self.hashes_in_use(&hashes).await?;
+        let mut keys = Vec::new();
+        for hash in hashes.iter().filter(|hash| !used.contains(*hash)) {
+            let Some(object) = self.index.delete_unreferenced(hash).await? else {
+                continue;
+            };
+            keys.extend(
+                [Some(object.url), object.thumbnail_url, object.preview_url, object.poster_url]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|url| self.storage.key_from_url(&url))
+            );
+        }
+        self.storage.delete_many(&keys).await.map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Forgets indexed files whose objects are gone, see the orphan sweep
+    pub async fn forget(&self, hashes: &[String]) -> Result<(), AppError> {
+        self.index.delete_many(hashes).await
+    }
+
+    /// Hands out presigned upload URLs for new media of a draft or scheduled post.
+    pub async fn presign(&self, post: &Post, user_id: &str, files: Vec<NewUpload>) -> Result<Vec<UploadTicket>, AppError> {
+        if post.author_id.to_string() != user_id {
+            return Err(AppError::Unauthorized("You are not the author of this post".into()));
+        }
+        if post.is_published() {
+            return Err(AppError::BadRequest("Media can only be uploaded to drafts and scheduled posts".into()));
+        }
+        if files.is_empty() {
+            return Err(AppError::BadRequest("No files to upload".into()));
+        }
+
+        let post_id = post.id.to_string();
+        let pending = self.uploads.count_by_post(&post_id).await? as usize;
+        if post.media.len() + pending + files.len() > MAX_FILES_PER_REQUEST {
+            return Err(
+                AppError::BadRequest(format!("A post can have at most {} files", MAX_FILES_PER_REQUEST))
+            );
+        }
+
+        let now = Utc::now();
+        let mut uploads = Vec::new();
+        let mut tickets = Vec::new();
+        for file in files {
+            if !is_allowed_type(&file.content_type) {
+                return Err(AppError::BadRequest(format!("Unsupported file type: {}", file.content_type)));
+            }
+            let content_type = canonical_type(&file.content_type).to_string();
+            let limit = if ALLOWED_IMAGE_TYPES.contains(&content_type.as_str()) {
+                MAX_IMAGE_SIZE_BYTES
+            } else {
+                MAX_DIRECT_VIDEO_SIZE_BYTES
+            };
+            if file.size_bytes == 0 {
+                return Err(AppError::BadRequest("Files can't be empty".into()));
+            }
+            if file.size_bytes > limit as u64 {
+                return Err(AppError::FileToBig((limit / 1024 / 1024).to_string()));
+            }
+            if !is_sha256_hex(&file.sha256) {
+                return Err(AppError::BadRequest("sha256 must be a hex SHA-256 digest".into()));
+            }
+            let sha256 = file.sha256.to_ascii_lowercase();
+            self.ensure_not_banned(&sha256).await?;
+
+            let id = Uuid::new_v4();
+            let extension = extension_for(&content_type).unwrap_or("bin");
+            let key = format!("{}{}/{}.{}", STAGING_PREFIX, post_id, id.simple(), extension);
+            let presigned = self.storage
+                .presign_upload(
+                    &key,
+                    &content_type,
+                    file.size_bytes,
+                    &sha256,
+                    Duration::from_secs(UPLOAD_URL_MINUTES * 60)
+                ).await
+                .map_err(storage_error)?;
+
+            tickets.push(UploadTicket {
+                upload_id: id.to_string(),
+                url: presigned.url,
+                method: presigned.method,
+                headers: presigned.headers,
+                expires_at: presigned.expires_at,
+            });
+            uploads.push(PendingUpload {
+                id,
+                post_id: post_id.clone(),
+                user_id: user_id.to_string(),
+                key,
+                content_type,
+                size_bytes: file.size_bytes,
+                sha256,
+                created_at: now,
+                expires_at: now + chrono::Duration::hours(PENDING_UPLOAD_HOURS),
+                claimed_until: None,
+            });
+        }
+
+        self.uploads.create_many(&uploads).await?;
+        Ok(tickets)
+    }
+
+    /// Checks the uploaded objects against what was presigned and attaches them to the post,
+    /// in the order given. Objects that fail the checks are deleted.
+    pub async fn finalize(&self, post: &Post, user_id: &str, upload_ids: &[String]) -> Result<Post, AppError> {
+        if post.author_id.to_string() != user_id {
+            return Err(AppError::Unauthorized("You are not the author of this post".into()));
+        }
+        if post.is_published() {
+            return Err(AppError::BadRequest("Media can only be uploaded to drafts and scheduled posts".into()));
+        }
+
+        // every upload is claimed before any is checked, so two finalize calls racing each
+        // other can't both attach the same file
+        let post_id = post.id.to_string();
+        let now = Utc::now();
+        let until = now + chrono::Duration::minutes(CLAIM_MINUTES);
+        let mut claimed = Vec::new();
+        for id in upload_ids {
+            match self.uploads.claim(id, &post_id, user_id, now, until).await? {
+                Some(upload) => claimed.push(upload),
+                None => {
+                    self.unclaim(&claimed).await;
+                    return Err(AppError::BadRequest(format!("Upload {} is unknown, expired or already being finalized", id)));
+                }
+            }
+        }
+
+        let mut post = post.clone();
+        let mut remaining = claimed.into_iter();
+        while let Some(upload) = remaining.next() {
+            match self.attach(&post_id, &upload).await {
+                Ok(updated) => {
+                    post = updated;
+                }
+                Err(e) => {
+                    self.unclaim(remaining.as_slice()).await;
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(post)
+    }
+
+    // checks one claimed upload and adds it to the post
+    async fn attach(&self, post_id: &str, upload: &PendingUpload) -> Result<Post, AppError> {
+        // not there yet is fine, the client can finalize again once it is
+        let Some(info) = self.storage.head(&upload.key).await.map_err(storage_error)? else {
+            self.unclaim(std::slice::from_ref(upload)).await;
+            return Err(AppError::BadRequest(format!("Upload {} has not been uploaded yet", upload.id)));
+        };
+
+        let media = match self.verify(upload, info.size_bytes, info.sha256).await {
+            Ok(media) => media,
+            Err(e) => {
+                self.discard(upload).await;
+                return Err(e);
+            }
+        };
+
+        let post = match self.post_service.add_media(post_id, &media).await {
+            Ok(post) => post,
+            Err(e) => {
+                // verify took a ref for the post, give it back
+                if let Err(e) = self.release(std::slice::from_ref(&media)).await {
+                    log::warn!("Failed to release media {}: {:?}", media.url, e);
+                }
+                self.unclaim(std::slice::from_ref(upload)).await;
+                return Err(e);
+            }
+        };
+        self.uploads.delete(&upload.id.to_string()).await?;
+
+        // the file lives under media/ now, or already did
+        if let Err(e) = self.storage.delete(&upload.key).await {
+            log::warn!("Failed to delete finalized upload {}: {:?}", upload.key, e);
+        }
+        Ok(post)
+    }
+
+    async fn unclaim(&self, uploads: &[PendingUpload]) {
+        for upload in uploads {
+            if let Err(e) = self.uploads.unclaim(&upload.id.to_string()).await {
+                log::warn!("Failed to unclaim upload {}: {:?}", upload.id, e);
+            }
+        }
+    }
+
+    /// Deletes uploads that were never finalized, returns how many
+    pub async fn expire_abandoned(&self) -> Result<usize, AppError> {
+        let mut expired = 0;
+        loop {
+            let uploads = self.uploads.get_expired(Utc::now(), EXPIRE_BATCH_SIZE).await?;
+            if uploads.is_empty() {
+                return Ok(expired);
+            }
+
+            let keys: Vec<String> = uploads
+                .iter()
+                .map(|u| u.key.clone())
+                .collect();
+            self.storage.delete_many(&keys).await.map_err(storage_error)?;
+
+            let ids: Vec<String> = uploads
+                .iter()
+                .map(|u| u.id.to_string())
+                .collect();
+            self.uploads.delete_many(&ids).await?;
+            expired += ids.len();
+        }
+    }
+
+    /// Drops the pending uploads of a deleted post and whatever was already uploaded for them
+    pub async fn remove_post(&self, post_id: &str) -> Result<(), AppError> {
+        self.uploads.delete_by_post(post_id).await?;
+        if let Err(e) = self.storage.delete_prefix(&format!("{}{}/", STAGING_PREFIX, post_id)).await {
+            log::warn!("Failed to delete the staged uploads of post {}: {:?}", post_id, e);
+        }
+        Ok(())
+    }
+
+    /// Which of the given staged objects a pending upload still waits on, see the orphan sweep
+    pub async fn staged_keys_in_use(&self, keys: &[String]) -> Result<HashSet<String>, AppError> {
+        Ok(self.uploads.keys_in_use(keys).await?.into_iter().collect())
+    }
+
+    async fn verify(&self, upload: &PendingUpload, size_bytes: u64, stored_sha256: Option<String>) -> Result<Media, AppError> {
+        if size_bytes != upload.size_bytes {
+            return Err(AppError::BadRequest(format!("Upload {} is not the size it was signed for", upload.id)));
+        }
+        // it may have been banned since the URL was handed out
+        self.ensure_not_banned(&upload.sha256).await?;
+        let mismatch = || AppError::BadRequest(format!("Upload {} does not match its SHA-256", upload.id));
+        // storage checked the checksum it was signed with, there's no need to hash it again
+        let hash_checked = match stored_sha256 {
+            Some(sha256) if sha256 != upload.sha256 => {
+                return Err(mismatch());
+            }
+            Some(_) => true,
+            None => false,
+        };
+
+        // sniff first so obvious junk isn't downloaded whole
+        let head = self.storage.read_start(&upload.key, SNIFF_BYTES).await.map_err(storage_error)?;
+        validate_upload_start(&upload.content_type, &head)?;
+
+        // a file we already have was checked in full when it was first stored
+        let known = if hash_checked { self.index.take_ref(&upload.sha256).await? } else { None };
+        if let Some(existing) = known {
+            return Ok(existing.to_media());
+        }
+
+        let mut data = self.storage.read(&upload.key).await.map_err(storage_error)?;
+        if !hash_checked && hash_bytes_sha256(&data) != upload.sha256 {
+            return Err(mismatch());
+        }
+        let (_, len) = validate_upload(&upload.content_type, &data)?;
+        data.truncate(len);
+
+        let object = self
+            .store_object_from(&upload.sha256, upload.content_type.clone(), data.into(), Some(&upload.key)).await?;
+        Ok(object.to_media())
+    }
+
+    // the indexed objects of a file, stored first when it's new
+    async fn store_object(&self, sha256: &str, content_type: String, data: Bytes) -> Result<MediaObject, AppError> {
+        self.store_object_from(sha256, content_type, data, None).await
+    }
+
+    // like store_object, videos already uploaded under `staged` are copied instead of sent again
+    async fn store_object_from(
+        &self,
+        sha256: &str,
+        content_type: String,
+        data: Bytes,
+        staged: Option<&str>
+    ) -> Result<MediaObject, AppError> {
+        self.ensure_not_banned(sha256).await?;
+        // a file released in the meantime is gone from the index, it's then stored again
+        if let Some(existing) = self.index.take_ref(sha256).await? {
+            return Ok(existing);
+        }
+
+        if !ALLOWED_IMAGE_TYPES.contains(&content_type.as_str()) {
+            return self.store_video(sha256, content_type, data, staged).await;
+        }
+
+        let upload_failed = |_| AppError::InternalServerError("Failed to upload media".into());
+        let key = media_key(sha256, &content_type);
+
+        // decoding and resizing would stall the worker, so it gets its own thread
+        let image_type = content_type.clone();
+        let image = tokio::task
+            ::spawn_blocking(move || process_image(&image_type, &data)).await
+            .map_err(|_| AppError::InternalServerError("Failed to process image".into()))??;
+
+        let thumbnail_key = format!("media/thumb/{}.webp", sha256);
+        let preview_key = format!("media/preview/{}.webp", sha256);
+        self.storage.upload(&key, &image.data, &content_type).await.map_err(upload_failed)?;
+        self.storage.upload(&thumbnail_key, &image.thumbnail, "image/webp").await.map_err(upload_failed)?;
+        self.storage.upload(&preview_key, &image.preview, "image/webp").await.map_err(upload_failed)?;
+
+        self.index.insert(&MediaObject {
+            sha256: sha256.to_string(),
+            url: self.storage.public_url(&key),
+            thumbnail_url: Some(self.storage.public_url(&thumbnail_key)),
+            preview_url: Some(self.storage.public_url(&preview_key)),
+            poster_url: None,
+            content_type,
+            size_bytes: image.data.len() as u64,
+            width: Some(image.width),
+            height: Some(image.height),
+            duration_secs: None,
+            refs: 1,
+            created_at: Utc::now(),
+        }).await
+    }
+
+    // videos keep their bytes as they are, the container gives us the size and length
+    // and a frame from it becomes the poster and thumbnail
+    async fn store_video(
+        &self,
+        sha256: &str,
+        content_type: String,
+        data: Bytes,
+        staged: Option<&str>
+    ) -> Result<MediaObject, AppError> {
+        let upload_failed = |_| AppError::InternalServerError("Failed to upload media".into());
+        let info = probe_video(&content_type, &data)?;
+
+        let key = media_key(sha256, &content_type);
+        match staged {
+            Some(staged) => self.storage.copy(staged, &key).await.map_err(upload_failed)?,
+            None => self.storage.upload(&key, &data, &content_type).await.map_err(upload_failed)?,
+        }
+
+        let mut thumbnail_url = None;
+        let mut poster_url = None;
+        if let Some(frame) = extract_poster(&data, &info).await {
+            let frame = tokio::task::spawn_blocking(move || process_image("image/png", &frame)).await;
+            match frame {
+                Ok(Ok(frame)) => {
+                    let thumbnail_key = format!("media/thumb/{}.webp", sha256);
+                    let poster_key = format!("media/poster/{}.webp", sha256);
+                    self.storage.upload(&thumbnail_key, &frame.thumbnail, "image/webp").await.map_err(upload_failed)?;
+                    self.storage.upload(&poster_key, &frame.preview, "image/webp").await.map_err(upload_failed)?;
+                    thumbnail_url = Some(self.storage.public_url(&thumbnail_key));
+                    poster_url = Some(self.storage.public_url(&poster_key));
+                }
+                _ => log::warn!("Failed to process the poster frame of {}", key),
+            }
+        }
+
+        self.index.insert(&MediaObject {
+            sha256: sha256.to_string(),
+            url: self.storage.public_url(&key),
+            thumbnail_url,
+            preview_url: None,
+            poster_url,
+            content_type,
+            size_bytes: data.len() as u64,
+            width: Some(info.width),
+            height: Some(info.height),
+            duration_secs: info.duration_secs,
+            refs: 1,
+            created_at: Utc::now(),
+        }).await
+    }
+
+    // a failed upload is of no use to anyone, the client starts over with a new URL
+    async fn discard(&self, upload: &PendingUpload) {
+        if let Err(e) = self.storage.delete(&upload.key).await {
+            log::warn!("Failed to delete rejected upload {}: {:?}", upload.key, e);
+        }
+        if let Err(e) = self.uploads.delete(&upload.id.to_string()).await {
+            log::warn!("Failed to drop rejected upload {}: {:?}", upload.id, e);
+        }
+    }
+}
+
+fn media_key(sha256: &str, content_type: &str) -> String {
+    format!("media/{}.{}", sha256, extension_for(content_type).unwrap_or("bin"))
+}
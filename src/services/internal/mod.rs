@@ -1,33 +1,121 @@
+use std::sync::Arc;
 use crate::database::mongo::InkvaultDB;
 use crate::database::repos::announcement_repository::AnnouncementRepository;
 use crate::redis::InkvaultCache;
 use crate::services::internal::announcement_service::AnnouncementService;
+use crate::services::internal::bookmark_service::BookmarkService;
+use crate::services::internal::character_service::CharacterService;
+use crate::services::internal::collection_service::CollectionService;
+use crate::services::internal::comment_service::CommentService;
+use crate::services::internal::media_service::MediaService;
+use crate::services::internal::notification_service::NotificationService;
 use crate::services::internal::post_service::PostService;
 use crate::services::internal::profile_service::ProfileService;
+use crate::services::internal::search_service::SearchService;
+use crate::services::internal::tag_service::TagService;
+use crate::services::storage::Storage;
 use crate::utils::error::AppError;
 
 mod profile_service;
 mod post_service;
 mod announcement_service;
+mod character_service;
+pub mod search_service;
+pub mod tag_service;
+pub mod collection_service;
+pub mod bookmark_service;
+pub mod media_service;
+pub mod comment_service;
+pub mod notification_service;
 
 #[derive(Clone)]
 pub struct InternalServices {
     pub profile_service: ProfileService,
     pub post_service: PostService,
     pub announcement_service: AnnouncementService,
+    pub character_service: CharacterService,
+    pub search_service: SearchService,
+    pub tag_service: TagService,
+    pub collection_service: CollectionService,
+    pub bookmark_service: BookmarkService,
+    pub media_service: MediaService,
+    pub comment_service: CommentService,
+    pub notification_service: NotificationService,
 }
 
 impl InternalServices {
-    pub async fn new(db: InkvaultDB, cache: InkvaultCache) -> Result<Self, AppError> {
+    pub async fn new(db: InkvaultDB, cache: InkvaultCache, storage: Arc<dyn Storage>) -> Result<Self, AppError> {
         let announcement_service = AnnouncementService::new(db.announcements);
         announcement_service
             .load_cache()
             .await
             .expect("Failed to load announcement cache");
+
+        let profile_service = ProfileService::new(db.profiles, cache.profile_cache);
+        let search_service = SearchService::new(db.posts.clone(), profile_service.clone());
+        search_service
+            .ensure_indexes()
+            .await
+            .expect("Failed to build search indexes");
+
+        let post_service = PostService::new(db.posts.clone(), db.revisions, cache.post_cache);
+        post_service
+            .ensure_indexes()
+            .await
+            .expect("Failed to build revision indexes");
+        let character_service = CharacterService::new(db.characters.clone(), cache.character_cache);
+        let tag_service = TagService::new(db.tags, post_service.clone(), character_service.clone());
+        let collection_service = CollectionService::new(db.collections, post_service.clone());
+        collection_service
+            .ensure_indexes()
+            .await
+            .expect("Failed to build collection indexes");
+        let bookmark_service = BookmarkService::new(db.bookmarks, post_service.clone(), character_service.clone());
+        bookmark_service
+            .ensure_indexes()
+            .await
+            .expect("Failed to build bookmark indexes");
+        let media_service = MediaService::new(
+            storage,
+            db.pending_uploads,
+            db.media_index,
+            db.hash_bans,
+            db.posts,
+            db.characters,
+            post_service.clone()
+        );
+        media_service
+            .ensure_indexes()
+            .await
+            .expect("Failed to build pending upload indexes");
+        let notification_service = NotificationService::new(db.notifications, db.settings, profile_service.clone());
+        notification_service
+            .ensure_indexes()
+            .await
+            .expect("Failed to build notification indexes");
+        let comment_service = CommentService::new(
+            db.comments,
+            post_service.clone(),
+            profile_service.clone(),
+            notification_service.clone()
+        );
+        comment_service
+            .ensure_indexes()
+            .await
+            .expect("Failed to build comment indexes");
+
         Ok(Self {
-            profile_service: ProfileService::new(db.profiles, cache.profile_cache),
-            post_service: PostService::new(db.posts, cache.post_cache),
-            announcement_service
+            profile_service,
+            post_service,
+            announcement_service,
+            character_service,
+            search_service,
+            tag_service,
+            collection_service,
+            bookmark_service,
+            media_service,
+            comment_service,
+            notification_service,
         })
     }
 }
\ No newline at end of file
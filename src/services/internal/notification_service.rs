@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+
+use uuid::Uuid;
+
+use crate::database::repos::notification_repo::NotificationRepository;
+use crate::database::repos::settings_repo::SettingsRepository;
+use crate::models::comment::Comment;
+use crate::models::notification::{ Notification, NotificationKind, NotificationListResponse, NotificationResponse };
+use crate::models::post::Post;
+use crate::models::profile::ProfileSummary;
+use crate::models::report::Report;
+use crate::services::internal::profile_service::ProfileService;
+use crate::utils::cursor::FeedCursor;
+use crate::utils::error::AppError;
+
+// how many of a group's actors come back with each notification
+const SHOWN_ACTORS: usize = 3;
+// comment notifications carry this much of the comment
+const PREVIEW_CHARS: usize = 100;
+
+#[derive(Clone)]
+pub struct NotificationService {
+    repo: NotificationRepository,
+    settings: SettingsRepository,
+    profiles: ProfileService,
+}
+
+impl NotificationService {
+    pub fn new(repo: NotificationRepository, settings: SettingsRepository, profiles: ProfileService) -> Self {
+        Self { repo, settings, profiles }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        self.repo.ensure_indexes().await
+    }
+
+    pub async fn followed(&self, user_id: &str, follower_id: &str) {
+        let notification = Notification::new(
+            user_id.to_string(),
+            NotificationKind::Follow,
+            Some(follower_id.to_string()),
+            user_id.to_string()
+        );
+        self.notify(notification).await;
+    }
+
+    pub async fn post_liked(&self, post: &Post, user_id: &str) {
+        let notification = Notification::new(
+            post.author_id.to_string(),
+            NotificationKind::PostLike,
+            Some(user_id.to_string()),
+            post.id.to_string()
+        );
+        self.notify(notification).await;
+    }
+
+    pub async fn comment_liked(&self, comment: &Comment, user_id: &str) {
+        let Some(author_id) = comment.author_id.clone() else {
+            return;
+        };
+        let mut notification = Notification::new(
+            author_id,
+            NotificationKind::CommentLike,
+            Some(user_id.to_string()),
+            comment.id.to_string()
+        );
+        notification.post_id = Some(comment.post_id.to_string());
+        self.notify(notification).await;
+    }
+
+    /// Tells the post's author about a new comment, or the parent's author about a reply,
+    /// then everyone mentioned in it. Nobody hears about the same comment twice, `skip` has
+    /// already been told
+    pub async fn comment_posted(&self, post: &Post, parent: Option<&Comment>, comment: &Comment, skip: Option<&str>) {
+        let Some(author_id) = comment.author_id.as_deref() else {
+            return;
+        };
+        let mut told: HashSet<&str> = HashSet::from([author_id]);
+        told.extend(skip);
+
+        let post_author = post.author_id.to_string();
+        let (recipient, kind) = match parent {
+            Some(parent) => (parent.author_id.as_deref(), NotificationKind::Reply),
+            None => (Some(post_author.as_str()), NotificationKind::Comment),
+        };
+        if let Some(recipient) = recipient.filter(|r| told.insert(r)) {
+            self.notify(comment_notification(recipient, kind, comment)).await;
+        }
+
+        for mention in &comment.mentions {
+            if told.insert(&mention.user_id) {
+                self.notify(comment_notification(&mention.user_id, NotificationKind::Mention, comment)).await;
+            }
+        }
+    }
+
+    /// Lets the post's author know a comment is waiting for their approval
+    pub async fn comment_held(&self, post: &Post, comment: &Comment) {
+        let post_author = post.author_id.to_string();
+        if comment.author_id.as_deref() != Some(post_author.as_str()) {
+            self.notify(comment_notification(&post_author, NotificationKind::Comment, comment)).await;
+        }
+    }
+
+    pub async fn report_resolved(&self, report: &Report) {
+        let notification = Notification::new(
+            report.creator_id.to_string(),
+            NotificationKind::ReportResolved,
+            None,
+            report.report_id.to_string()
+        );
+        self.notify(notification).await;
+    }
+
+    // notifications never fail whatever caused them, problems are only logged. users don't
+    // hear about their own actions or about kinds they turned off
+    async fn notify(&self, notification: Notification) {
+        if notification.actor_ids.contains(&notification.user_id) {
+            return;
+        }
+        if !self.wants(&notification.user_id, notification.kind).await {
+            return;
+        }
+
+        let result = if notification.kind.is_grouped() {
+            self.repo.group(&notification).await
+        } else {
+            self.repo.create(&notification).await
+        };
+        if let Err(e) = result {
+            log::warn!("Failed to notify {} of {}: {:?}", notification.user_id, notification.kind.as_str(), e);
+        }
+    }
+
+    async fn wants(&self, user_id: &str, kind: NotificationKind) -> bool {
+        let Ok(uuid) = Uuid::parse_str(user_id) else {
+            return false;
+        };
+        // users without settings get the defaults
+        match self.settings.get_by_uuid(&uuid).await {
+            Ok(settings) => settings.notifications.unwrap_or_default().allows(kind),
+            Err(_) => true,
+        }
+    }
+
+    /// One page of the user's notifications with the latest actors of each, plus the next cursor
+    pub async fn list(
+        &self,
+        user_id: &str,
+        unread_only: bool,
+        after: Option<&FeedCursor>,
+        limit: u64
+    ) -> Result<NotificationListResponse, AppError> {
+        let notifications = self.repo.list(user_id, unread_only, after, limit).await?;
+
+        let actor_ids: Vec<String> = notifications
+            .iter()
+            .flat_map(|n| n.actor_ids.iter().rev().take(SHOWN_ACTORS).cloned())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let profiles = if actor_ids.is_empty() {
+            Vec::new()
+        } else {
+            self.profiles.get_many(Vec::new(), actor_ids).await?
+        };
+
+        let next_cursor = match notifications.last() {
+            Some(last) if (notifications.len() as u64) >= limit =>
+                Some(FeedCursor::Time { k: last.created_at.timestamp_millis(), id: last.id.to_string() }.encode()),
+            _ => None,
+        };
+
+        let notifications = notifications
+            .into_iter()
+            .map(|n| NotificationResponse {
+                id: n.id,
+                kind: n.kind,
+                actors: n.actor_ids
+                    .iter()
+                    .rev()
+                    .take(SHOWN_ACTORS)
+                    .filter_map(|id| profiles.iter().find(|p| p.id.to_string() == *id))
+                    .map(ProfileSummary::from)
+                    .collect(),
+                target_id: n.target_id,
+                post_id: n.post_id,
+                actor_count: n.actor_count,
+                preview: n.preview,
+                read: n.read,
+                created_at: n.created_at,
+            })
+            .collect();
+
+        Ok(NotificationListResponse {
+            notifications,
+            next_cursor,
+            unread_count: self.repo.unread_count(user_id).await?,
+        })
+    }
+
+    pub async fn unread_count(&self, user_id: &str) -> Result<u64, AppError> {
+        self.repo.unread_count(user_id).await
+    }
+
+    /// Marks the given notifications read, or every one without ids. Returns how many changed
+    pub async fn mark_read(&self, user_id: &str, ids: Option<&[String]>) -> Result<u64, AppError> {
+        self.repo.mark_read(user_id, ids).await
+    }
+}
+
+fn comment_notification(recipient: &str, kind: NotificationKind, comment: &Comment) -> Notification {
+    let mut notification = Notification::new(
+        recipient.to_string(),
+        kind,
+        comment.author_id.clone(),
+        comment.id.to_string()
+    );
+    notification.post_id = Some(comment.post_id.to_string());
+    notification.preview = Some(comment.content.chars().take(PREVIEW_CHARS).collect());
+    notification
+}
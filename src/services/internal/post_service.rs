@@ -1,20 +1,32 @@
 use std::sync::Arc;
+use chrono::Utc;
 use tokio::sync::Mutex;
+use uuid::Uuid;
 use crate::database::repos::post_repo::PostRepository;
-use crate::models::post::{AdminPatchPost, Post};
+use crate::database::repos::revision_repo::RevisionRepository;
+use crate::models::media::Media;
+use crate::models::post::{AdminPatchPost, CommentSettings, Post};
+use crate::models::reaction::Reaction;
+use crate::models::revision::{ Editor, PostRevision, PostSnapshot };
+use crate::models::search::FacetCount;
+use crate::models::viewer::Viewer;
 use crate::redis::cache::post_cache::PostCache;
+use crate::utils::cursor::FeedPage;
 use crate::utils::error::AppError;
+use crate::utils::post::PostStatus;
+use crate::utils::trending::PopularWindow;
 
 #[derive(Clone)]
 pub struct PostService {
     repo: PostRepository,
+    revisions: RevisionRepository,
     cache: PostCache,
     latest_head: Arc<Mutex<Vec<Post>>>, // i love rust ;3
 }
 
 impl PostService {
-    pub fn new(repo: PostRepository, cache: PostCache) -> Self {
-        Self { repo, cache, latest_head: Arc::new(Mutex::new(Vec::new())) }
+    pub fn new(repo: PostRepository, revisions: RevisionRepository, cache: PostCache) -> Self {
+        Self { repo, revisions, cache, latest_head: Arc::new(Mutex::new(Vec::new())) }
     }
 
     /// Gets a post by ID, checking Redis cache first.
@@ -30,40 +42,222 @@ impl PostService {
         Ok(post)
     }
 
+    /// gets posts by id, returned in the order the ids were given. missing ones are skipped.
+    pub async fn get_many(&self, ids: &[String]) -> Result<Vec<Post>, AppError> {
+        let posts = self.repo.get_many(ids).await?;
+        self.cache.set_many(&posts).await.ok();
+
+        Ok(
+            ids
+                .iter()
+                .filter_map(|id| posts.iter().find(|p| p.id.to_string() == *id).cloned())
+                .collect()
+        )
+    }
+
     /// creates a post (no need to cache right now)
     pub async fn create(&self, post: &Post) -> Result<(), AppError> {
         self.repo.create(post).await?;
         self.cache.set(&post).await.ok(); // cache it immediately
         
-        // update latest post cache
-        {
-            let mut latest = self.latest_head.lock().await;
-            latest.insert(0, post.clone());
-            if latest.len() > 100 {
-                latest.truncate(100);
-            }
+        // drafts and scheduled posts stay out of the latest cache until they go live
+        if post.is_published() {
+            self.push_latest(post).await;
         }
         
         Ok(())
     }
 
-    /// Saves a post and updates the cache
-    pub async fn save(&self, post: &Post) -> Result<(), AppError> {
-        self.repo.save(post).await?;
-        self.cache.set(&post).await.ok(); // update cache
+    /// puts a newly published post into the latest post cache, keeping it newest first
+    pub async fn push_latest(&self, post: &Post) {
+        let mut latest = self.latest_head.lock().await;
+        latest.retain(|p| p.id != post.id);
+        let pos = latest
+            .iter()
+            .position(|p| p.created_at < post.created_at)
+            .unwrap_or(latest.len());
+        latest.insert(pos, post.clone());
+        if latest.len() > 100 {
+            latest.truncate(100);
+        }
+    }
+
+    /// publishes every scheduled post whose time has come, returns how many went live
+    pub async fn publish_due(&self) -> Result<Vec<Post>, AppError> {
+        let now = Utc::now();
+        let mut published = Vec::new();
+
+        for due in self.repo.get_due(now).await? {
+            let published_at = due.publish_at.unwrap_or(now);
+            let Some(post) = self.repo.publish_scheduled(&due.id.to_string(), published_at).await? else {
+                continue; // changed by its author since we looked
+            };
+
+            self.cache.set(&post).await.ok();
+            self.push_latest(&post).await;
+            published.push(post);
+        }
+
+        Ok(published)
+    }
+
+    /// a post the viewer is allowed to see, others look like they don't exist
+    pub async fn get_visible(&self, id: &str, viewer: &Viewer) -> Result<Post, AppError> {
+        let post = self.get_by_id(id).await?;
+        if !post.visible_to(viewer) {
+            return Err(AppError::PostNotFound);
+        }
+        Ok(post)
+    }
+
+    /// an author's drafts and scheduled posts, no caching
+    pub async fn get_drafts(&self, author_id: &str, limit: u64, page: &FeedPage) -> Result<Vec<Post>, AppError> {
+        self.repo.get_unpublished_by_author(author_id, limit, page).await
+    }
+
+    /// Saves a post and updates the cache. `post` is refreshed with what's stored, media and
+    /// settings changed in place elsewhere included
+    pub async fn save(&self, post: &mut Post) -> Result<(), AppError> {
+        *post = self.repo.save(post).await?.ok_or(AppError::PostNotFound)?;
+        self.cache.set(post).await.ok(); // update cache
+        Ok(())
+    }
+
+    /// Writes the publish state of an edited draft or scheduled post that was `from` before
+    pub async fn set_publish_state(&self, post: &Post, from: PostStatus) -> Result<(), AppError> {
+        let stored = self.repo
+            .set_publish_state(post, from).await?
+            .ok_or(AppError::BadRequest("The post was published in the meantime".into()))?;
+        self.cache.set(&stored).await.ok();
         Ok(())
     }
 
+    /// Saves an edit made by `editor`. When the title, body, tags or nsfw flag changed the
+    /// old values are kept as a revision and `edited_at` is bumped.
+    pub async fn save_edit(
+        &self,
+        before: &PostSnapshot,
+        post: &mut Post,
+        editor: &Editor,
+        restored_from: Option<String>
+    ) -> Result<Option<PostRevision>, AppError> {
+        let changes = before.diff(&PostSnapshot::from(&*post));
+        if changes.is_empty() {
+            self.save(post).await?;
+            return Ok(None);
+        }
+
+        let now = Utc::now();
+        post.edited_at = Some(now);
+        self.save(post).await?;
+
+        let revision = PostRevision {
+            id: Uuid::new_v4(),
+            post_id: post.id,
+            number: self.repo.next_revision_number(&post.id.to_string()).await?,
+            editor_id: editor.id,
+            editor: editor.username.clone(),
+            changes,
+            previous: before.clone(),
+            restored_from,
+            created_at: now,
+        };
+        self.revisions.create(&revision).await?;
+
+        // keep the latest cache showing the edited version
+        let mut latest = self.latest_head.lock().await;
+        if let Some(cached) = latest.iter_mut().find(|p| p.id == post.id) {
+            *cached = post.clone();
+        }
+
+        Ok(Some(revision))
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        self.revisions.ensure_indexes().await
+    }
+
+    /// A post's edit history, newest first
+    pub async fn get_revisions(&self, post_id: &str, skip: u64, limit: u64) -> Result<Vec<PostRevision>, AppError> {
+        self.revisions.get_by_post(post_id, skip, limit).await
+    }
+
+    /// Puts a post back to how it was before `revision_id`, recorded as a new revision.
+    /// Returns the restored post and the tags it had before, for usage counts.
+    pub async fn restore_revision(
+        &self,
+        post_id: &str,
+        revision_id: &str,
+        editor: &Editor
+    ) -> Result<(Post, Vec<String>), AppError> {
+        let revision = self.revisions
+            .get(post_id, revision_id).await?
+            .ok_or(AppError::RevisionNotFound)?;
+
+        let mut post = self.get_by_id(post_id).await?;
+        let before = PostSnapshot::from(&post);
+        revision.previous.apply_to(&mut post);
+
+        self.save_edit(&before, &mut post, editor, Some(revision_id.to_string())).await?;
+        Ok((post, before.tags))
+    }
+
     pub async fn patch(&self, id: &str, patch: AdminPatchPost) -> Result<Post, AppError> {
         let mut post = self.get_by_id(id).await?;
 
         post.apply_patch(patch);
 
-        self.save(&post).await?;
+        self.save(&mut post).await?;
 
         Ok(post)
     }
 
+    /// Toggles a user's like/dislike atomically, then refreshes the cached copies of the post.
+    /// Returns whether the reaction is now set.
+    pub async fn react(&self, id: &str, user_id: &str, reaction: Reaction) -> Result<(bool, Post), AppError> {
+        let (set, post) = self.repo.toggle_reaction(id, user_id, reaction).await?;
+        self.cache.set(&post).await.ok();
+
+        let mut latest = self.latest_head.lock().await;
+        if let Some(cached) = latest.iter_mut().find(|p| p.id == post.id) {
+            *cached = post.clone();
+        }
+
+        Ok((set, post))
+    }
+
+    /// ids of the users that reacted to a post with `reaction`, oldest first
+    pub async fn get_reactors(&self, id: &str, reaction: Reaction, skip: u64, limit: u64) -> Result<Vec<String>, AppError> {
+        self.repo.get_reactors(id, reaction, skip, limit).await
+    }
+
+    /// attaches one more piece of media to a post and returns the updated post
+    pub async fn add_media(&self, id: &str, media: &Media) -> Result<Post, AppError> {
+        let post = self.repo.push_media(id, media).await?.ok_or(AppError::PostNotFound)?;
+        self.cache.set(&post).await.ok();
+        Ok(post)
+    }
+
+    /// replaces who may comment on a post and how, returning the updated post
+    pub async fn set_comment_settings(&self, id: &str, settings: &CommentSettings) -> Result<Post, AppError> {
+        let post = self.repo.set_comment_settings(id, settings).await?.ok_or(AppError::PostNotFound)?;
+        self.cache.set(&post).await.ok();
+        Ok(post)
+    }
+
+    /// takes a comment that is going away off the top of its post
+    pub async fn unpin_comment(&self, id: &str, comment_id: &str) -> Result<(), AppError> {
+        if let Some(post) = self.repo.clear_pinned_comment(id, comment_id).await? {
+            self.cache.set(&post).await.ok();
+        }
+        Ok(())
+    }
+
+    /// counts a view straight in Mongo, the cached copy keeps its old count until it expires
+    pub async fn add_view(&self, id: &str) -> Result<(), AppError> {
+        self.repo.add_view(id).await
+    }
+
     /// invalidates a post by ID
     pub async fn invalidate(&self, id: &str) -> Result<(), AppError> {
         self.cache.invalidate(id).await
@@ -71,6 +265,7 @@ impl PostService {
     
     pub async fn delete(&self, id: &str) -> Result<(), AppError> {
         self.repo.delete(id).await?;
+        self.revisions.delete_by_post(id).await?;
         self.cache.invalidate(id).await?;
         
         // also remove from latest head cache
@@ -82,27 +277,41 @@ impl PostService {
         Ok(())
     }
 
-    /// Fetches latest posts
-    pub async fn get_latest(&self, limit: u64, skip: u64) -> Result<Vec<Post>, AppError> {
+    /// Fetches latest posts, from the in-memory head when the page fits inside it.
+    /// NSFW posts are left out unless the viewer opted in.
+    pub async fn get_latest(&self, limit: u64, page: &FeedPage, viewer: &Viewer) -> Result<Vec<Post>, AppError> {
         const CACHE_LIMIT: usize = 100;
 
-        if skip + limit <= CACHE_LIMIT as u64 {
-            let head = self.latest_head.lock().await;
-            let start = skip as usize;
-            let end = (skip + limit) as usize;
-            if head.len() >= end {
-                log::debug!("Serving from in-memory cache");
-                return Ok(head[start..end].to_vec());
+        {
+            let full_head = self.latest_head.lock().await;
+            // the sfw posts of the head are still the start of the sfw feed, so they can be paged the same way
+            let head: Vec<&Post> = full_head
+                .iter()
+                .filter(|p| viewer.show_nsfw || !p.nsfw)
+                .collect();
+            let start = match page {
+                FeedPage::Offset(skip) => Some(*skip as usize),
+                FeedPage::After(cursor) => head
+                    .iter()
+                    .position(|p| p.id.to_string() == cursor.id())
+                    .map(|pos| pos + 1),
+            };
+            if let Some(start) = start {
+                let end = start + limit as usize;
+                if end <= CACHE_LIMIT && head.len() >= end {
+                    log::debug!("Serving from in-memory cache");
+                    return Ok(head[start..end].iter().map(|p| (*p).clone()).collect());
+                }
             }
         }
 
         // fallback to DB
-        let posts = self.repo.get_latest(limit, skip).await?;
+        let posts = self.repo.get_latest(limit, page, viewer.show_nsfw).await?;
 
-        // set last 100 cache
-        if skip == 0 {
+        // set last 100 cache, always with everything in it
+        if matches!(page, FeedPage::Offset(0)) {
             let mut head = self.latest_head.lock().await;
-            *head = self.repo.get_latest(CACHE_LIMIT as u64, 0).await?;
+            *head = self.repo.get_latest(CACHE_LIMIT as u64, &FeedPage::Offset(0), true).await?;
         }
 
         self.cache.set_many(&posts).await.ok();
@@ -110,12 +319,115 @@ impl PostService {
     }
 
     /// gets posts by author, with optional tags, no caching
-    pub async fn get_all_by_user(&self, username: &str, limit: u64, skip: u64, tags: Option<Vec<String>>) -> Result<Vec<Post>, AppError> {
-        let posts = self.repo.get_all_by_user(username, limit, skip, tags).await?;
+    pub async fn get_all_by_user(&self, username: &str, limit: u64, page: &FeedPage, tags: Option<Vec<String>>, viewer: &Viewer) -> Result<Vec<Post>, AppError> {
+        let posts = self.repo.get_all_by_user(username, limit, page, tags, viewer.show_nsfw).await?;
         self.cache.set_many(&posts).await.ok();
         Ok(posts)
     }
     
+    /// Posts from the authors a user follows, newest first.
+    ///
+    /// The first `FEED_HEAD_LIMIT` posts of each user's feed are kept in Redis and pages
+    /// inside that head are served from it.
+    pub async fn get_following(
+        &self,
+        user_id: &str,
+        following: Vec<String>,
+        include_nsfw: bool,
+        limit: u64,
+        page: &FeedPage
+    ) -> Result<Vec<Post>, AppError> {
+        const FEED_HEAD_LIMIT: usize = 100;
+
+        if following.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let key = format!("{user_id}:{}", if include_nsfw { "nsfw" } else { "sfw" });
+        let head = match self.cache.get_feed_head(&key).await.ok().flatten() {
+            Some(head) => head,
+            None => {
+                let head = self.repo
+                    .get_by_authors(following.clone(), include_nsfw, FEED_HEAD_LIMIT as u64, &FeedPage::Offset(0))
+                    .await?;
+                self.cache.set_feed_head(&key, &head).await.ok();
+                head
+            }
+        };
+
+        let start = match page {
+            FeedPage::Offset(skip) => Some(*skip as usize),
+            FeedPage::After(cursor) => head
+                .iter()
+                .position(|p| p.id.to_string() == cursor.id())
+                .map(|pos| pos + 1),
+        };
+        if let Some(start) = start {
+            let end = start + limit as usize;
+            // a head shorter than its limit already holds the whole feed
+            if head.len() >= end || head.len() < FEED_HEAD_LIMIT {
+                let end = end.min(head.len());
+                return Ok(head.get(start..end).unwrap_or_default().to_vec());
+            }
+        }
+
+        let posts = self.repo.get_by_authors(following, include_nsfw, limit, page).await?;
+        self.cache.set_many(&posts).await.ok();
+        Ok(posts)
+    }
+
+    /// drops a user's cached following feed, used when who they follow changes
+    pub async fn invalidate_following(&self, user_id: &str) -> Result<(), AppError> {
+        self.cache.invalidate_feed_head(user_id).await
+    }
+
+    /// gets posts featuring a character, newest first
+    pub async fn get_all_by_character(&self, character_id: &str, limit: u64, page: &FeedPage, viewer: &Viewer) -> Result<Vec<Post>, AppError> {
+        let posts = self.repo.get_all_by_character(character_id, limit, page, viewer.show_nsfw).await?;
+        self.cache.set_many(&posts).await.ok();
+        Ok(posts)
+    }
+
+    /// drops a deleted character from every post, dropping cached copies and the in-memory head
+    pub async fn remove_character_ref(&self, character_id: &str) -> Result<(), AppError> {
+        let ids = self.repo.remove_character_ref(character_id).await?;
+        self.forget_many(&ids).await;
+        Ok(())
+    }
+
+    /// swaps one tag for another on every post, dropping cached copies and the in-memory head
+    pub async fn retag(&self, from: &str, to: &str) -> Result<(), AppError> {
+        let ids = self.repo.retag(from, to).await?;
+        self.forget_many(&ids).await;
+        Ok(())
+    }
+
+    /// adds an implied tag to every post carrying `tag`
+    pub async fn add_implied_tag(&self, tag: &str, implied: &str) -> Result<(), AppError> {
+        let ids = self.repo.add_implied_tag(tag, implied).await?;
+        self.forget_many(&ids).await;
+        Ok(())
+    }
+
+    pub async fn count_tagged(&self, tag: &str) -> Result<u64, AppError> {
+        self.repo.count_tagged(tag).await
+    }
+
+    pub async fn related_tags(&self, tag: &str, limit: u64) -> Result<Vec<FacetCount>, AppError> {
+        self.repo.related_tags(tag, limit).await
+    }
+
+    // drops posts changed behind the cache's back
+    async fn forget_many(&self, ids: &[String]) {
+        if ids.is_empty() {
+            return;
+        }
+        for id in ids {
+            self.cache.invalidate(id).await.ok();
+        }
+        self.latest_head.lock().await.clear();
+    }
+
     // Get all posts (no caching, used for admin panel)
     pub async fn get_all(&self) -> Result<Vec<Post>, AppError> {
         let posts = self.repo.get_all().await?;
@@ -136,20 +448,20 @@ impl PostService {
         Ok(post)
     }
     
-    pub async fn get_popular(&self, limit: u64, offset: u64, tags: Option<Vec<String>>) -> Result<Vec<Post>, AppError> {
-        let posts = self.repo.get_popular_posts(limit, offset, tags).await?;
+    pub async fn get_popular(&self, limit: u64, page: &FeedPage, window: PopularWindow, tags: Option<Vec<String>>, viewer: &Viewer) -> Result<Vec<Post>, AppError> {
+        let posts = self.repo.get_popular_posts(limit, page, window, tags, viewer.show_nsfw).await?;
         self.cache.set_many(&posts).await.ok();
         Ok(posts)
     }
 
-    pub async fn get_random(&self, limit: u64, tags: Option<Vec<String>>) -> Result<Vec<Post>, AppError> {
-        let posts = self.repo.get_random_posts(limit, tags).await?;
+    pub async fn get_random(&self, limit: u64, tags: Option<Vec<String>>, viewer: &Viewer) -> Result<Vec<Post>, AppError> {
+        let posts = self.repo.get_random_posts(limit, tags, viewer.show_nsfw).await?;
         self.cache.set_many(&posts).await.ok();
         Ok(posts)
     }
 
-    pub async fn get_premium(&self, limit: u64, offset: u64, tags: Option<Vec<String>>) -> Result<Vec<Post>, AppError> {
-        let posts = self.repo.get_premium_posts(limit, offset, tags).await?;
+    pub async fn get_premium(&self, limit: u64, page: &FeedPage, tags: Option<Vec<String>>, viewer: &Viewer) -> Result<Vec<Post>, AppError> {
+        let posts = self.repo.get_premium_posts(limit, page, tags, viewer.show_nsfw).await?;
         self.cache.set_many(&posts).await.ok();
         Ok(posts)
     }
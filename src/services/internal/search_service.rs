@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use bson::{ doc, Document };
+
+use crate::database::repos::post_repo::PostRepository;
+use crate::models::post::Post;
+use crate::models::search::{ SearchFacets, SearchHighlight };
+use crate::models::viewer::Viewer;
+use crate::services::internal::profile_service::ProfileService;
+use crate::utils::cursor::{ FeedCursor, FeedPage, FeedSort };
+use crate::utils::error::AppError;
+use crate::utils::search::{ highlight, ParsedQuery };
+
+// characters of context kept either side of a highlighted body match
+const SNIPPET_RADIUS: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchSort {
+    Relevance,
+    Feed(FeedSort),
+}
+
+pub struct SearchResults {
+    pub posts: Vec<Post>,
+    pub total: u64,
+    pub facets: SearchFacets,
+    pub highlights: HashMap<String, SearchHighlight>,
+    pub used_regex: bool,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct SearchService {
+    posts: PostRepository,
+    profiles: ProfileService,
+}
+
+impl SearchService {
+    pub fn new(posts: PostRepository, profiles: ProfileService) -> Self {
+        Self { posts, profiles }
+    }
+
+    /// Builds the post indexes search needs, run at startup
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        self.posts.ensure_search_indexes().await
+    }
+
+    /// Runs a parsed search. Falls back to an escaped regex when the text index finds nothing
+    /// (e.g. a partial word), the total and facets always describe the filter actually used.
+    pub async fn search(
+        &self,
+        query: &ParsedQuery,
+        any_tags: Vec<String>,
+        sort: SearchSort,
+        page: &FeedPage,
+        limit: u64,
+        viewer: &Viewer
+    ) -> Result<SearchResults, AppError> {
+        let Some(base) = self.base_filter(query, any_tags, viewer).await? else {
+            // asked for an author that does not exist
+            return Ok(SearchResults {
+                posts: Vec::new(),
+                total: 0,
+                facets: SearchFacets::default(),
+                highlights: HashMap::new(),
+                used_regex: false,
+                next_cursor: None,
+            });
+        };
+
+        let mut used_regex = false;
+        let mut filter = with_text(base.clone(), query, false);
+        let (mut total, mut facets) = self.posts.search_facets(filter.clone()).await?;
+
+        if total == 0 && query.has_text() {
+            used_regex = true;
+            filter = with_text(base, query, true);
+            (total, facets) = self.posts.search_facets(filter.clone()).await?;
+        }
+
+        let (posts, next_cursor) = match sort {
+            SearchSort::Relevance if query.has_text() && !used_regex => {
+                let skip = match page {
+                    FeedPage::Offset(skip) => *skip,
+                    FeedPage::After(_) => {
+                        return Err(AppError::BadRequest("Relevance sorted results are paged with `page`".into()));
+                    }
+                };
+                (self.posts.search_by_relevance(filter, skip, limit).await?, None)
+            }
+            // nothing to score on, newest is the next best thing
+            SearchSort::Relevance => {
+                let posts = self.posts.search(filter, FeedSort::Newest, page, limit).await?;
+                let next = FeedCursor::next_page(FeedSort::Newest, &posts, limit);
+                (posts, next)
+            }
+            SearchSort::Feed(sort) => {
+                let posts = self.posts.search(filter, sort, page, limit).await?;
+                let next = FeedCursor::next_page(sort, &posts, limit);
+                (posts, next)
+            }
+        };
+
+        let mut highlights = HashMap::new();
+        if let Some(matcher) = query.highlighter() {
+            for post in &posts {
+                let title = highlight(&post.title, &matcher, post.title.chars().count());
+                let body = post.body.as_deref().and_then(|body| highlight(body, &matcher, SNIPPET_RADIUS));
+                if title.is_some() || body.is_some() {
+                    highlights.insert(post.id.to_string(), SearchHighlight { title, body });
+                }
+            }
+        }
+
+        Ok(SearchResults { posts, total, facets, highlights, used_regex, next_cursor })
+    }
+
+    /// Everything but the free text part of the filter. `None` when an `author:` matched nobody.
+    async fn base_filter(
+        &self,
+        query: &ParsedQuery,
+        any_tags: Vec<String>,
+        viewer: &Viewer
+    ) -> Result<Option<Document>, AppError> {
+        let mut filter = doc! { "status": "published" };
+        let mut and: Vec<Document> = Vec::new();
+
+        if !query.include_tags.is_empty() {
+            and.push(doc! { "tags": { "$all": &query.include_tags } });
+        }
+        if !any_tags.is_empty() {
+            and.push(doc! { "tags": { "$in": any_tags } });
+        }
+        if !query.exclude_tags.is_empty() {
+            and.push(doc! { "tags": { "$nin": &query.exclude_tags } });
+        }
+
+        if !query.authors.is_empty() {
+            let profiles = self.profiles.get_many(query.authors.clone(), vec![]).await?;
+            if profiles.is_empty() {
+                return Ok(None);
+            }
+            let ids: Vec<String> = profiles
+                .iter()
+                .map(|p| p.id.to_string())
+                .collect();
+            filter.insert("author_id", doc! { "$in": ids });
+        }
+
+        if !query.post_types.is_empty() {
+            filter.insert("post_type", doc! { "$in": &query.post_types });
+        }
+
+        for prefix in &query.prefixes {
+            let escaped = regex::escape(prefix);
+            and.push(
+                doc! {
+                "$or": [
+                    { "title": { "$regex": format!(r"\b{}", escaped), "$options": "i" } },
+                    { "body": { "$regex": format!(r"\b{}", escaped), "$options": "i" } },
+                    { "tags": { "$regex": format!("^{}", escaped), "$options": "i" } }
+                ]
+            }
+            );
+        }
+
+        if !viewer.show_nsfw {
+            filter.insert("nsfw", false);
+        }
+
+        if !and.is_empty() {
+            filter.insert("$and", and);
+        }
+
+        Ok(Some(filter))
+    }
+}
+
+/// Adds the free text part of a search, either as a `$text` query or as escaped regexes that
+/// every term and phrase has to match in the title or body
+fn with_text(mut filter: Document, query: &ParsedQuery, use_regex: bool) -> Document {
+    if !query.has_text() {
+        return filter;
+    }
+
+    if !use_regex {
+        filter.insert("$text", doc! { "$search": query.text_search() });
+        return filter;
+    }
+
+    let mut and: Vec<Document> = filter
+        .remove("$and")
+        .and_then(|existing| bson::from_bson(existing).ok())
+        .unwrap_or_default();
+
+    for needle in query.terms.iter().chain(query.phrases.iter()) {
+        let escaped = regex::escape(needle);
+        and.push(
+            doc! {
+            "$or": [
+                { "title": { "$regex": &escaped, "$options": "i" } },
+                { "body": { "$regex": &escaped, "$options": "i" } }
+            ]
+        }
+        );
+    }
+
+    filter.insert("$and", and);
+    filter
+}
@@ -0,0 +1,258 @@
+use std::collections::{ HashMap, HashSet };
+
+use crate::database::repos::tag_repo::TagRepository;
+use crate::models::tag::{ Tag, TagKind, TagPageResponse, TagSuggestion };
+use crate::services::internal::character_service::CharacterService;
+use crate::services::internal::post_service::PostService;
+use crate::utils::error::AppError;
+use crate::utils::tags::{ normalize_tag, normalize_tags };
+
+// most tags a user can put on one post or character, implied tags don't count
+pub const MAX_TAGS: usize = 30;
+// how many hops of implications are followed when tagging
+const MAX_IMPLICATION_DEPTH: usize = 5;
+const MAX_DESCRIPTION_LEN: usize = 2000;
+const RELATED_TAGS: u64 = 10;
+
+#[derive(Clone)]
+pub struct TagService {
+    repo: TagRepository,
+    posts: PostService,
+    characters: CharacterService,
+}
+
+impl TagService {
+    pub fn new(repo: TagRepository, posts: PostService, characters: CharacterService) -> Self {
+        Self { repo, posts, characters }
+    }
+
+    /// Normalizes user supplied tags, swaps aliases for their canonical tag and adds
+    /// everything the tags imply
+    pub async fn resolve(&self, raw: &[String]) -> Result<Vec<String>, AppError> {
+        let mut pending = normalize_tags(raw);
+        if pending.len() > MAX_TAGS {
+            return Err(AppError::BadRequest(format!("A maximum of {} tags is allowed", MAX_TAGS)));
+        }
+
+        let mut resolved: Vec<String> = Vec::new();
+        for _ in 0..MAX_IMPLICATION_DEPTH {
+            if pending.is_empty() {
+                break;
+            }
+
+            let known = self.get_map(&pending).await?;
+            let mut added = Vec::new();
+            for name in pending {
+                let canonical = known
+                    .get(&name)
+                    .and_then(|t| t.alias_of.clone())
+                    .unwrap_or(name);
+                if !resolved.contains(&canonical) {
+                    resolved.push(canonical.clone());
+                    added.push(canonical);
+                }
+            }
+
+            pending = self.repo
+                .get_many(&added).await?
+                .into_iter()
+                .flat_map(|t| t.implies)
+                .filter(|t| !resolved.contains(t))
+                .collect();
+        }
+
+        Ok(resolved)
+    }
+
+    /// Updates usage counts after tags on a post or character changed from `old` to `new`.
+    /// Runs after the write it counts, so failures are only logged instead of failing that write
+    pub async fn record_usage(&self, kind: TagKind, old: &[String], new: &[String]) {
+        let added: Vec<String> = new
+            .iter()
+            .filter(|t| !old.contains(t))
+            .cloned()
+            .collect();
+        let removed: Vec<String> = old
+            .iter()
+            .filter(|t| !new.contains(t))
+            .cloned()
+            .collect();
+
+        for (tags, delta) in [(added, 1), (removed, -1)] {
+            if tags.is_empty() {
+                continue;
+            }
+            if let Err(e) = self.repo.add_usage(&tags, kind, delta).await {
+                log::warn!("Failed to update usage of tags {:?} by {}: {:?}", tags, delta, e);
+            }
+        }
+    }
+
+    /// Tags starting with what the user typed so far, most used first
+    pub async fn autocomplete(&self, query: &str, limit: u64) -> Result<Vec<TagSuggestion>, AppError> {
+        let Some(prefix) = normalize_tag(query) else {
+            return Ok(Vec::new());
+        };
+
+        let tags = self.repo.autocomplete(&prefix, limit).await?;
+        Ok(tags.iter().map(TagSuggestion::from).collect())
+    }
+
+    /// Everything shown on a tag's page. Aliases show their canonical tag's page.
+    pub async fn get_page(&self, name: &str) -> Result<TagPageResponse, AppError> {
+        let tag = self.get_canonical(name).await?;
+        let aliases = self.repo.get_aliases(&tag.name).await?;
+        let related = self.posts.related_tags(&tag.name, RELATED_TAGS).await?;
+
+        Ok(TagPageResponse {
+            name: tag.name,
+            description: tag.description,
+            post_count: tag.post_count,
+            character_count: tag.character_count,
+            implies: tag.implies,
+            aliases,
+            related,
+        })
+    }
+
+    pub async fn set_description(&self, name: &str, description: Option<String>) -> Result<(), AppError> {
+        let tag = self.get_canonical(name).await?;
+        let description = description
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty());
+
+        if description.as_ref().is_some_and(|d| d.chars().count() > MAX_DESCRIPTION_LEN) {
+            return Err(
+                AppError::BadRequest(format!("Description can be at most {} characters", MAX_DESCRIPTION_LEN))
+            );
+        }
+
+        self.repo.set_description(&tag.name, description).await
+    }
+
+    /// Makes `name` another spelling of `target`. Everything tagged `name` is retagged, and
+    /// its aliases and implications move over to the target.
+    pub async fn add_alias(&self, name: &str, target: &str) -> Result<(), AppError> {
+        let name = normalize(name)?;
+        let target = normalize(target)?;
+        let canonical = match self.repo.get(&target).await? {
+            Some(tag) => tag.alias_of.unwrap_or(tag.name),
+            None => target,
+        };
+
+        if canonical == name {
+            return Err(AppError::BadRequest("A tag can't be an alias of itself".into()));
+        }
+
+        self.repo.ensure(&canonical).await?;
+        self.repo.set_alias(&name, Some(&canonical)).await?;
+        self.repo.repoint_aliases(&name, &canonical).await?;
+        self.repo.merge_implications(&name, &canonical).await?;
+        self.repo.replace_implication(&name, &canonical).await?;
+
+        self.posts.retag(&name, &canonical).await?;
+        self.characters.retag(&name, &canonical).await?;
+
+        self.recount(&name).await?;
+        self.recount(&canonical).await
+    }
+
+    /// Stops `name` being an alias, tags already rewritten stay as they are
+    pub async fn remove_alias(&self, name: &str) -> Result<(), AppError> {
+        let name = normalize(name)?;
+        self.repo.get(&name).await?.ok_or(AppError::TagNotFound)?;
+        self.repo.set_alias(&name, None).await
+    }
+
+    /// Makes `name` imply `implied` and applies it to everything already tagged `name`
+    pub async fn add_implication(&self, name: &str, implied: &str) -> Result<(), AppError> {
+        let tag = self.get_canonical(name).await?;
+        let implied = normalize(implied)?;
+        let implied = match self.repo.get(&implied).await? {
+            Some(t) => t.alias_of.unwrap_or(t.name),
+            None => implied,
+        };
+
+        if implied == tag.name {
+            return Err(AppError::BadRequest("A tag can't imply itself".into()));
+        }
+
+        let closure = self.implied_closure(&implied).await?;
+        if closure.contains(&tag.name) {
+            return Err(AppError::BadRequest(format!("`{}` already implies `{}`", implied, tag.name)));
+        }
+
+        self.repo.ensure(&implied).await?;
+        self.repo.add_implication(&tag.name, &implied).await?;
+
+        for added in &closure {
+            self.posts.add_implied_tag(&tag.name, added).await?;
+            self.characters.add_implied_tag(&tag.name, added).await?;
+            self.recount(added).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stops `name` implying `implied` for new tagging, existing tags are left alone
+    pub async fn remove_implication(&self, name: &str, implied: &str) -> Result<(), AppError> {
+        let tag = self.get_canonical(name).await?;
+        self.repo.remove_implication(&tag.name, &normalize(implied)?).await
+    }
+
+    /// `tag` plus every tag it implies, directly or not
+    async fn implied_closure(&self, tag: &str) -> Result<Vec<String>, AppError> {
+        let mut seen: Vec<String> = vec![tag.to_string()];
+        let mut pending = vec![tag.to_string()];
+
+        for _ in 0..MAX_IMPLICATION_DEPTH {
+            if pending.is_empty() {
+                break;
+            }
+            pending = self.repo
+                .get_many(&pending).await?
+                .into_iter()
+                .flat_map(|t| t.implies)
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .filter(|t| !seen.contains(t))
+                .collect();
+            seen.extend(pending.iter().cloned());
+        }
+
+        Ok(seen)
+    }
+
+    /// Resyncs a tag's counts with what is actually stored after a bulk rewrite
+    async fn recount(&self, name: &str) -> Result<(), AppError> {
+        let posts = self.posts.count_tagged(name).await?;
+        let characters = self.characters.count_tagged(name).await?;
+        self.repo.set_count(name, TagKind::Post, posts).await?;
+        self.repo.set_count(name, TagKind::Character, characters).await
+    }
+
+    /// Looks a tag up by any spelling, following an alias to its canonical tag
+    async fn get_canonical(&self, name: &str) -> Result<Tag, AppError> {
+        let name = normalize(name)?;
+        let tag = self.repo.get(&name).await?.ok_or(AppError::TagNotFound)?;
+
+        match tag.alias_of {
+            Some(ref canonical) => self.repo.get(canonical).await?.ok_or(AppError::TagNotFound),
+            None => Ok(tag),
+        }
+    }
+
+    async fn get_map(&self, names: &[String]) -> Result<HashMap<String, Tag>, AppError> {
+        Ok(
+            self.repo
+                .get_many(names).await?
+                .into_iter()
+                .map(|t| (t.name.clone(), t))
+                .collect()
+        )
+    }
+}
+
+fn normalize(name: &str) -> Result<String, AppError> {
+    normalize_tag(name).ok_or(AppError::BadRequest("Invalid tag name".into()))
+}
@@ -1,4 +1,4 @@
-pub mod r2;
+pub mod storage;
 pub mod watchdog;
 pub mod smtp_service;
 pub mod internal;
@@ -0,0 +1,258 @@
+use std::env;
+use std::io::ErrorKind;
+use std::path::{ Component, Path, PathBuf };
+use std::time::{ Duration, SystemTime };
+
+use async_trait::async_trait;
+use chrono::{ DateTime, Utc };
+use hmac::{ Hmac, Mac };
+use log::info;
+use rand::RngCore;
+use sha2::Sha256;
+use tokio::io::AsyncReadExt;
+use uuid::Uuid;
+
+use crate::services::storage::{ ObjectInfo, PresignedUpload, Storage, StorageError, StoredObject };
+use crate::utils::hash::{ decode_hex, encode_hex };
+
+const LIST_PAGE_SIZE: usize = 1000;
+
+/// Keeps files on local disk and serves them from `/files`, for running without a bucket.
+#[derive(Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+    base_url: String,
+    signing_key: Vec<u8>,
+}
+
+impl LocalStorage {
+    /// Reads `LOCAL_STORAGE_PATH` (default `./storage`) and `LOCAL_STORAGE_URL`,
+    /// the public base the files are served under (default `http://localhost:{PORT}/files`).
+    pub async fn new_from_env() -> Self {
+        let root = PathBuf::from(env::var("LOCAL_STORAGE_PATH").unwrap_or_else(|_| "./storage".to_string()));
+        let base_url = env::var("LOCAL_STORAGE_URL").unwrap_or_else(|_| {
+            let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
+            format!("http://localhost:{}/files", port)
+        });
+
+        // without a configured secret, upload links just don't survive a restart
+        let signing_key = match env::var("LOCAL_STORAGE_SECRET") {
+            Ok(secret) => secret.into_bytes(),
+            Err(_) => {
+                let mut key = vec![0u8; 32];
+                rand::rng().fill_bytes(&mut key);
+                key
+            }
+        };
+
+        tokio::fs::create_dir_all(&root).await.expect("Failed to create LOCAL_STORAGE_PATH");
+        info!("Storing files under {} served from {}", root.display(), base_url);
+        Self { root, base_url: base_url.trim_end_matches('/').to_string(), signing_key }
+    }
+
+    /// Where `key` lives on disk. Keys that would escape the storage root are refused.
+    pub fn path_for(&self, key: &str) -> Result<PathBuf, StorageError> {
+        let relative = Path::new(key);
+        let clean = !key.is_empty() &&
+            !key.contains('\\') &&
+            relative.components().all(|c| matches!(c, Component::Normal(_)));
+        if !clean {
+            return Err(StorageError::InvalidKey(key.to_string()));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// Checks a presigned upload link made by `presign_upload`.
+    pub fn verify_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+        size_bytes: u64,
+        sha256: &str,
+        expires: i64,
+        signature: &str
+    ) -> bool {
+        if expires < Utc::now().timestamp() {
+            return false;
+        }
+        let Some(signature) = decode_hex(signature) else {
+            return false;
+        };
+        self.mac(key, content_type, size_bytes, sha256, expires).verify_slice(&signature).is_ok()
+    }
+
+    fn mac(&self, key: &str, content_type: &str, size_bytes: u64, sha256: &str, expires: i64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.signing_key).expect("HMAC takes keys of any size");
+        mac.update(format!("{}\n{}\n{}\n{}\n{}", key, content_type, size_bytes, sha256, expires).as_bytes());
+        mac
+    }
+
+    // every file under `dir` as a key
+    async fn walk(&self, dir: PathBuf) -> Result<Vec<StoredObject>, StorageError> {
+        let mut objects = Vec::new();
+        let mut pending = vec![dir];
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            while let Some(entry) = entries.next_entry().await? {
+                let metadata = entry.metadata().await?;
+                if metadata.is_dir() {
+                    pending.push(entry.path());
+                    continue;
+                }
+
+                let Ok(relative) = entry.path().strip_prefix(&self.root).map(Path::to_path_buf) else {
+                    continue;
+                };
+                let key = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                // half written uploads
+                if key.contains(".part-") {
+                    continue;
+                }
+
+                objects.push(StoredObject {
+                    key,
+                    last_modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+                });
+            }
+        }
+        Ok(objects)
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn upload(&self, key: &str, bytes: &[u8], _content_type: &str) -> Result<(), StorageError> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // write next to it first so readers never see half a file
+        let partial = path.with_extension(format!("part-{}", Uuid::new_v4().simple()));
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(())
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        let source = self.path_for(from)?;
+        let path = self.path_for(to)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let partial = path.with_extension(format!("part-{}", Uuid::new_v4().simple()));
+        tokio::fs::copy(&source, &partial).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.path_for(key)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn delete_many(&self, keys: &[String]) -> Result<usize, StorageError> {
+        for key in keys {
+            self.delete(key).await?;
+        }
+        Ok(keys.len())
+    }
+
+    async fn list_page(
+        &self,
+        prefix: &str,
+        continuation: Option<String>
+    ) -> Result<(Vec<StoredObject>, Option<String>), StorageError> {
+        // only walk the directory the prefix points into
+        let dir = match prefix.rfind('/') {
+            Some(end) => self.path_for(&prefix[..end])?,
+            None => self.root.clone(),
+        };
+
+        let mut objects: Vec<StoredObject> = self.walk(dir).await?
+            .into_iter()
+            .filter(|o| o.key.starts_with(prefix))
+            .filter(|o| continuation.as_ref().is_none_or(|after| o.key > *after))
+            .collect();
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+
+        // the continuation token is just the last key handed out
+        let next = if objects.len() > LIST_PAGE_SIZE {
+            objects.truncate(LIST_PAGE_SIZE);
+            objects.last().map(|o| o.key.clone())
+        } else {
+            None
+        };
+        Ok((objects, next))
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<ObjectInfo>, StorageError> {
+        match tokio::fs::metadata(self.path_for(key)?).await {
+            // the upload route already checked the hash, nothing keeps it around though
+            Ok(metadata) if metadata.is_file() => Ok(Some(ObjectInfo { size_bytes: metadata.len(), sha256: None })),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn read(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        Ok(tokio::fs::read(self.path_for(key)?).await?)
+    }
+
+    async fn read_start(&self, key: &str, len: u64) -> Result<Vec<u8>, StorageError> {
+        let file = tokio::fs::File::open(self.path_for(key)?).await?;
+        let mut bytes = Vec::new();
+        file.take(len).read_to_end(&mut bytes).await?;
+        Ok(bytes)
+    }
+
+    async fn presign_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+        size_bytes: u64,
+        sha256: &str,
+        expires_in: Duration
+    ) -> Result<PresignedUpload, StorageError> {
+        self.path_for(key)?;
+        let expires_at = DateTime::<Utc>::from(SystemTime::now() + expires_in);
+        let expires = expires_at.timestamp();
+        let signature = self.mac(key, content_type, size_bytes, sha256, expires).finalize().into_bytes();
+
+        Ok(PresignedUpload {
+            url: format!(
+                "{}?expires={}&size={}&sha256={}&signature={}",
+                self.public_url(key),
+                expires,
+                size_bytes,
+                sha256,
+                encode_hex(&signature)
+            ),
+            method: "PUT".to_string(),
+            headers: [("content-type".to_string(), content_type.to_string())].into(),
+            expires_at,
+        })
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url, key)
+    }
+
+    fn as_local(&self) -> Option<&LocalStorage> {
+        Some(self)
+    }
+}
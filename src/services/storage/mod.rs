@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{ DateTime, Utc };
+use log::info;
+
+use crate::services::storage::local::LocalStorage;
+use crate::services::storage::r2::R2;
+use crate::utils::hash::hash_bytes_sha256_slimmed;
+use crate::utils::sniff::extension_for;
+
+pub mod local;
+pub mod r2;
+
+// max sizes
+pub const MAX_IMAGE_SIZE_BYTES: usize = 15 * 1024 * 1024; // 15 MB
+pub const MAX_VIDEO_SIZE_BYTES: usize = 20 * 1024 * 1024; // 20 MB
+pub const MAX_REQUEST_SIZE_BYTES: usize = 60 * 1024 * 1024; // 60 MB, all files and fields of one upload together
+pub const MAX_FILES_PER_REQUEST: usize = 10;
+pub const MAX_DIRECT_VIDEO_SIZE_BYTES: usize = 200 * 1024 * 1024; // 200 MB, videos uploaded straight to storage
+
+// allowed MIME types
+pub const ALLOWED_IMAGE_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/webp",
+    "image/gif",
+];
+pub const ALLOWED_VIDEO_TYPES: &[&str] = &[
+    "video/mp4",
+    "video/webm",
+];
+
+// presigned uploads land here until they are checked, nothing under it is ever served
+pub const STAGING_PREFIX: &str = "staging/";
+
+/// Types of user assets for organized storage.
+pub enum UserAssetType {
+    ProfilePicture,
+    Banner,
+}
+
+/// An object in the store, as returned by a listing.
+#[derive(Clone, Debug)]
+pub struct StoredObject {
+    pub key: String,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// What storage knows about an object without reading it.
+#[derive(Clone, Debug)]
+pub struct ObjectInfo {
+    pub size_bytes: u64,
+    pub sha256: Option<String>, // hex, only when the backend kept a checksum
+}
+
+/// A URL the client can upload one object to directly, without going through us.
+#[derive(Clone, Debug)]
+pub struct PresignedUpload {
+    pub url: String,
+    pub method: String,
+    pub headers: HashMap<String, String>, // must be sent exactly as given
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Storage backend error: {0}")] Backend(String),
+
+    #[error("Storage IO error: {0}")] Io(#[from] std::io::Error),
+
+    #[error("Invalid storage key: {0}")] InvalidKey(String),
+}
+
+/// Where uploaded files live. Keys are `/` separated paths like `media/{sha256}.png`.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Stores `bytes` under `key`, replacing whatever was there.
+    async fn upload(&self, key: &str, bytes: &[u8], content_type: &str) -> Result<(), StorageError>;
+
+    /// Copies the object at `from` to `to`, replacing whatever was there.
+    async fn copy(&self, from: &str, to: &str) -> Result<(), StorageError>;
+
+    /// Deletes a single object. Deleting a key that doesn't exist is not an error.
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+
+    /// Deletes the given keys and returns how many were removed.
+    async fn delete_many(&self, keys: &[String]) -> Result<usize, StorageError>;
+
+    /// Lists one page of keys under `prefix`, with the token for the next page.
+    async fn list_page(
+        &self,
+        prefix: &str,
+        continuation: Option<String>
+    ) -> Result<(Vec<StoredObject>, Option<String>), StorageError>;
+
+    /// Size and checksum of `key`, `None` when nothing is stored there.
+    async fn head(&self, key: &str) -> Result<Option<ObjectInfo>, StorageError>;
+
+    /// Reads the whole object.
+    async fn read(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+
+    /// Reads at most the first `len` bytes of the object.
+    async fn read_start(&self, key: &str, len: u64) -> Result<Vec<u8>, StorageError>;
+
+    /// A URL the client can upload exactly `size_bytes` of `content_type` with the given
+    /// SHA-256 (hex) to until it expires.
+    async fn presign_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+        size_bytes: u64,
+        sha256: &str,
+        expires_in: Duration
+    ) -> Result<PresignedUpload, StorageError>;
+
+    /// Where the public can fetch `key` from.
+    fn public_url(&self, key: &str) -> String;
+
+    /// The local disk backend, so its file routes can reach it.
+    fn as_local(&self) -> Option<&LocalStorage> {
+        None
+    }
+
+    /// Turns one of our public URLs back into its key.
+    fn key_from_url(&self, url: &str) -> Option<String> {
+        url.strip_prefix(&self.public_url(""))
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+    }
+
+    /// Lists every key under `prefix`.
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<StoredObject>, StorageError> {
+        let mut objects = Vec::new();
+        let mut continuation = None;
+        loop {
+            let (page, next) = self.list_page(prefix, continuation).await?;
+            objects.extend(page);
+            match next {
+                Some(token) => continuation = Some(token),
+                None => return Ok(objects),
+            }
+        }
+    }
+
+    /// Deletes everything under `prefix`, like all of a post's assets.
+    async fn delete_prefix(&self, prefix: &str) -> Result<usize, StorageError> {
+        // an empty prefix would be the whole store
+        if prefix.is_empty() {
+            return Ok(0);
+        }
+        let keys: Vec<String> = self.list_keys(prefix).await?
+            .into_iter()
+            .map(|object| object.key)
+            .collect();
+        self.delete_many(&keys).await
+    }
+
+    /// Uploads a user asset (profile picture or banner) and returns its public URL.
+    /// Assets are kept under the user's id, which unlike the username never changes.
+    ///
+    /// `content_type` must be the sniffed type of `bytes`, it picks the stored extension.
+    async fn upload_user_asset(
+        &self,
+        user_id: &str,
+        asset_type: UserAssetType,
+        bytes: &[u8],
+        content_type: &str
+    ) -> Result<String, StorageError> {
+        let hash = hash_bytes_sha256_slimmed(bytes);
+        let dir = match asset_type {
+            UserAssetType::ProfilePicture => "profile",
+            UserAssetType::Banner => "banner",
+        };
+        let extension = extension_for(content_type).unwrap_or("bin");
+
+        let key = format!("userassets/{}/{}/{}.{}", user_id, dir, hash, extension);
+        self.upload(&key, bytes, content_type).await?;
+        Ok(self.public_url(&key))
+    }
+}
+
+/// Picks the backend from `STORAGE_BACKEND`: `r2` (the default) or `local`.
+pub async fn storage_from_env() -> Arc<dyn Storage> {
+    let backend = env::var("STORAGE_BACKEND").unwrap_or_else(|_| "r2".to_string());
+    info!("Using {} storage backend", backend);
+
+    match backend.as_str() {
+        "r2" | "s3" => Arc::new(R2::new_from_env().await),
+        "local" => Arc::new(LocalStorage::new_from_env().await),
+        other => panic!("Unknown STORAGE_BACKEND {}, expected r2 or local", other),
+    }
+}
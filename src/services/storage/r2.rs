@@ -0,0 +1,276 @@
+use std::env;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use aws_config::Region;
+use aws_sdk_s3::{
+    Client, Config,
+    config::Credentials,
+    error::{ DisplayErrorContext, ProvideErrorMetadata },
+    presigning::PresigningConfig,
+    primitives::ByteStream,
+    types::{ ChecksumMode, Delete, ObjectCannedAcl, ObjectIdentifier },
+};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{ DateTime, Utc };
+use log::{ info, warn };
+
+use crate::services::storage::{ ObjectInfo, PresignedUpload, Storage, StorageError, StoredObject };
+use crate::utils::hash::{ decode_hex, encode_hex };
+use crate::utils::r2endpoint::CustomEndpointResolver;
+
+// the most keys one DeleteObjects call takes
+const DELETE_BATCH_SIZE: usize = 1000;
+
+/// Cloudflare R2, or any other S3 compatible store when `R2_ENDPOINT` is set.
+#[derive(Clone, Debug)]
+pub struct R2 {
+    client: Client,
+    bucket: String,
+    cdn_domain: String,
+}
+
+fn backend_error(e: impl std::error::Error) -> StorageError {
+    StorageError::Backend(DisplayErrorContext(e).to_string())
+}
+
+impl R2 {
+    /// Creates a new `R2` client configured from environment variables.
+    pub async fn new_from_env() -> Self {
+        info!("Attempting to initialize R2");
+        let access_key = env::var("R2_ACCESS_KEY_ID").expect("R2_ACCESS_KEY_ID must be set");
+        let secret_key = env::var("R2_SECRET_KEY").expect("R2_SECRET_KEY must be set");
+        let bucket = env::var("R2_BUCKET").expect("R2_BUCKET must be set");
+        let cdn_domain = env::var("CDN_DOMAIN").expect("CDN_DOMAIN must be set");
+        let region = env::var("R2_REGION").unwrap_or_else(|_| "auto".to_string());
+
+        // plain S3 compatible stores give their endpoint, R2 derives it from the account
+        let endpoint_url = match env::var("R2_ENDPOINT") {
+            Ok(endpoint) => format!("{}/{}", endpoint.trim_end_matches('/'), bucket),
+            Err(_) => {
+                let account_id = env::var("R2_ACCOUNT_ID").expect("R2_ACCOUNT_ID or R2_ENDPOINT must be set");
+                format!("https://{}.r2.cloudflarestorage.com/{}", account_id, bucket)
+            }
+        };
+        let endpoint = CustomEndpointResolver { endpoint: endpoint_url };
+
+        let config = Config::builder()
+            .credentials_provider(Credentials::new(
+                access_key, secret_key, None, None, "static",
+            ))
+            .region(Region::new(region))
+            .endpoint_resolver(endpoint)
+            .behavior_version_latest()
+            .build();
+
+        let client = Client::from_conf(config);
+        info!("Successfully to initialize R2");
+        Self { client, bucket, cdn_domain }
+    }
+}
+
+#[async_trait]
+impl Storage for R2 {
+    async fn upload(&self, key: &str, bytes: &[u8], content_type: &str) -> Result<(), StorageError> {
+        let stream = ByteStream::from(bytes.to_vec());
+
+        info!("Uploading {} to R2 bucket {}", key, self.bucket);
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(stream)
+            .content_type(content_type)
+            .metadata("postedon", "InkVault")
+            .metadata("posteddate", chrono::Utc::now().to_rfc3339())
+            .acl(ObjectCannedAcl::PublicRead)
+            .send()
+            .await
+            .map_err(backend_error)?;
+
+        info!("Uploaded {} to R2 bucket {}", key, self.bucket);
+        Ok(())
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        info!("Copying {} to {} in R2 bucket {}", from, to, self.bucket);
+        self.client
+            .copy_object()
+            .bucket(&self.bucket)
+            .copy_source(format!("{}/{}", self.bucket, from))
+            .key(to)
+            .acl(ObjectCannedAcl::PublicRead)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        info!("Deleting {} from R2 bucket {}", key, self.bucket);
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn delete_many(&self, keys: &[String]) -> Result<usize, StorageError> {
+        let mut deleted = 0;
+        for batch in keys.chunks(DELETE_BATCH_SIZE) {
+            let objects = batch
+                .iter()
+                .map(|key| ObjectIdentifier::builder().key(key).build())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(backend_error)?;
+            let delete = Delete::builder().set_objects(Some(objects)).quiet(true).build().map_err(backend_error)?;
+
+            let output = self.client
+                .delete_objects()
+                .bucket(&self.bucket)
+                .delete(delete)
+                .send()
+                .await
+                .map_err(backend_error)?;
+
+            // quiet mode only reports the failures
+            for error in output.errors() {
+                warn!("Failed to delete {:?} from R2: {:?}", error.key(), error.message());
+            }
+            deleted += batch.len() - output.errors().len();
+        }
+
+        info!("Deleted {} objects from R2 bucket {}", deleted, self.bucket);
+        Ok(deleted)
+    }
+
+    async fn list_page(
+        &self,
+        prefix: &str,
+        continuation: Option<String>
+    ) -> Result<(Vec<StoredObject>, Option<String>), StorageError> {
+        let output = self.client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(prefix)
+            .set_continuation_token(continuation)
+            .send()
+            .await
+            .map_err(backend_error)?;
+
+        let objects = output
+            .contents()
+            .iter()
+            .filter_map(|object| {
+                Some(StoredObject {
+                    key: object.key()?.to_string(),
+                    last_modified: object
+                        .last_modified()
+                        .and_then(|t| DateTime::from_timestamp(t.secs(), t.subsec_nanos())),
+                })
+            })
+            .collect();
+
+        let next = match output.is_truncated() {
+            Some(true) => output.next_continuation_token().map(str::to_string),
+            _ => None,
+        };
+        Ok((objects, next))
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<ObjectInfo>, StorageError> {
+        let output = match
+            self.client
+                .head_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .checksum_mode(ChecksumMode::Enabled)
+                .send().await
+        {
+            Ok(output) => output,
+            Err(e) if e.code() == Some("NotFound") || e.raw_response().is_some_and(|r| r.status().as_u16() == 404) => {
+                return Ok(None);
+            }
+            Err(e) => {
+                return Err(backend_error(e));
+            }
+        };
+
+        // checksums come back base64 encoded, we compare hex everywhere else
+        let sha256 = output
+            .checksum_sha256()
+            .and_then(|checksum| BASE64.decode(checksum).ok())
+            .map(|bytes| encode_hex(&bytes));
+
+        Ok(
+            Some(ObjectInfo {
+                size_bytes: output.content_length().unwrap_or_default().max(0) as u64,
+                sha256,
+            })
+        )
+    }
+
+    async fn read(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        let output = self.client.get_object().bucket(&self.bucket).key(key).send().await.map_err(backend_error)?;
+        let body = output.body.collect().await.map_err(backend_error)?;
+        Ok(body.into_bytes().to_vec())
+    }
+
+    async fn read_start(&self, key: &str, len: u64) -> Result<Vec<u8>, StorageError> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let output = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .range(format!("bytes=0-{}", len - 1))
+            .send()
+            .await
+            .map_err(backend_error)?;
+        let body = output.body.collect().await.map_err(backend_error)?;
+        Ok(body.into_bytes().to_vec())
+    }
+
+    async fn presign_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+        size_bytes: u64,
+        sha256: &str,
+        expires_in: Duration
+    ) -> Result<PresignedUpload, StorageError> {
+        let config = PresigningConfig::expires_in(expires_in).map_err(backend_error)?;
+        let checksum = decode_hex(sha256).ok_or(StorageError::Backend("SHA-256 must be hex".into()))?;
+
+        // type, length and checksum are signed, so the client can't swap in something else
+        let request = self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .content_length(size_bytes as i64)
+            .checksum_sha256(BASE64.encode(checksum))
+            .presigned(config)
+            .await
+            .map_err(backend_error)?;
+
+        Ok(PresignedUpload {
+            url: request.uri().to_string(),
+            method: request.method().to_string(),
+            headers: request
+                .headers()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            expires_at: Utc::now() + expires_in,
+        })
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        format!("https://{}/{}", self.cdn_domain, key)
+    }
+}
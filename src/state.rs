@@ -2,7 +2,7 @@ use std::collections::HashSet;
 use dotenv::dotenv;
 use std::env;
 use std::sync::{Arc, Mutex};
-use crate::{ database::mongo::InkvaultDB, services::{ r2::R2, watchdog::Watchdog } };
+use crate::{ database::mongo::InkvaultDB, services::{ storage::{ storage_from_env, Storage }, watchdog::Watchdog } };
 use crate::redis::InkvaultCache;
 use crate::services::internal::InternalServices;
 use crate::services::smtp_service::{EmailConfig, EmailService };
@@ -15,8 +15,7 @@ pub struct AppState {
     pub services: InternalServices,
     pub jwt_secret: String,
     pub jwt_expiration_seconds: i64,
-    pub r2: R2,
-    pub cdn_domain: String,
+    pub storage: Arc<dyn Storage>,
     pub frontend_domain: String,
     pub watchdog: Watchdog,
     pub smtp_service: EmailService,
@@ -25,7 +24,6 @@ pub struct AppState {
 
 pub async fn init_app_state(watchdog: Watchdog) -> AppState {
     dotenv().ok();
-    let cdn_domain = env::var("CDN_DOMAIN").expect("CDN_DOMAIN must be set");
     let mongodb_uri = env::var("MONGODB_URI").expect("MONGODB_URI must be set");
     let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
     let jwt_expiration_seconds = env
@@ -37,9 +35,11 @@ pub async fn init_app_state(watchdog: Watchdog) -> AppState {
     let redis_url = env::var("REDIS_URL").expect("REDIS_URL must be set");
     
     let db = InkvaultDB::new(&mongodb_uri, "inkvault").await.expect("Failed to initalize MongoDB");
+    db.migrate().await.expect("Failed to migrate MongoDB");
     let cache = InkvaultCache::new(&redis_url).await.expect("Failed to init Redis");
     
-    let services = InternalServices::new(db.clone(), cache).await.expect("Failed to init internal services");
+    let storage = storage_from_env().await;
+    let services = InternalServices::new(db.clone(), cache, storage.clone()).await.expect("Failed to init internal services");
     
     let port = env
         ::var("PORT")
@@ -71,8 +71,7 @@ pub async fn init_app_state(watchdog: Watchdog) -> AppState {
         services,
         jwt_secret,
         jwt_expiration_seconds,
-        r2: R2::new_from_env().await,
-        cdn_domain,
+        storage,
         frontend_domain,
         watchdog,
         smtp_service,
@@ -1,4 +1,8 @@
 pub mod cleanup;
+pub mod orphans;
+pub mod publish;
+pub mod trending;
+pub mod uploads;
 
 use std::sync::Arc;
 use async_trait::async_trait;
@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use chrono::{ Duration, Utc };
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use crate::models::media::Media;
+use crate::services::storage::{ StoredObject, STAGING_PREFIX };
+use crate::state::AppState;
+use crate::task::ScheduledTask;
+use crate::utils::error::AppError;
+
+// media is uploaded before its post is saved, give slow requests plenty of room
+const GRACE_PERIOD_HOURS: i64 = 24;
+
+/// Deletes bucket objects nothing in Mongo points at anymore
+pub struct OrphanSweepTask;
+impl ScheduledTask for OrphanSweepTask {
+    fn run(&self, state: Arc<AppState>) -> BoxFuture<'static, ()> {
+        async move {
+            for prefix in ["media/", STAGING_PREFIX, "postassets/", "characterassets/", "userassets/"] {
+                match sweep(&state, prefix).await {
+                    Ok(0) => {}
+                    Ok(count) => {
+                        log::info!("OrphanSweepTask: Deleted {} orphaned objects under {}.", count, prefix);
+                    }
+                    Err(e) => {
+                        log::error!("OrphanSweepTask: Failed to sweep {}: {}", prefix, e);
+                    }
+                }
+            }
+        }
+            .boxed()
+    }
+
+    fn name(&self) -> &str {
+        "OrphanSweepTask"
+    }
+
+    fn interval_seconds(&self) -> u64 {
+        6 * 3600
+    }
+}
+
+async fn sweep(state: &AppState, prefix: &str) -> Result<usize, AppError> {
+    let cutoff = Utc::now() - Duration::hours(GRACE_PERIOD_HOURS);
+    let mut deleted = 0;
+    let mut continuation = None;
+
+    loop {
+        let (objects, next) = state.storage
+            .list_page(prefix, continuation).await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        // objects without a timestamp are left alone, we can't tell how new they are
+        let candidates: Vec<StoredObject> = objects
+            .into_iter()
+            .filter(|o| o.last_modified.is_some_and(|t| t < cutoff))
+            .collect();
+
+        let referenced = referenced_keys(state, prefix, &candidates).await?;
+        let orphans: Vec<String> = candidates
+            .into_iter()
+            .map(|o| o.key)
+            .filter(|key| !referenced.contains(key))
+            .collect();
+
+        if !orphans.is_empty() {
+            // unindex first so nothing new starts pointing at them
+            if prefix == "media/" {
+                let hashes: Vec<String> = orphans.iter().filter_map(|key| media_hash(key)).collect();
+                state.services.media_service.forget(&hashes).await?;
+            }
+            deleted += state.storage
+                .delete_many(&orphans).await
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        }
+
+        match next {
+            Some(token) => continuation = Some(token),
+            None => return Ok(deleted),
+        }
+    }
+}
+
+// keys of the listed objects that a post, character or profile still uses
+async fn referenced_keys(state: &AppState, prefix: &str, objects: &[StoredObject]) -> Result<HashSet<String>, AppError> {
+    // media is shared, an object stays as long as anything uses its hash
+    if prefix == "media/" {
+        let hashes: Vec<String> = objects
+            .iter()
+            .filter_map(|o| media_hash(&o.key))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let used = state.services.media_service.hashes_in_use(&hashes).await?;
+        return Ok(
+            objects
+                .iter()
+                .filter(|o| media_hash(&o.key).is_some_and(|hash| used.contains(&hash)))
+                .map(|o| o.key.clone())
+                .collect()
+        );
+    }
+
+    // staged uploads belong to their pending upload until it's finalized or expires
+    if prefix == STAGING_PREFIX {
+        let keys: Vec<String> = objects
+            .iter()
+            .map(|o| o.key.clone())
+            .collect();
+        return state.services.media_service.staged_keys_in_use(&keys).await;
+    }
+
+    // keys look like postassets/{id}/..., characterassets/{id}/... and userassets/{user id}/...
+    let owners: Vec<String> = objects
+        .iter()
+        .filter_map(|o| o.key.strip_prefix(prefix)?.split('/').next())
+        .filter(|owner| !owner.is_empty())
+        .map(str::to_string)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    let urls: Vec<String> = match prefix {
+        "postassets/" =>
+            state.db.posts
+                .get_many(&owners).await?
+                .iter()
+                .flat_map(|post| media_urls(&post.media))
+                .collect(),
+        "characterassets/" =>
+            state.db.characters
+                .get_many(&owners).await?
+                .iter()
+                .flat_map(|character| media_urls(&character.media))
+                .collect(),
+        _ => {
+            // older assets sit under the username they were uploaded with, which may have
+            // changed since, so profiles are also found by the urls themselves
+            let listed: Vec<String> = objects
+                .iter()
+                .map(|o| state.storage.public_url(&o.key))
+                .collect();
+            let mut profiles = state.db.profiles.get_profiles_by_ids_and_usernames(owners.clone(), owners).await?;
+            profiles.extend(state.db.profiles.get_by_picture_urls(&listed).await?);
+            profiles
+                .into_iter()
+                .flat_map(|profile| [profile.profile_picture, profile.banner_picture])
+                .flatten()
+                .collect()
+        }
+    };
+
+    // a url we can't turn back into a key could be pointing at any of the listed objects,
+    // deleting anything then might break it
+    let mut keys = HashSet::new();
+    for url in &urls {
+        match state.storage.key_from_url(url) {
+            Some(key) => {
+                keys.insert(key);
+            }
+            None if url.contains(&format!("/{}", prefix)) => {
+                return Err(AppError::InternalServerError(format!("Can't tell which object {} is, not sweeping", url)));
+            }
+            None => {}
+        }
+    }
+    Ok(keys)
+}
+
+fn media_urls(media: &[Media]) -> Vec<String> {
+    media
+        .iter()
+        .flat_map(|m| [Some(m.url.clone()), m.thumbnail_url.clone(), m.preview_url.clone(), m.poster_url.clone()])
+        .flatten()
+        .collect()
+}
+
+// media/{sha256}.png and its variants like media/thumb/{sha256}.webp
+fn media_hash(key: &str) -> Option<String> {
+    let name = key.rsplit('/').next()?;
+    name.split('.').next().map(str::to_string)
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use crate::models::tag::TagKind;
+use crate::state::AppState;
+use crate::task::ScheduledTask;
+
+pub struct PublishTask;
+impl ScheduledTask for PublishTask {
+    fn run(&self, state: Arc<AppState>) -> BoxFuture<'static, ()> {
+        async move {
+            match state.services.post_service.publish_due().await {
+                Ok(posts) if posts.is_empty() => {}
+                Ok(posts) => {
+                    // tags only count once a post is live
+                    for post in &posts {
+                        state.services.tag_service.record_usage(TagKind::Post, &[], &post.tags).await;
+                    }
+                    log::info!("PublishTask: Published {} scheduled posts.", posts.len());
+                }
+                Err(e) => {
+                    log::error!("PublishTask: Failed to publish scheduled posts: {}", e);
+                }
+            }
+        }
+            .boxed()
+    }
+
+    fn name(&self) -> &str {
+        "PublishTask"
+    }
+
+    fn interval_seconds(&self) -> u64 {
+        60
+    }
+}
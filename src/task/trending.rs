@@ -0,0 +1,31 @@
+use std::sync::Arc;
+use chrono::{ Duration, Utc };
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use crate::state::AppState;
+use crate::task::ScheduledTask;
+use crate::utils::trending::SCORING_WINDOW_DAYS;
+
+pub struct TrendingTask;
+impl ScheduledTask for TrendingTask {
+    fn run(&self, state: Arc<AppState>) -> BoxFuture<'static, ()> {
+        async move {
+            let now = Utc::now();
+            let since = now - Duration::days(SCORING_WINDOW_DAYS);
+
+            match state.db.posts.rescore(since, now).await {
+                Ok(()) => log::info!("TrendingTask: Rescored posts."),
+                Err(e) => log::error!("TrendingTask: Failed to rescore posts: {}", e),
+            }
+        }
+            .boxed()
+    }
+
+    fn name(&self) -> &str {
+        "TrendingTask"
+    }
+
+    fn interval_seconds(&self) -> u64 {
+        900
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use crate::state::AppState;
+use crate::task::ScheduledTask;
+
+/// Throws away direct uploads that were never finalized
+pub struct PendingUploadExpiryTask;
+impl ScheduledTask for PendingUploadExpiryTask {
+    fn run(&self, state: Arc<AppState>) -> BoxFuture<'static, ()> {
+        async move {
+            match state.services.media_service.expire_abandoned().await {
+                Ok(0) => {}
+                Ok(count) => {
+                    log::info!("PendingUploadExpiryTask: Deleted {} abandoned uploads.", count);
+                }
+                Err(e) => {
+                    log::error!("PendingUploadExpiryTask: Failed to expire uploads: {}", e);
+                }
+            }
+        }
+            .boxed()
+    }
+
+    fn name(&self) -> &str {
+        "PendingUploadExpiryTask"
+    }
+
+    fn interval_seconds(&self) -> u64 {
+        600
+    }
+}
@@ -0,0 +1,206 @@
+use base64::{ engine::general_purpose::URL_SAFE_NO_PAD, Engine };
+use bson::{ doc, Document };
+use serde::{ Deserialize, Serialize };
+
+use crate::models::post::Post;
+use crate::utils::error::AppError;
+
+/// Legacy offset-paged feeds return a bare array, the next cursor rides along in this header
+pub const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+/// How a post feed is ordered. Every order breaks ties on `_id` so positions are stable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedSort {
+    Newest,
+    Oldest,
+    MostLiked,
+    Trending,
+    Top,
+}
+
+impl FeedSort {
+    fn field(&self) -> &'static str {
+        match self {
+            FeedSort::Newest | FeedSort::Oldest => "created_at",
+            FeedSort::MostLiked => "like_count",
+            FeedSort::Trending => "trending_score",
+            FeedSort::Top => "engagement",
+        }
+    }
+
+    fn direction(&self) -> i32 {
+        match self {
+            FeedSort::Oldest => 1,
+            _ => -1,
+        }
+    }
+
+    pub fn sort_doc(&self) -> Document {
+        doc! { self.field(): self.direction(), "_id": self.direction() }
+    }
+}
+
+/// Opaque position in a feed, made from the sort key and id of the last post a client saw.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "s")]
+pub enum FeedCursor {
+    #[serde(rename = "t")] Time {
+        k: i64,
+        id: String,
+    },
+    #[serde(rename = "n")] Score {
+        k: f64,
+        id: String,
+    },
+}
+
+impl FeedCursor {
+    /// Builds the cursor pointing just after `post` in a feed ordered by `sort`.
+    pub fn after(sort: FeedSort, post: &Post) -> Self {
+        let id = post.id.to_string();
+        match sort {
+            FeedSort::Newest | FeedSort::Oldest =>
+                FeedCursor::Time { k: post.created_at.timestamp_millis(), id },
+            FeedSort::MostLiked => FeedCursor::Score { k: post.like_count as f64, id },
+            FeedSort::Trending => FeedCursor::Score { k: post.trending_score, id },
+            FeedSort::Top => FeedCursor::Score { k: post.engagement, id },
+        }
+    }
+
+    /// Cursor for the next page, only when the page came back full.
+    pub fn next_page(sort: FeedSort, posts: &[Post], limit: u64) -> Option<String> {
+        if (posts.len() as u64) < limit {
+            return None;
+        }
+        posts.last().map(|post| FeedCursor::after(sort, post).encode())
+    }
+
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(raw: &str) -> Result<Self, AppError> {
+        URL_SAFE_NO_PAD.decode(raw)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or(AppError::BadRequest("Invalid cursor".into()))
+    }
+
+    pub fn id(&self) -> &str {
+        match self {
+            FeedCursor::Time { id, .. } | FeedCursor::Score { id, .. } => id,
+        }
+    }
+
+    /// `$match` body that only keeps posts after this cursor in `sort` order.
+    pub fn seek_filter(&self, sort: FeedSort) -> Result<Document, AppError> {
+        let op = if sort.direction() < 0 { "$lt" } else { "$gt" };
+        let field = sort.field();
+
+        let key = match (self, sort) {
+            (FeedCursor::Time { k, .. }, FeedSort::Newest | FeedSort::Oldest) =>
+                bson::Bson::DateTime(bson::DateTime::from_millis(*k)),
+            (FeedCursor::Score { k, .. }, FeedSort::MostLiked | FeedSort::Trending | FeedSort::Top) =>
+                bson::Bson::Double(*k),
+            _ => {
+                return Err(AppError::BadRequest("Cursor does not belong to this feed".into()));
+            }
+        };
+
+        Ok(
+            doc! {
+            "$or": [
+                { field: { op: key.clone() } },
+                { field: key, "_id": { op: self.id() } }
+            ]
+        }
+        )
+    }
+}
+
+/// Where a feed page starts: a cursor, or the old skip based offset which still works for now.
+#[derive(Debug, Clone)]
+pub enum FeedPage {
+    Offset(u64),
+    After(FeedCursor),
+}
+
+impl FeedPage {
+    /// Prefers `cursor` when given, otherwise falls back to the legacy `skip`.
+    pub fn from_params(cursor: Option<&str>, skip: u64) -> Result<Self, AppError> {
+        match cursor.filter(|c| !c.is_empty()) {
+            Some(raw) => Ok(FeedPage::After(FeedCursor::decode(raw)?)),
+            None => Ok(FeedPage::Offset(skip)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_cursor_round_trips() {
+        let cursor = FeedCursor::Time { k: 1_700_000_000_123, id: "abc".into() };
+        match FeedCursor::decode(&cursor.encode()).unwrap() {
+            FeedCursor::Time { k, id } => {
+                assert_eq!(k, 1_700_000_000_123);
+                assert_eq!(id, "abc");
+            }
+            other => panic!("decoded into {:?}", other),
+        }
+    }
+
+    #[test]
+    fn score_cursor_round_trips() {
+        let cursor = FeedCursor::Score { k: 12.5, id: "xyz".into() };
+        match FeedCursor::decode(&cursor.encode()).unwrap() {
+            FeedCursor::Score { k, id } => {
+                assert_eq!(k, 12.5);
+                assert_eq!(id, "xyz");
+            }
+            other => panic!("decoded into {:?}", other),
+        }
+    }
+
+    #[test]
+    fn encoded_cursor_is_url_safe() {
+        let encoded = FeedCursor::Time { k: -1, id: "?&/+=".into() }.encode();
+        assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert!(FeedCursor::decode("").is_err());
+        assert!(FeedCursor::decode("not a cursor!").is_err());
+        // valid base64, not a cursor
+        assert!(FeedCursor::decode(&URL_SAFE_NO_PAD.encode(b"{\"s\":\"q\"}")).is_err());
+    }
+
+    #[test]
+    fn seek_filter_follows_sort_direction() {
+        let cursor = FeedCursor::Time { k: 1000, id: "a".into() };
+        let newest = cursor.seek_filter(FeedSort::Newest).unwrap();
+        let oldest = cursor.seek_filter(FeedSort::Oldest).unwrap();
+        assert!(newest.to_string().contains("$lt"));
+        assert!(oldest.to_string().contains("$gt"));
+    }
+
+    #[test]
+    fn seek_filter_rejects_cursor_from_other_feed() {
+        let time = FeedCursor::Time { k: 1000, id: "a".into() };
+        let score = FeedCursor::Score { k: 1.0, id: "a".into() };
+        assert!(time.seek_filter(FeedSort::Trending).is_err());
+        assert!(score.seek_filter(FeedSort::Newest).is_err());
+        assert!(score.seek_filter(FeedSort::MostLiked).is_ok());
+    }
+
+    #[test]
+    fn empty_cursor_falls_back_to_offset() {
+        assert!(matches!(FeedPage::from_params(Some(""), 40).unwrap(), FeedPage::Offset(40)));
+        assert!(matches!(FeedPage::from_params(None, 0).unwrap(), FeedPage::Offset(0)));
+        let raw = FeedCursor::Time { k: 1, id: "a".into() }.encode();
+        assert!(matches!(FeedPage::from_params(Some(&raw), 40).unwrap(), FeedPage::After(_)));
+    }
+}
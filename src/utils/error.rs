@@ -39,6 +39,24 @@ pub enum AppError {
     #[error("Report was not found")]
     ReportNotFound,
 
+    #[error("Character was not found")]
+    CharacterNotFound,
+
+    #[error("Tag was not found")]
+    TagNotFound,
+
+    #[error("Revision was not found")]
+    RevisionNotFound,
+
+    #[error("Collection was not found")]
+    CollectionNotFound,
+
+    #[error("Bookmark was not found")]
+    BookmarkNotFound,
+
+    #[error("Hash ban was not found")]
+    HashBanNotFound,
+
     // Internal errors
     #[error("Internal server error: {0}")] InternalServerError(String),
 
@@ -60,7 +78,13 @@ impl ResponseError for AppError {
             | AppError::ProfileNotFound
             | AppError::CommentNotFound
             | AppError::PostNotFound
-            | AppError::ReportNotFound => StatusCode::NOT_FOUND,
+            | AppError::ReportNotFound
+            | AppError::CharacterNotFound
+            | AppError::TagNotFound
+            | AppError::RevisionNotFound
+            | AppError::CollectionNotFound
+            | AppError::BookmarkNotFound
+            | AppError::HashBanNotFound => StatusCode::NOT_FOUND,
 
             // 413 - Payload Too Large
             AppError::FileToBig(_) => StatusCode::PAYLOAD_TOO_LARGE,
@@ -12,3 +12,24 @@ pub fn hash_bytes_sha256_slimmed(bytes: &[u8]) -> String {
     let full_hash = hash_bytes_sha256(bytes);
     full_hash[..10].to_string()
 }
+
+/// Lowercase hex encoding of `bytes`.
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes a hex string, `None` when it isn't valid hex.
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Whether `hash` looks like a full SHA-256 hex digest.
+pub fn is_sha256_hex(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
@@ -0,0 +1,324 @@
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+use image::{ DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits };
+
+use crate::utils::error::AppError;
+
+// longest side of the generated variants
+pub const THUMBNAIL_SIZE: u32 = 320;
+pub const PREVIEW_SIZE: u32 = 1280;
+const WEBP_QUALITY: f32 = 80.0;
+const REENCODE_JPEG_QUALITY: u8 = 90;
+
+// anything bigger than this is a decompression bomb, not art
+const MAX_DIMENSION: u32 = 16_384;
+const MAX_DECODE_ALLOC: u64 = 512 * 1024 * 1024;
+
+/// An uploaded image after decoding, with its metadata stripped and the smaller WebP variants
+pub struct ProcessedImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>, // the original, minus EXIF/GPS and friends
+    pub thumbnail: Vec<u8>,
+    pub preview: Vec<u8>,
+}
+
+/// Decodes an uploaded image and builds everything we store for it.
+/// This is CPU heavy, call it from `spawn_blocking`.
+pub fn process_image(content_type: &str, bytes: &[u8]) -> Result<ProcessedImage, AppError> {
+    let format = ImageFormat::from_mime_type(content_type).ok_or(
+        AppError::BadRequest(format!("Unsupported image type: {}", content_type))
+    )?;
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+
+    let unreadable = |_| AppError::BadRequest("Could not read image".into());
+    let mut decoder = reader.into_decoder().map_err(unreadable)?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder).map_err(unreadable)?;
+    img.apply_orientation(orientation);
+
+    // the orientation lives in the EXIF we are about to drop, so bake it into the pixels
+    let data = if orientation == Orientation::NoTransforms {
+        strip_metadata(format, bytes)?
+    } else {
+        encode_as(&img, format)?
+    };
+
+    Ok(ProcessedImage {
+        width: img.width(),
+        height: img.height(),
+        data,
+        thumbnail: encode_webp(&shrink(&img, THUMBNAIL_SIZE)),
+        preview: encode_webp(&shrink(&img, PREVIEW_SIZE)),
+    })
+}
+
+// scales down to fit a size x size box, small images are left alone
+fn shrink(img: &DynamicImage, size: u32) -> DynamicImage {
+    if img.width() <= size && img.height() <= size {
+        return img.clone();
+    }
+    img.thumbnail(size, size)
+}
+
+fn encode_webp(img: &DynamicImage) -> Vec<u8> {
+    let (width, height) = (img.width(), img.height());
+    if img.color().has_alpha() {
+        let rgba = img.to_rgba8();
+        webp::Encoder::from_rgba(&rgba, width, height).encode(WEBP_QUALITY).to_vec()
+    } else {
+        let rgb = img.to_rgb8();
+        webp::Encoder::from_rgb(&rgb, width, height).encode(WEBP_QUALITY).to_vec()
+    }
+}
+
+fn encode_as(img: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, AppError> {
+    let mut out = Vec::new();
+    let result = match format {
+        ImageFormat::Jpeg => img.to_rgb8().write_with_encoder(JpegEncoder::new_with_quality(&mut out, REENCODE_JPEG_QUALITY)),
+        _ => img.write_to(&mut Cursor::new(&mut out), format),
+    };
+    result.map_err(|_| AppError::InternalServerError("Failed to encode image".into()))?;
+    Ok(out)
+}
+
+/// Drops EXIF, XMP and text metadata without touching the pixels
+pub fn strip_metadata(format: ImageFormat, bytes: &[u8]) -> Result<Vec<u8>, AppError> {
+    let stripped = match format {
+        ImageFormat::Jpeg => strip_jpeg(bytes),
+        ImageFormat::Png => strip_png(bytes),
+        ImageFormat::WebP => strip_webp(bytes),
+        // gifs have nowhere to put EXIF
+        _ => Some(bytes.to_vec()),
+    };
+    stripped.ok_or(AppError::BadRequest("Could not read image".into()))
+}
+
+fn strip_jpeg(bytes: &[u8]) -> Option<Vec<u8>> {
+    if !bytes.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(&bytes[..2]);
+    let mut pos = 2;
+
+    loop {
+        if *bytes.get(pos)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+
+        // standalone markers have no length
+        if marker == 0x01 || (0xd0..=0xd7).contains(&marker) {
+            out.extend_from_slice(&bytes[pos..pos + 2]);
+            pos += 2;
+            continue;
+        }
+
+        // start of scan, everything after is image data
+        if marker == 0xda {
+            out.extend_from_slice(&bytes[pos..]);
+            return Some(out);
+        }
+
+        let len = u16::from_be_bytes([*bytes.get(pos + 2)?, *bytes.get(pos + 3)?]) as usize;
+        let end = pos + 2 + len;
+        if len < 2 || end > bytes.len() {
+            return None;
+        }
+
+        // APP1 is EXIF/XMP, APP13 is IPTC, FE is a comment. ICC profiles (APP2) stay.
+        if !matches!(marker, 0xe1 | 0xed | 0xfe) {
+            out.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+}
+
+fn strip_png(bytes: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !bytes.starts_with(SIGNATURE) {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(SIGNATURE);
+    let mut pos = SIGNATURE.len();
+
+    while pos < bytes.len() {
+        let len = u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let kind = bytes.get(pos + 4..pos + 8)?;
+        let end = pos + 12 + len; // length, type, data, crc
+        if end > bytes.len() {
+            return None;
+        }
+
+        if !matches!(kind, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            out.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+    Some(out)
+}
+
+fn strip_webp(bytes: &[u8]) -> Option<Vec<u8>> {
+    if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WEBP" {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(&bytes[..12]);
+    let mut pos = 12;
+
+    while pos < bytes.len() {
+        let kind = bytes.get(pos..pos + 4)?;
+        let len = u32::from_le_bytes(bytes.get(pos + 4..pos + 8)?.try_into().ok()?) as usize;
+        let end = (pos + 8 + len + (len & 1)).min(bytes.len()); // chunks are padded to even sizes
+        if pos + 8 + len > bytes.len() {
+            return None;
+        }
+
+        match kind {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let start = out.len();
+                out.extend_from_slice(&bytes[pos..end]);
+                // clear the "has EXIF" and "has XMP" flags
+                if let Some(flags) = out.get_mut(start + 8) {
+                    *flags &= !0x0c;
+                }
+            }
+            _ => out.extend_from_slice(&bytes[pos..end]),
+        }
+        pos = end;
+    }
+
+    let riff_size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ ImageBuffer, Rgb };
+
+    // an APP1 Exif segment with a GPS IFD, and an orientation if given
+    fn exif_segment(orientation: Option<u16>) -> Vec<u8> {
+        let mut entries = vec![(0x8825u16, 4u16, 0u32)]; // GPSInfo, LONG, offset filled in below
+        if let Some(orientation) = orientation {
+            entries.insert(0, (0x0112, 3, (orientation as u32) << 16)); // Orientation, SHORT
+        }
+        let gps_at = 8 + 2 + entries.len() as u32 * 12 + 4;
+
+        let mut tiff = b"MM\0*".to_vec();
+        tiff.extend_from_slice(&8u32.to_be_bytes());
+        tiff.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+        for (tag, kind, value) in entries {
+            let value = if tag == 0x8825 { gps_at } else { value };
+            tiff.extend_from_slice(&tag.to_be_bytes());
+            tiff.extend_from_slice(&kind.to_be_bytes());
+            tiff.extend_from_slice(&1u32.to_be_bytes());
+            tiff.extend_from_slice(&value.to_be_bytes());
+        }
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+        // GPS IFD: GPSLatitudeRef "N"
+        tiff.extend_from_slice(&1u16.to_be_bytes());
+        tiff.extend_from_slice(&[0x00, 0x01, 0x00, 0x02, 0, 0, 0, 2, b'N', 0, 0, 0]);
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+
+        let mut segment = vec![0xff, 0xe1];
+        segment.extend_from_slice(&((tiff.len() + 8) as u16).to_be_bytes());
+        segment.extend_from_slice(b"Exif\0\0");
+        segment.extend_from_slice(&tiff);
+        segment
+    }
+
+    // a width x height JPEG with the Exif segment right after SOI
+    fn jpeg_with_exif(width: u32, height: u32, orientation: Option<u16>) -> Vec<u8> {
+        let img = ImageBuffer::from_fn(width, height, |x, y| Rgb([(x % 256) as u8, (y % 256) as u8, 128]));
+        let mut encoded = Vec::new();
+        img.write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, 80)).unwrap();
+
+        let mut bytes = encoded[..2].to_vec();
+        bytes.extend(exif_segment(orientation));
+        bytes.extend_from_slice(&encoded[2..]);
+        bytes
+    }
+
+    // the markers of the segments before the scan
+    fn jpeg_markers(bytes: &[u8]) -> Vec<u8> {
+        let mut markers = Vec::new();
+        let mut pos = 2;
+        while bytes[pos + 1] != 0xda {
+            markers.push(bytes[pos + 1]);
+            pos += 2 + u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        }
+        markers
+    }
+
+    fn webp_size(bytes: &[u8]) -> (u32, u32) {
+        let img = image::load_from_memory_with_format(bytes, ImageFormat::WebP).unwrap();
+        (img.width(), img.height())
+    }
+
+    #[test]
+    fn strips_exif_from_jpeg() {
+        let bytes = jpeg_with_exif(2000, 1000, None);
+        assert!(jpeg_markers(&bytes).contains(&0xe1));
+
+        let processed = process_image("image/jpeg", &bytes).unwrap();
+        assert_eq!((processed.width, processed.height), (2000, 1000));
+        assert!(!jpeg_markers(&processed.data).contains(&0xe1));
+        assert!(!processed.data.windows(6).any(|w| w == b"Exif\0\0"));
+        // the pixels are untouched, only the segment is gone
+        assert_eq!(processed.data.len(), bytes.len() - exif_segment(None).len());
+
+        let data = image::load_from_memory_with_format(&processed.data, ImageFormat::Jpeg).unwrap();
+        assert_eq!((data.width(), data.height()), (2000, 1000));
+    }
+
+    #[test]
+    fn variants_fit_their_bounds() {
+        let processed = process_image("image/jpeg", &jpeg_with_exif(2000, 1000, None)).unwrap();
+        assert_eq!(webp_size(&processed.thumbnail), (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2));
+        assert_eq!(webp_size(&processed.preview), (PREVIEW_SIZE, PREVIEW_SIZE / 2));
+
+        // small images are not scaled up
+        let processed = process_image("image/jpeg", &jpeg_with_exif(200, 100, None)).unwrap();
+        assert_eq!(webp_size(&processed.thumbnail), (200, 100));
+        assert_eq!(webp_size(&processed.preview), (200, 100));
+    }
+
+    #[test]
+    fn bakes_orientation_before_stripping() {
+        // 6 is rotated 90° clockwise
+        let processed = process_image("image/jpeg", &jpeg_with_exif(2000, 1000, Some(6))).unwrap();
+        assert_eq!((processed.width, processed.height), (1000, 2000));
+        assert!(!jpeg_markers(&processed.data).contains(&0xe1));
+
+        let data = image::load_from_memory_with_format(&processed.data, ImageFormat::Jpeg).unwrap();
+        assert_eq!((data.width(), data.height()), (1000, 2000));
+        let (width, height) = webp_size(&processed.thumbnail);
+        assert!(width <= THUMBNAIL_SIZE && height <= THUMBNAIL_SIZE);
+        assert_eq!(height, THUMBNAIL_SIZE);
+    }
+
+    #[test]
+    fn rejects_unreadable_images() {
+        let bytes = jpeg_with_exif(64, 64, None);
+        assert!(process_image("image/jpeg", &bytes[..bytes.len() / 3]).is_err());
+        assert!(process_image("image/jpeg", b"not an image").is_err());
+        assert!(process_image("image/tiff", &bytes).is_err());
+    }
+}
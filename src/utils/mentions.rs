@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+
+// more than this in one comment is spam, the rest are left as plain text
+pub const MAX_MENTIONS: usize = 10;
+
+/// The lowercased usernames `@mentioned` in `content`, deduped and in order of appearance.
+/// An `@` right after a letter or digit is an email address, not a mention.
+pub fn parse_mentions(content: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut mentions = Vec::new();
+    let mut previous = None;
+
+    let mut chars = content.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let after_word = previous.is_some_and(|p: char| p.is_alphanumeric());
+        previous = Some(c);
+        if c != '@' || after_word {
+            continue;
+        }
+
+        let mut end = start + 1;
+        while let Some(&(i, next)) = chars.peek() {
+            if !(next.is_alphanumeric() || matches!(next, '_' | '-' | '.')) {
+                break;
+            }
+            end = i + next.len_utf8();
+            previous = Some(next);
+            chars.next();
+        }
+
+        // a sentence ending right after the name isn't part of it
+        let username = content[start + 1..end].trim_end_matches('.').to_lowercase();
+        if !username.is_empty() && seen.insert(username.clone()) {
+            mentions.push(username);
+            if mentions.len() == MAX_MENTIONS {
+                break;
+            }
+        }
+    }
+    mentions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_mentions_in_order() {
+        assert_eq!(parse_mentions("hey @alice and @bob_2, look"), vec!["alice", "bob_2"]);
+    }
+
+    #[test]
+    fn lowercases_and_dedupes() {
+        assert_eq!(parse_mentions("@Alice @alice @ALICE @carol"), vec!["alice", "carol"]);
+    }
+
+    #[test]
+    fn email_addresses_are_not_mentions() {
+        assert!(parse_mentions("mail me at someone@example.com").is_empty());
+        assert_eq!(parse_mentions("(@dave) x@y @erin"), vec!["dave", "erin"]);
+    }
+
+    #[test]
+    fn trailing_dots_are_punctuation() {
+        assert_eq!(parse_mentions("thanks @frank. and @g.h..."), vec!["frank", "g.h"]);
+    }
+
+    #[test]
+    fn bare_at_signs_are_skipped() {
+        assert!(parse_mentions("@ @@ @. email@").is_empty());
+        assert_eq!(parse_mentions("@@ivan"), vec!["ivan"]);
+    }
+
+    #[test]
+    fn handles_unicode_names() {
+        assert_eq!(parse_mentions("hi @Zoë!"), vec!["zoë"]);
+    }
+
+    #[test]
+    fn caps_the_number_of_mentions() {
+        let content: String = (0..MAX_MENTIONS + 5).map(|i| format!("@user{} ", i)).collect();
+        let mentions = parse_mentions(&content);
+        assert_eq!(mentions.len(), MAX_MENTIONS);
+        assert_eq!(mentions.last().unwrap(), &format!("user{}", MAX_MENTIONS - 1));
+    }
+}
@@ -8,6 +8,14 @@ pub mod post;
 pub mod r2endpoint;
 pub mod hash;
 pub mod roles;
+pub mod cursor;
+pub mod trending;
+pub mod search;
+pub mod tags;
+pub mod mentions;
+pub mod image;
+pub mod sniff;
+pub mod video;
 
 /// (De)serialize `Uuid` as a string in JSON.
 pub mod uuid_as_string {
@@ -37,6 +45,7 @@ pub fn default_cors() -> Cors {
         .allow_any_origin()
         .allow_any_method()
         .allow_any_header()
+        .expose_headers([cursor::NEXT_CURSOR_HEADER])
         .max_age(3600)
         .send_wildcard()
 }
@@ -7,3 +7,13 @@ use utoipa::ToSchema;
 pub enum PostType {
     Generic,
 }
+
+/// Where a post is in its life. Only published posts show up in feeds and search.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PostStatus {
+    Draft,
+    Scheduled, // goes live at `publish_at`, see task::publish
+    #[default]
+    Published,
+}
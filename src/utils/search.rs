@@ -0,0 +1,308 @@
+use regex::{ Regex, RegexBuilder };
+
+use crate::utils::tags::normalize_tag;
+
+// caps so a single query can't turn into a huge filter
+const MAX_TOKENS: usize = 32;
+const MAX_TOKEN_LEN: usize = 64;
+
+/// A search box query split into its parts.
+///
+/// Supported syntax: plain words, `"quoted phrases"`, `prefix*`, `-tag` to exclude a tag,
+/// `tag:name` or `#name` to require one, `author:username` and `type:post_type`.
+#[derive(Debug, Default, Clone)]
+pub struct ParsedQuery {
+    pub terms: Vec<String>,
+    pub phrases: Vec<String>,
+    pub prefixes: Vec<String>,
+    pub include_tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+    pub authors: Vec<String>,
+    pub post_types: Vec<String>,
+}
+
+impl ParsedQuery {
+    pub fn parse(raw: &str) -> Self {
+        let mut query = ParsedQuery::default();
+
+        for (token, quoted) in tokenize(raw).into_iter().take(MAX_TOKENS) {
+            let token: String = token.chars().take(MAX_TOKEN_LEN).collect();
+
+            if quoted {
+                query.phrases.push(token);
+                continue;
+            }
+
+            if let Some(tag) = token.strip_prefix('-') {
+                push_tag(&mut query.exclude_tags, tag);
+            } else if let Some(tag) = token.strip_prefix('#') {
+                push_tag(&mut query.include_tags, tag);
+            } else if let Some((key, value)) = token.split_once(':') {
+                match key.to_lowercase().as_str() {
+                    "tag" => push_tag(&mut query.include_tags, value),
+                    "author" => push_non_empty(&mut query.authors, value.to_string()),
+                    "type" => push_non_empty(&mut query.post_types, value.to_lowercase()),
+                    // not a filter we know, search for it as written
+                    _ => query.terms.push(token),
+                }
+            } else if let Some(prefix) = token.strip_suffix('*') {
+                push_non_empty(&mut query.prefixes, prefix.to_string());
+            } else {
+                query.terms.push(token);
+            }
+        }
+
+        query
+    }
+
+    /// Whether there is anything for the `$text` index to match
+    pub fn has_text(&self) -> bool {
+        !self.terms.is_empty() || !self.phrases.is_empty()
+    }
+
+    /// The `$search` string for a `$text` query, phrases stay quoted
+    pub fn text_search(&self) -> String {
+        let mut parts: Vec<String> = self.terms
+            .iter()
+            .map(|t| t.replace('"', ""))
+            .collect();
+        parts.extend(self.phrases.iter().map(|p| format!("\"{}\"", p.replace('"', ""))));
+        parts.join(" ")
+    }
+
+    /// Case-insensitive regex matching any searched word, phrase or prefix, used for highlighting
+    pub fn highlighter(&self) -> Option<Regex> {
+        let mut alternatives: Vec<String> = self.terms
+            .iter()
+            .chain(self.phrases.iter())
+            .map(|t| regex::escape(t))
+            .collect();
+        alternatives.extend(self.prefixes.iter().map(|p| format!(r"\b{}\w*", regex::escape(p))));
+
+        if alternatives.is_empty() {
+            return None;
+        }
+
+        RegexBuilder::new(&alternatives.join("|")).case_insensitive(true).build().ok()
+    }
+}
+
+/// Splits on whitespace, keeping `"quoted phrases"` together. The bool is true for phrases.
+fn tokenize(raw: &str) -> Vec<(String, bool)> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in raw.chars() {
+        match c {
+            '"' => {
+                let token = current.trim().to_string();
+                if !token.is_empty() {
+                    tokens.push((token, in_quotes));
+                }
+                current.clear();
+                in_quotes = !in_quotes;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push((current.clone(), false));
+                    current.clear();
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    // an unclosed quote still counts as a phrase
+    let token = current.trim().to_string();
+    if !token.is_empty() {
+        tokens.push((token, in_quotes));
+    }
+
+    tokens
+}
+
+fn push_non_empty(list: &mut Vec<String>, value: String) {
+    if !value.is_empty() {
+        list.push(value);
+    }
+}
+
+fn push_tag(list: &mut Vec<String>, raw: &str) {
+    if let Some(tag) = normalize_tag(raw) {
+        list.push(tag);
+    }
+}
+
+/// Cuts a snippet of about `radius` characters either side of the first match and wraps every
+/// match in `<mark>`. The rest of the text is HTML escaped so the snippet is safe to render.
+pub fn highlight(text: &str, matcher: &Regex, radius: usize) -> Option<String> {
+    let first = matcher.find(text)?;
+
+    // widen to `radius` chars each side, on char boundaries
+    let start = text[..first.start()]
+        .char_indices()
+        .rev()
+        .nth(radius.saturating_sub(1))
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let end = text[first.end()..]
+        .char_indices()
+        .nth(radius)
+        .map(|(i, _)| first.end() + i)
+        .unwrap_or(text.len());
+    let window = &text[start..end];
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+
+    let mut last = 0;
+    for m in matcher.find_iter(window) {
+        out.push_str(&escape_html(&window[last..m.start()]));
+        out.push_str("<mark>");
+        out.push_str(&escape_html(m.as_str()));
+        out.push_str("</mark>");
+        last = m.end();
+    }
+    out.push_str(&escape_html(&window[last..]));
+
+    if end < text.len() {
+        out.push('…');
+    }
+
+    Some(out)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(raw: &str) -> Regex {
+        ParsedQuery::parse(raw).highlighter().unwrap()
+    }
+
+    #[test]
+    fn keeps_quoted_phrases_together() {
+        assert_eq!(
+            tokenize(r#"cat "black  dog" fox"#),
+            vec![("cat".to_string(), false), ("black  dog".to_string(), true), ("fox".to_string(), false)]
+        );
+        // a quote right after a word still starts a phrase
+        assert_eq!(
+            tokenize(r#"cat"black dog""#),
+            vec![("cat".to_string(), false), ("black dog".to_string(), true)]
+        );
+        // empty quotes are dropped
+        assert_eq!(tokenize(r#""" "  ""#), vec![]);
+    }
+
+    #[test]
+    fn unclosed_quote_is_a_phrase() {
+        let query = ParsedQuery::parse(r#"cat "black dog"#);
+        assert_eq!(query.terms, vec!["cat"]);
+        assert_eq!(query.phrases, vec!["black dog"]);
+        assert_eq!(query.text_search(), r#"cat "black dog""#);
+    }
+
+    #[test]
+    fn quoted_syntax_is_searched_as_written() {
+        let query = ParsedQuery::parse(r#""-nsfw tag:oc""#);
+        assert_eq!(query.phrases, vec!["-nsfw tag:oc"]);
+        assert!(query.exclude_tags.is_empty());
+        assert!(query.include_tags.is_empty());
+    }
+
+    #[test]
+    fn parses_filters() {
+        let query = ParsedQuery::parse("-NSFW #Digital_Art tag:OC Tag:wip author:Alice type:Comic");
+        assert_eq!(query.exclude_tags, vec!["nsfw"]);
+        assert_eq!(query.include_tags, vec!["digital_art", "oc", "wip"]);
+        assert_eq!(query.authors, vec!["Alice"]);
+        assert_eq!(query.post_types, vec!["comic"]);
+        assert!(!query.has_text());
+    }
+
+    #[test]
+    fn drops_empty_filters() {
+        let query = ParsedQuery::parse("- # tag: author: type: *");
+        assert!(query.exclude_tags.is_empty());
+        assert!(query.include_tags.is_empty());
+        assert!(query.authors.is_empty());
+        assert!(query.post_types.is_empty());
+        assert!(query.prefixes.is_empty());
+        assert!(query.terms.is_empty());
+    }
+
+    #[test]
+    fn unknown_filters_are_terms() {
+        let query = ParsedQuery::parse("ratio:16:9");
+        assert_eq!(query.terms, vec!["ratio:16:9"]);
+    }
+
+    #[test]
+    fn parses_prefixes() {
+        let query = ParsedQuery::parse("draw* cat");
+        assert_eq!(query.prefixes, vec!["draw"]);
+        assert_eq!(query.terms, vec!["cat"]);
+        // prefixes are not part of the $text search
+        assert_eq!(query.text_search(), "cat");
+
+        let snippet = highlight("Drawings of a cat", &matcher("draw* cat"), 40).unwrap();
+        assert_eq!(snippet, "<mark>Drawings</mark> of a <mark>cat</mark>");
+    }
+
+    #[test]
+    fn caps_tokens() {
+        let raw = (0..MAX_TOKENS + 10).map(|i| format!("w{}", i)).collect::<Vec<_>>().join(" ");
+        let query = ParsedQuery::parse(&raw);
+        assert_eq!(query.terms.len(), MAX_TOKENS);
+        assert_eq!(query.terms.last().unwrap(), &format!("w{}", MAX_TOKENS - 1));
+
+        // counted in chars, not bytes
+        let long = "é".repeat(MAX_TOKEN_LEN * 2);
+        let query = ParsedQuery::parse(&format!("{} \"{}\"", long, long));
+        assert_eq!(query.terms[0].chars().count(), MAX_TOKEN_LEN);
+        assert_eq!(query.phrases[0].chars().count(), MAX_TOKEN_LEN);
+    }
+
+    #[test]
+    fn highlight_cuts_on_char_boundaries() {
+        let text = "ààààà cat ààààà";
+        let snippet = highlight(text, &matcher("cat"), 3).unwrap();
+        assert_eq!(snippet, "…àà <mark>cat</mark> àà…");
+
+        // multibyte right at the match, and a match at each end of the text
+        let snippet = highlight("日本語の猫", &matcher("日本"), 2).unwrap();
+        assert_eq!(snippet, "<mark>日本</mark>語の…");
+        let snippet = highlight("日本語の猫", &matcher("猫"), 2).unwrap();
+        assert_eq!(snippet, "…語の<mark>猫</mark>");
+        let snippet = highlight("ÉCOLE école", &matcher("école"), 40).unwrap();
+        assert_eq!(snippet, "<mark>ÉCOLE</mark> <mark>école</mark>");
+    }
+
+    #[test]
+    fn highlight_escapes_around_marks() {
+        let snippet = highlight("a < b & cat<script>", &matcher("cat"), 40).unwrap();
+        assert_eq!(snippet, "a &lt; b &amp; <mark>cat</mark>&lt;script&gt;");
+
+        // the matched text is escaped too
+        let snippet = highlight("x <b>&y z", &matcher(r#""<b>&y""#), 40).unwrap();
+        assert_eq!(snippet, "x <mark>&lt;b&gt;&amp;y</mark> z");
+    }
+
+    #[test]
+    fn highlight_needs_a_match() {
+        assert!(highlight("no cats here", &matcher("dog"), 40).is_none());
+        assert!(ParsedQuery::parse("-nsfw author:alice").highlighter().is_none());
+    }
+}
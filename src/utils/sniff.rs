@@ -0,0 +1,454 @@
+use crate::services::storage::{ ALLOWED_IMAGE_TYPES, ALLOWED_VIDEO_TYPES };
+use crate::utils::error::AppError;
+
+// markup a browser would happily render if it ever sniffed the file as html
+const MARKUP_MARKERS: &[&[u8]] = &[b"<html", b"<script", b"<!doctype", b"<?php", b"<svg", b"<iframe", b"<body"];
+const MARKUP_SCAN_BYTES: usize = 1024;
+
+// ftyp major brands of plain mp4, heic and quicktime share the box but aren't mp4
+const MP4_BRANDS: &[&[u8]] = &[b"isom", b"iso2", b"iso4", b"iso5", b"iso6", b"mp41", b"mp42", b"avc1", b"M4V ", b"dash", b"mmp4"];
+
+/// Works out what an upload really is from its first bytes
+pub fn sniff_media_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" && MP4_BRANDS.contains(&&bytes[8..12]) {
+        Some("video/mp4")
+    } else if bytes.starts_with(&[0x1a, 0x45, 0xdf, 0xa3]) && is_webm(bytes) {
+        Some("video/webm")
+    } else {
+        None
+    }
+}
+
+/// File extension we store an allowed type under
+pub fn extension_for(content_type: &str) -> Option<&'static str> {
+    match content_type {
+        "image/png" => Some("png"),
+        "image/jpeg" => Some("jpg"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        "video/mp4" => Some("mp4"),
+        "video/webm" => Some("webm"),
+        _ => None,
+    }
+}
+
+/// Checks an upload against the type the client claimed and returns the real one, with how
+/// many of the bytes to keep. Files whose bytes don't match, or that carry a second file
+/// inside, are rejected.
+pub fn validate_upload(declared: &str, bytes: &[u8]) -> Result<(&'static str, usize), AppError> {
+    let sniffed = sniff_media_type(bytes).ok_or(
+        AppError::BadRequest("File content is not a supported image or video".into())
+    )?;
+
+    if normalize_declared(declared) != sniffed {
+        return Err(AppError::BadRequest(format!("File content is {} but it was sent as {}", sniffed, declared)));
+    }
+
+    Ok((sniffed, content_len(sniffed, bytes)?))
+}
+
+/// Like `validate_upload` when the client sent no usable type, only images and videos we host get through
+pub fn validate_untyped_upload(bytes: &[u8], allowed: &[&str]) -> Result<(&'static str, usize), AppError> {
+    let sniffed = sniff_media_type(bytes)
+        .filter(|t| allowed.contains(t))
+        .ok_or(AppError::BadRequest(format!("File must be one of {}", allowed.join(", "))))?;
+
+    Ok((sniffed, content_len(sniffed, bytes)?))
+}
+
+/// `validate_upload` for when only the start of a file is at hand. Trailing data can't be
+/// seen from there, so only the type and markup are checked.
+pub fn validate_upload_start(declared: &str, head: &[u8]) -> Result<&'static str, AppError> {
+    let sniffed = sniff_media_type(head).ok_or(
+        AppError::BadRequest("File content is not a supported image or video".into())
+    )?;
+
+    if normalize_declared(declared) != sniffed {
+        return Err(AppError::BadRequest(format!("File content is {} but it was sent as {}", sniffed, declared)));
+    }
+
+    if has_markup(head) {
+        return Err(AppError::BadRequest("File contains more than one kind of content".into()));
+    }
+
+    Ok(sniffed)
+}
+
+/// The type we store uploads sent as `content_type` under
+pub fn canonical_type(content_type: &str) -> &str {
+    normalize_declared(content_type)
+}
+
+pub fn is_allowed_type(content_type: &str) -> bool {
+    let content_type = normalize_declared(content_type);
+    ALLOWED_IMAGE_TYPES.contains(&content_type) || ALLOWED_VIDEO_TYPES.contains(&content_type)
+}
+
+// older clients send the non standard names
+fn normalize_declared(content_type: &str) -> &str {
+    match content_type {
+        "image/jpg" | "image/pjpeg" => "image/jpeg",
+        other => other,
+    }
+}
+
+fn has_markup(bytes: &[u8]) -> bool {
+    let head = bytes[..bytes.len().min(MARKUP_SCAN_BYTES)].to_ascii_lowercase();
+    MARKUP_MARKERS.iter().any(|marker| head.windows(marker.len()).any(|w| w == *marker))
+}
+
+// how much of the file is the file itself. data riding along past the end of a png, webp
+// or mp4 is refused, but jpegs and gifs commonly carry some (motion photos, gain maps,
+// padding) so theirs is cut off instead and never served
+fn content_len(content_type: &str, bytes: &[u8]) -> Result<usize, AppError> {
+    let polyglot = || AppError::BadRequest("File contains more than one kind of content".into());
+    if has_markup(bytes) {
+        return Err(polyglot());
+    }
+
+    let end = match content_type {
+        "image/jpeg" => return jpeg_end(bytes).ok_or_else(polyglot),
+        "image/gif" => return gif_end(bytes).ok_or_else(polyglot),
+        "image/png" => png_end(bytes),
+        "image/webp" => riff_end(bytes),
+        "video/mp4" => mp4_end(bytes),
+        // webm segments are often written with an unknown size, so there is no end to check
+        _ => Some(bytes.len()),
+    };
+    end.filter(|end| *end == bytes.len()).ok_or_else(polyglot)
+}
+
+fn png_end(bytes: &[u8]) -> Option<usize> {
+    let mut pos = 8;
+    loop {
+        let len = u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let kind = bytes.get(pos + 4..pos + 8)?;
+        pos = pos.checked_add(12 + len)?;
+        if kind == b"IEND" {
+            return Some(pos);
+        }
+    }
+}
+
+fn jpeg_end(bytes: &[u8]) -> Option<usize> {
+    let mut pos = 2;
+    loop {
+        if *bytes.get(pos)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        match marker {
+            0xd9 => {
+                break;
+            }
+            // fill byte before a marker
+            0xff => {
+                pos += 1;
+                continue;
+            }
+            0x01 | 0xd0..=0xd7 => {
+                pos += 2;
+                continue;
+            }
+            _ => {}
+        }
+
+        let len = u16::from_be_bytes([*bytes.get(pos + 2)?, *bytes.get(pos + 3)?]) as usize;
+        pos += 2 + len;
+
+        // scan data runs until the next marker that isn't a stuffed byte or a restart
+        if marker == 0xda {
+            while *bytes.get(pos)? != 0xff || matches!(*bytes.get(pos + 1)?, 0x00 | 0xd0..=0xd7) {
+                pos += 1;
+            }
+        }
+    }
+
+    Some(pos + 2)
+}
+
+fn gif_end(bytes: &[u8]) -> Option<usize> {
+    // header, logical screen descriptor and the optional global color table
+    let flags = *bytes.get(10)?;
+    let mut pos = 13;
+    if flags & 0x80 != 0 {
+        pos += 3 * (1 << ((flags & 0x07) + 1));
+    }
+
+    loop {
+        match *bytes.get(pos)? {
+            0x3b => {
+                return Some(pos + 1);
+            }
+            // extension: label, then sub blocks
+            0x21 => {
+                pos = skip_gif_sub_blocks(bytes, pos + 2)?;
+            }
+            // image: descriptor, optional local color table, lzw min code size, then sub blocks
+            0x2c => {
+                let flags = *bytes.get(pos + 9)?;
+                pos += 10;
+                if flags & 0x80 != 0 {
+                    pos += 3 * (1 << ((flags & 0x07) + 1));
+                }
+                pos = skip_gif_sub_blocks(bytes, pos + 1)?;
+            }
+            _ => {
+                return None;
+            }
+        }
+    }
+}
+
+fn skip_gif_sub_blocks(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *bytes.get(pos)? as usize;
+        pos += 1 + len;
+        if len == 0 {
+            return Some(pos);
+        }
+    }
+}
+
+fn riff_end(bytes: &[u8]) -> Option<usize> {
+    let size = u32::from_le_bytes(bytes.get(4..8)?.try_into().ok()?) as usize;
+    let end = 8 + size;
+    // odd sized files are padded by one byte, which some encoders leave off
+    if size & 1 == 1 && bytes.len() == end + 1 { Some(end + 1) } else { Some(end) }
+}
+
+fn mp4_end(bytes: &[u8]) -> Option<usize> {
+    // top level boxes have to tile the file exactly
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let size = u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?) as u64;
+        let size = match size {
+            0 => (bytes.len() - pos) as u64, // runs to the end of the file
+            1 => u64::from_be_bytes(bytes.get(pos + 8..pos + 16)?.try_into().ok()?),
+            _ => size,
+        };
+        if size < 8 {
+            return None;
+        }
+        pos = pos.checked_add(usize::try_from(size).ok()?)?;
+    }
+    Some(pos)
+}
+
+fn is_webm(bytes: &[u8]) -> bool {
+    // the doctype sits in the ebml header, which is tiny
+    let head = &bytes[..bytes.len().min(64)];
+    head.windows(4).any(|w| w == b"webm")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png() -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        bytes.extend_from_slice(&[0, 0, 0, 13]);
+        bytes.extend_from_slice(b"IHDR");
+        bytes.extend_from_slice(&[0; 13 + 4]);
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        bytes.extend_from_slice(b"IEND");
+        bytes.extend_from_slice(&[0; 4]);
+        bytes
+    }
+
+    fn jpeg() -> Vec<u8> {
+        let mut bytes = vec![0xff, 0xd8];
+        // app0 segment
+        bytes.extend_from_slice(&[0xff, 0xe0, 0x00, 0x10]);
+        bytes.extend_from_slice(b"JFIF\0");
+        bytes.extend_from_slice(&[0; 9]);
+        // start of scan, then scan data with a stuffed byte and a restart marker
+        bytes.extend_from_slice(&[0xff, 0xda, 0x00, 0x08, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&[0x12, 0xff, 0x00, 0x34, 0xff, 0xd0, 0x56]);
+        bytes.extend_from_slice(&[0xff, 0xd9]);
+        bytes
+    }
+
+    fn gif() -> Vec<u8> {
+        let mut bytes = b"GIF89a".to_vec();
+        // 1x1, no global color table
+        bytes.extend_from_slice(&[1, 0, 1, 0, 0x00, 0, 0]);
+        // graphic control extension
+        bytes.extend_from_slice(&[0x21, 0xf9, 4, 0, 0, 0, 0, 0]);
+        // image descriptor, lzw min code size, one data block
+        bytes.extend_from_slice(&[0x2c, 0, 0, 0, 0, 1, 0, 1, 0, 0x00]);
+        bytes.extend_from_slice(&[2, 2, 0x4c, 0x01, 0]);
+        bytes.push(0x3b);
+        bytes
+    }
+
+    fn webp() -> Vec<u8> {
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&12u32.to_le_bytes());
+        bytes.extend_from_slice(b"WEBPVP8L");
+        bytes.extend_from_slice(&[0; 4]);
+        bytes
+    }
+
+    fn mp4() -> Vec<u8> {
+        let mut bytes = 16u32.to_be_bytes().to_vec();
+        bytes.extend_from_slice(b"ftypisom");
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&12u32.to_be_bytes());
+        bytes.extend_from_slice(b"mdat");
+        bytes.extend_from_slice(&[1, 2, 3, 4]);
+        bytes
+    }
+
+    fn webm() -> Vec<u8> {
+        let mut bytes = vec![0x1a, 0x45, 0xdf, 0xa3, 0x9f, 0x42, 0x82, 0x84];
+        bytes.extend_from_slice(b"webm");
+        bytes.extend_from_slice(&[0; 16]);
+        bytes
+    }
+
+    fn fixtures() -> Vec<(&'static str, Vec<u8>)> {
+        vec![
+            ("image/png", png()),
+            ("image/jpeg", jpeg()),
+            ("image/gif", gif()),
+            ("image/webp", webp()),
+            ("video/mp4", mp4()),
+            ("video/webm", webm())
+        ]
+    }
+
+    #[test]
+    fn sniffs_every_allowed_type() {
+        for (content_type, bytes) in fixtures() {
+            assert_eq!(sniff_media_type(&bytes), Some(content_type));
+            assert!(extension_for(content_type).is_some());
+        }
+    }
+
+    #[test]
+    fn unknown_bytes_are_not_sniffed() {
+        assert_eq!(sniff_media_type(b""), None);
+        assert_eq!(sniff_media_type(b"just some text"), None);
+        assert_eq!(sniff_media_type(b"RIFF\0\0\0\0WAVE"), None);
+        // heic shares the ftyp box with mp4
+        assert_eq!(sniff_media_type(b"\0\0\0\x18ftypheic\0\0\0\0"), None);
+        // matroska that isn't webm
+        assert_eq!(sniff_media_type(&[0x1a, 0x45, 0xdf, 0xa3, 0x84, 0x42, 0x82, 0x88]), None);
+    }
+
+    #[test]
+    fn valid_files_are_kept_whole() {
+        for (content_type, bytes) in fixtures() {
+            assert_eq!(validate_upload(content_type, &bytes).unwrap(), (content_type, bytes.len()));
+        }
+    }
+
+    #[test]
+    fn declared_type_must_match() {
+        assert!(validate_upload("image/png", &jpeg()).is_err());
+        assert!(validate_upload("video/mp4", &png()).is_err());
+        // older names for jpeg still count
+        assert!(validate_upload("image/jpg", &jpeg()).is_ok());
+        assert!(validate_upload("image/pjpeg", &jpeg()).is_ok());
+    }
+
+    #[test]
+    fn untyped_uploads_are_limited_to_allowed_types() {
+        assert_eq!(validate_untyped_upload(&png(), &["image/png"]).unwrap().0, "image/png");
+        assert!(validate_untyped_upload(&mp4(), &["image/png", "image/jpeg"]).is_err());
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        for (content_type, bytes) in fixtures() {
+            if content_type == "video/webm" {
+                continue;
+            }
+            let truncated = &bytes[..bytes.len() - 3];
+            assert!(validate_upload(content_type, truncated).is_err(), "{} was accepted", content_type);
+        }
+    }
+
+    #[test]
+    fn jpeg_trailers_are_cut_off() {
+        let image = jpeg();
+
+        // a second jpeg, like the gain map or depth map of multi picture files
+        let mut mpf = image.clone();
+        mpf.extend_from_slice(&jpeg());
+        assert_eq!(validate_upload("image/jpeg", &mpf).unwrap().1, image.len());
+
+        // a motion photo's video
+        let mut motion = image.clone();
+        motion.extend_from_slice(&mp4());
+        assert_eq!(validate_upload("image/jpeg", &motion).unwrap().1, image.len());
+
+        let mut padded = image.clone();
+        padded.extend_from_slice(&[0; 32]);
+        assert_eq!(validate_upload("image/jpeg", &padded).unwrap().1, image.len());
+    }
+
+    #[test]
+    fn gif_trailers_are_cut_off() {
+        let image = gif();
+        let mut padded = image.clone();
+        padded.extend_from_slice(&[0; 7]);
+        padded.extend_from_slice(b"junk");
+        assert_eq!(validate_upload("image/gif", &padded).unwrap().1, image.len());
+    }
+
+    #[test]
+    fn other_trailers_are_rejected() {
+        for (content_type, bytes) in [("image/png", png()), ("image/webp", webp()), ("video/mp4", mp4())] {
+            let mut trailed = bytes.clone();
+            trailed.extend_from_slice(b"PK\x03\x04 a zip riding along");
+            assert!(validate_upload(content_type, &trailed).is_err(), "{} was accepted", content_type);
+        }
+    }
+
+    #[test]
+    fn odd_riff_padding_is_optional() {
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&13u32.to_le_bytes());
+        bytes.extend_from_slice(b"WEBPVP8L");
+        bytes.extend_from_slice(&[0; 5]);
+        assert!(validate_upload("image/webp", &bytes).is_ok());
+        bytes.push(0);
+        assert!(validate_upload("image/webp", &bytes).is_ok());
+    }
+
+    #[test]
+    fn markup_polyglots_are_rejected() {
+        // markup in a comment segment a browser could sniff as html
+        let image = jpeg();
+        let mut polyglot = image[..2].to_vec();
+        polyglot.extend_from_slice(&[0xff, 0xfe, 0x00, 0x1a]);
+        polyglot.extend_from_slice(b"<script>alert(1)</script>");
+        polyglot.extend_from_slice(&image[2..]);
+        assert!(validate_upload("image/jpeg", &polyglot).is_err());
+
+        let mut gif = gif();
+        gif.splice(13..13, [0x21, 0xfe, 9].iter().copied().chain(*b"<HTML>hi!").chain([0]));
+        assert!(validate_upload("image/gif", &gif).is_err());
+    }
+
+    #[test]
+    fn start_checks_see_type_and_markup_only() {
+        let image = png();
+        assert_eq!(validate_upload_start("image/png", &image[..16]).unwrap(), "image/png");
+        assert!(validate_upload_start("image/jpeg", &image[..16]).is_err());
+
+        let mut marked = image[..16].to_vec();
+        marked.extend_from_slice(b"<svg onload=x>");
+        assert!(validate_upload_start("image/png", &marked).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::HashSet;
+
+pub const MAX_TAG_LEN: usize = 50;
+
+/// Turns user input into the canonical tag spelling: lowercase, words joined by `_`, only
+/// letters, digits and `_-.()`. Leading `#`/`-` are dropped since search uses them as syntax.
+pub fn normalize_tag(raw: &str) -> Option<String> {
+    let joined = raw
+        .trim()
+        .trim_start_matches(['#', '-'])
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_");
+
+    let tag: String = joined
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '(' | ')'))
+        .take(MAX_TAG_LEN)
+        .collect();
+    let tag = tag.trim_matches('_');
+
+    (!tag.is_empty()).then(|| tag.to_string())
+}
+
+/// Reads tags sent either as a JSON array or a comma separated list, normalized and deduped
+pub fn parse_tag_list(raw: &str) -> Vec<String> {
+    let items: Vec<String> = serde_json
+        ::from_str::<Vec<String>>(raw)
+        .unwrap_or_else(|_| raw.split(',').map(String::from).collect());
+
+    normalize_tags(&items)
+}
+
+/// Normalizes every tag, dropping empty ones and duplicates while keeping the order
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.iter()
+        .filter_map(|t| normalize_tag(t))
+        .filter(|t| seen.insert(t.clone()))
+        .collect()
+}
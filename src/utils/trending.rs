@@ -0,0 +1,63 @@
+use bson::{ doc, Document };
+use chrono::{ DateTime, Duration, Utc };
+use serde::Deserialize;
+
+// how much each kind of interaction counts towards a post's engagement
+const LIKE_WEIGHT: f64 = 1.0;
+const DISLIKE_WEIGHT: f64 = 1.0;
+const COMMENT_WEIGHT: f64 = 2.0;
+const VIEW_WEIGHT: f64 = 0.05;
+
+// how fast trending scores fall off with age, higher means faster
+const GRAVITY: f64 = 1.5;
+
+// posts older than this drop out of trending, their engagement keeps being updated
+pub const SCORING_WINDOW_DAYS: i64 = 30;
+
+/// Raw engagement of a post as an aggregation expression over `like_count`, `dislike_count`,
+/// `views` and a looked up `comment_count`. Used for the `day`/`week`/`month`/`all` windows.
+pub fn engagement_expr() -> Document {
+    doc! {
+        "$add": [
+            { "$multiply": [{ "$ifNull": ["$like_count", 0] }, LIKE_WEIGHT] },
+            { "$multiply": [{ "$ifNull": ["$dislike_count", 0] }, -DISLIKE_WEIGHT] },
+            { "$multiply": ["$comment_count", COMMENT_WEIGHT] },
+            { "$multiply": [{ "$ifNull": ["$views", 0] }, VIEW_WEIGHT] }
+        ]
+    }
+}
+
+/// `engagement` decayed by age in hours at `now`, so fresh posts can outrank older ones.
+pub fn trending_expr(now: DateTime<Utc>) -> Document {
+    let age_hours = doc! {
+        "$divide": [{ "$max": [{ "$subtract": [bson::DateTime::from_chrono(now), "$created_at"] }, 0] }, 3_600_000.0]
+    };
+    doc! { "$divide": ["$engagement", { "$pow": [{ "$add": [age_hours, 2.0] }, GRAVITY] }] }
+}
+
+/// Which slice of posts `/api/posts/popular` ranks.
+///
+/// `trending` ranks every post by its decayed score, the other windows only look at
+/// posts created inside the window and rank them by raw engagement.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PopularWindow {
+    #[default]
+    Trending,
+    Day,
+    Week,
+    Month,
+    All,
+}
+
+impl PopularWindow {
+    /// Oldest creation time a post can have to be inside the window, `None` when unbounded.
+    pub fn cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            PopularWindow::Day => Some(now - Duration::days(1)),
+            PopularWindow::Week => Some(now - Duration::weeks(1)),
+            PopularWindow::Month => Some(now - Duration::days(30)),
+            PopularWindow::Trending | PopularWindow::All => None,
+        }
+    }
+}
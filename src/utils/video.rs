@@ -0,0 +1,426 @@
+use std::env;
+use std::time::Duration;
+
+use tokio::process::Command;
+use uuid::Uuid;
+
+use crate::utils::error::AppError;
+
+// ffmpeg gets this long to pull one frame before we give up on a poster
+const POSTER_TIMEOUT_SECS: u64 = 30;
+
+/// What the container says about a video
+#[derive(Debug, Clone, Copy)]
+pub struct VideoInfo {
+    pub width: u32,
+    pub height: u32,
+    pub duration_secs: Option<f32>, // live recorded webm often leaves it out
+}
+
+/// Reads duration and size from an MP4 or WebM container. Videos whose container doesn't
+/// parse, or that have no video track, are rejected.
+pub fn probe_video(content_type: &str, bytes: &[u8]) -> Result<VideoInfo, AppError> {
+    let info = match content_type {
+        "video/mp4" => probe_mp4(bytes),
+        "video/webm" => probe_webm(bytes),
+        _ => None,
+    };
+    info
+        .filter(|info| info.width > 0 && info.height > 0)
+        .ok_or(AppError::BadRequest(format!("Video could not be read as {}", content_type)))
+}
+
+/// Grabs a frame about a second in as PNG using `FFMPEG_PATH` (default `ffmpeg`).
+/// `None` when ffmpeg isn't around or can't decode it, the video is still fine without a poster.
+pub async fn extract_poster(bytes: &[u8], info: &VideoInfo) -> Option<Vec<u8>> {
+    let ffmpeg = env::var("FFMPEG_PATH").unwrap_or_else(|_| "ffmpeg".to_string());
+    // mp4s with the index at the end can't be read from a pipe, so it goes through a file
+    let input = env::temp_dir().join(format!("inkvault-poster-{}", Uuid::new_v4().simple()));
+    if let Err(e) = tokio::fs::write(&input, bytes).await {
+        log::warn!("Failed to write video for poster extraction: {:?}", e);
+        return None;
+    }
+
+    // the first frame is often black, a second in is usually something
+    let at = match info.duration_secs {
+        Some(duration) if duration > 2.0 => 1.0,
+        _ => 0.0,
+    };
+    let output = Command::new(&ffmpeg)
+        .args(["-v", "error", "-ss", &at.to_string(), "-i"])
+        .arg(&input)
+        .args(["-frames:v", "1", "-f", "image2pipe", "-vcodec", "png", "-"])
+        .kill_on_drop(true)
+        .output();
+    let result = tokio::time::timeout(Duration::from_secs(POSTER_TIMEOUT_SECS), output).await;
+    tokio::fs::remove_file(&input).await.ok();
+
+    match result {
+        Ok(Ok(output)) if output.status.success() && !output.stdout.is_empty() => Some(output.stdout),
+        Ok(Ok(output)) => {
+            log::warn!("ffmpeg could not extract a poster: {}", String::from_utf8_lossy(&output.stderr).trim());
+            None
+        }
+        Ok(Err(e)) => {
+            log::warn!("Failed to run {} for a poster: {:?}", ffmpeg, e);
+            None
+        }
+        Err(_) => {
+            log::warn!("ffmpeg took too long to extract a poster");
+            None
+        }
+    }
+}
+
+// ----- mp4 -----
+
+// the boxes directly inside `bytes` as (type, content)
+fn mp4_boxes(bytes: &[u8]) -> Option<Vec<(&[u8], &[u8])>> {
+    let mut boxes = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let size = u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?) as u64;
+        let kind = bytes.get(pos + 4..pos + 8)?;
+        let (header, size) = match size {
+            0 => (8, (bytes.len() - pos) as u64), // runs to the end
+            1 => (16, u64::from_be_bytes(bytes.get(pos + 8..pos + 16)?.try_into().ok()?)),
+            size => (8, size),
+        };
+        let end = pos.checked_add(usize::try_from(size).ok()?)?;
+        if size < header || end > bytes.len() {
+            return None;
+        }
+        boxes.push((kind, &bytes[pos + header as usize..end]));
+        pos = end;
+    }
+    Some(boxes)
+}
+
+fn mp4_child<'a>(bytes: &'a [u8], kind: &[u8]) -> Option<&'a [u8]> {
+    mp4_boxes(bytes)?
+        .into_iter()
+        .find(|(k, _)| *k == kind)
+        .map(|(_, content)| content)
+}
+
+fn be_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn probe_mp4(bytes: &[u8]) -> Option<VideoInfo> {
+    let moov = mp4_child(bytes, b"moov")?;
+
+    // mvhd: version, then times and the timescale the duration is counted in
+    let mvhd = mp4_child(moov, b"mvhd")?;
+    let (timescale, duration) = match mvhd.first()? {
+        0 => (be_u32(mvhd, 12)?, be_u32(mvhd, 16)? as u64),
+        _ => (be_u32(mvhd, 20)?, be_u64(mvhd, 24)?),
+    };
+    let duration_secs = (timescale > 0 && duration > 0 && duration != u64::MAX).then(|| {
+        (duration as f64 / timescale as f64) as f32
+    });
+
+    // the first track whose handler says it's video
+    let (width, height) = mp4_boxes(moov)?
+        .into_iter()
+        .filter(|(kind, _)| *kind == b"trak")
+        .find_map(|(_, trak)| {
+            let mdia = mp4_child(trak, b"mdia")?;
+            if mp4_child(mdia, b"hdlr")?.get(8..12)? != b"vide" {
+                return None;
+            }
+            mp4_track_size(trak, mdia)
+        })?;
+
+    Some(VideoInfo { width, height, duration_secs })
+}
+
+fn mp4_track_size(trak: &[u8], mdia: &[u8]) -> Option<(u32, u32)> {
+    // tkhd ends with width and height as 16.16 fixed point
+    let tkhd = mp4_child(trak, b"tkhd")?;
+    let at = if *tkhd.first()? == 0 { 76 } else { 88 };
+    let (width, height) = (be_u32(tkhd, at)? >> 16, be_u32(tkhd, at + 4)? >> 16);
+    if width > 0 && height > 0 {
+        return Some((width, height));
+    }
+
+    // otherwise the first sample description has them
+    let stbl = mp4_child(mp4_child(mdia, b"minf")?, b"stbl")?;
+    let entry = mp4_child(stbl, b"stsd")?.get(8..)?;
+    let width = u16::from_be_bytes(entry.get(32..34)?.try_into().ok()?) as u32;
+    let height = u16::from_be_bytes(entry.get(34..36)?.try_into().ok()?) as u32;
+    Some((width, height))
+}
+
+// ----- webm -----
+
+const EBML_HEADER: u32 = 0x1a45dfa3;
+const SEGMENT: u32 = 0x18538067;
+const CLUSTER: u32 = 0x1f43b675;
+const INFO: u32 = 0x1549a966;
+const TIMECODE_SCALE: u32 = 0x2ad7b1;
+const DURATION: u32 = 0x4489;
+const TRACKS: u32 = 0x1654ae6b;
+const TRACK_ENTRY: u32 = 0xae;
+const TRACK_TYPE: u32 = 0x83;
+const TRACK_VIDEO: u32 = 0xe0;
+const PIXEL_WIDTH: u32 = 0xb0;
+const PIXEL_HEIGHT: u32 = 0xba;
+
+// one EBML variable length integer, as (value, length). ids keep their marker bit, sizes don't
+fn ebml_vint(bytes: &[u8], pos: usize, keep_marker: bool) -> Option<(u64, usize)> {
+    let first = *bytes.get(pos)?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    let mut value = if keep_marker { first as u64 } else { (first as u64) & (0xff >> len) };
+    for i in 1..len {
+        value = (value << 8) | *bytes.get(pos + i)? as u64;
+    }
+    Some((value, len))
+}
+
+// the elements directly inside `bytes` as (id, content). an element of unknown size runs to the end
+fn ebml_elements(bytes: &[u8]) -> Option<Vec<(u32, &[u8])>> {
+    let mut elements = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let (id, id_len) = ebml_vint(bytes, pos, true)?;
+        let (size, size_len) = ebml_vint(bytes, pos + id_len, false)?;
+        let start = pos + id_len + size_len;
+        let unknown = size == (1u64 << (7 * size_len)) - 1;
+        let end = if unknown { bytes.len() } else { start.checked_add(usize::try_from(size).ok()?)? };
+        if end > bytes.len() {
+            return None;
+        }
+        elements.push((id as u32, &bytes[start..end]));
+        // clusters are the frames, everything we need comes before them
+        if id as u32 == CLUSTER || unknown {
+            break;
+        }
+        pos = end;
+    }
+    Some(elements)
+}
+
+fn ebml_uint(bytes: &[u8]) -> Option<u64> {
+    if bytes.is_empty() || bytes.len() > 8 {
+        return None;
+    }
+    Some(bytes.iter().fold(0u64, |value, b| (value << 8) | *b as u64))
+}
+
+fn ebml_float(bytes: &[u8]) -> Option<f64> {
+    match bytes.len() {
+        4 => Some(f32::from_be_bytes(bytes.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(bytes.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+fn probe_webm(bytes: &[u8]) -> Option<VideoInfo> {
+    let top = ebml_elements(bytes)?;
+    if top.first()?.0 != EBML_HEADER {
+        return None;
+    }
+    let segment = top
+        .iter()
+        .find(|(id, _)| *id == SEGMENT)
+        .map(|(_, content)| *content)?;
+    let children = ebml_elements(segment)?;
+
+    // the duration is in timecode units, nanoseconds times the scale
+    let duration_secs = children
+        .iter()
+        .find(|(id, _)| *id == INFO)
+        .and_then(|(_, info)| {
+            let fields = ebml_elements(info)?;
+            let scale = fields
+                .iter()
+                .find(|(id, _)| *id == TIMECODE_SCALE)
+                .and_then(|(_, v)| ebml_uint(v))
+                .unwrap_or(1_000_000);
+            let duration = fields
+                .iter()
+                .find(|(id, _)| *id == DURATION)
+                .and_then(|(_, v)| ebml_float(v))?;
+            let secs = (duration * scale as f64) / 1_000_000_000.0;
+            (secs.is_finite() && secs > 0.0).then_some(secs as f32)
+        });
+
+    let tracks = children
+        .iter()
+        .find(|(id, _)| *id == TRACKS)
+        .map(|(_, content)| *content)?;
+    let (width, height) = ebml_elements(tracks)?
+        .into_iter()
+        .filter(|(id, _)| *id == TRACK_ENTRY)
+        .find_map(|(_, entry)| {
+            let fields = ebml_elements(entry)?;
+            let is_video = fields
+                .iter()
+                .any(|(id, v)| *id == TRACK_TYPE && ebml_uint(v) == Some(1));
+            if !is_video {
+                return None;
+            }
+            let video = ebml_elements(fields.iter().find(|(id, _)| *id == TRACK_VIDEO)?.1)?;
+            let dimension = |wanted: u32| {
+                video
+                    .iter()
+                    .find(|(id, _)| *id == wanted)
+                    .and_then(|(_, v)| ebml_uint(v))
+            };
+            Some((dimension(PIXEL_WIDTH)? as u32, dimension(PIXEL_HEIGHT)? as u32))
+        })?;
+
+    Some(VideoInfo { width, height, duration_secs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8], content: &[u8]) -> Vec<u8> {
+        let mut bytes = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(content);
+        bytes
+    }
+
+    // a video track of `width`x`height` lasting `duration` in 1/1000 s, plus an empty mdat
+    fn mp4(width: u32, height: u32, duration: u32, handler: &[u8]) -> Vec<u8> {
+        let mut mvhd = vec![0; 100];
+        mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&duration.to_be_bytes());
+
+        let mut tkhd = vec![0; 84];
+        tkhd[76..80].copy_from_slice(&(width << 16).to_be_bytes());
+        tkhd[80..84].copy_from_slice(&(height << 16).to_be_bytes());
+
+        let mut hdlr = vec![0; 24];
+        hdlr[8..12].copy_from_slice(handler);
+        let mdia = [mp4_box(b"mdhd", &[0; 24]), mp4_box(b"hdlr", &hdlr)].concat();
+        let trak = [mp4_box(b"tkhd", &tkhd), mp4_box(b"mdia", &mdia)].concat();
+        let moov = [mp4_box(b"mvhd", &mvhd), mp4_box(b"trak", &trak)].concat();
+
+        [mp4_box(b"ftyp", b"isom\0\0\0\0"), mp4_box(b"moov", &moov), mp4_box(b"mdat", &[1, 2, 3, 4])].concat()
+    }
+
+    fn ebml(id: u32, content: &[u8]) -> Vec<u8> {
+        let mut bytes: Vec<u8> = id.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+        if content.len() < 0x7f {
+            bytes.push(0x80 | content.len() as u8);
+        } else {
+            bytes.push(0x01);
+            bytes.extend_from_slice(&(content.len() as u64).to_be_bytes()[1..]);
+        }
+        bytes.extend_from_slice(content);
+        bytes
+    }
+
+    // a video track of `width`x`height`, lasting `duration_ms` if given, then the first cluster
+    fn webm(width: u16, height: u16, duration_ms: Option<f64>) -> Vec<u8> {
+        let mut info = ebml(TIMECODE_SCALE, &[0x0f, 0x42, 0x40]);
+        if let Some(duration) = duration_ms {
+            info.extend(ebml(DURATION, &duration.to_be_bytes()));
+        }
+        let video = [ebml(PIXEL_WIDTH, &width.to_be_bytes()), ebml(PIXEL_HEIGHT, &height.to_be_bytes())].concat();
+        let entry = [ebml(TRACK_TYPE, &[1]), ebml(TRACK_VIDEO, &video)].concat();
+        let segment = [
+            ebml(INFO, &info),
+            ebml(TRACKS, &ebml(TRACK_ENTRY, &entry)),
+            ebml(CLUSTER, &[0xe7, 0x81, 0x00]),
+        ].concat();
+
+        [ebml(EBML_HEADER, &ebml(0x4282, b"webm")), ebml(SEGMENT, &segment)].concat()
+    }
+
+    #[test]
+    fn reads_mp4_size_and_duration() {
+        let info = probe_video("video/mp4", &mp4(640, 360, 12_500, b"vide")).unwrap();
+        assert_eq!((info.width, info.height), (640, 360));
+        assert_eq!(info.duration_secs, Some(12.5));
+    }
+
+    #[test]
+    fn reads_mp4_size_from_the_sample_description() {
+        // no size in tkhd, the avc1 entry has it
+        let mut entry = [0u8; 36];
+        entry[32..34].copy_from_slice(&320u16.to_be_bytes());
+        entry[34..36].copy_from_slice(&240u16.to_be_bytes());
+        let stsd = [vec![0; 8], mp4_box(b"avc1", &entry[8..])].concat();
+        let minf = mp4_box(b"stbl", &mp4_box(b"stsd", &stsd));
+
+        let mut hdlr = vec![0; 24];
+        hdlr[8..12].copy_from_slice(b"vide");
+        let mdia = [mp4_box(b"hdlr", &hdlr), mp4_box(b"minf", &minf)].concat();
+        let trak = [mp4_box(b"tkhd", &[0; 84]), mp4_box(b"mdia", &mdia)].concat();
+        let moov = [mp4_box(b"mvhd", &[0; 100]), mp4_box(b"trak", &trak)].concat();
+
+        let info = probe_video("video/mp4", &mp4_box(b"moov", &moov)).unwrap();
+        assert_eq!((info.width, info.height), (320, 240));
+        assert_eq!(info.duration_secs, None);
+    }
+
+    #[test]
+    fn rejects_mp4_without_a_video_track() {
+        assert!(probe_video("video/mp4", &mp4(640, 360, 12_500, b"soun")).is_err());
+    }
+
+    #[test]
+    fn reads_webm_size_and_duration() {
+        let info = probe_video("video/webm", &webm(1280, 720, Some(8_500.0))).unwrap();
+        assert_eq!((info.width, info.height), (1280, 720));
+        assert_eq!(info.duration_secs, Some(8.5));
+    }
+
+    #[test]
+    fn reads_webm_without_a_duration() {
+        let info = probe_video("video/webm", &webm(1280, 720, None)).unwrap();
+        assert_eq!((info.width, info.height), (1280, 720));
+        assert_eq!(info.duration_secs, None);
+    }
+
+    #[test]
+    fn reads_live_webm_with_an_unknown_segment_size() {
+        let bytes = webm(1280, 720, None);
+        // the segment size is the 8 bytes after its id, all ones means unknown
+        let at = bytes.windows(4).position(|w| w == SEGMENT.to_be_bytes()).unwrap() + 4;
+        let mut live = bytes[..at].to_vec();
+        live.push(0xff);
+        live.extend_from_slice(&bytes[at + 8..]);
+
+        let info = probe_video("video/webm", &live).unwrap();
+        assert_eq!((info.width, info.height), (1280, 720));
+    }
+
+    #[test]
+    fn rejects_truncated_videos() {
+        let mp4 = mp4(640, 360, 12_500, b"vide");
+        let webm = webm(1280, 720, Some(8_500.0));
+        for len in [0, 4, 12, mp4.len() / 2, mp4.len() - 1] {
+            assert!(probe_video("video/mp4", &mp4[..len]).is_err(), "mp4 cut at {}", len);
+        }
+        for len in [0, 4, 12, webm.len() / 2] {
+            assert!(probe_video("video/webm", &webm[..len]).is_err(), "webm cut at {}", len);
+        }
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        let garbage: Vec<u8> = (0..512u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        for content_type in ["video/mp4", "video/webm"] {
+            assert!(probe_video(content_type, &garbage).is_err());
+            assert!(probe_video(content_type, b"<html></html>").is_err());
+        }
+        // the right bytes under the wrong type
+        assert!(probe_video("video/webm", &mp4(640, 360, 12_500, b"vide")).is_err());
+        assert!(probe_video("video/mp4", &webm(1280, 720, None)).is_err());
+        assert!(probe_video("video/quicktime", &mp4(640, 360, 12_500, b"vide")).is_err());
+    }
+}